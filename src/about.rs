@@ -0,0 +1,79 @@
+use windows::core::{w, PCWSTR};
+use windows::Win32::Foundation::{HWND, LPARAM, LRESULT, WPARAM};
+use windows::Win32::UI::WindowsAndMessaging::{
+    DefWindowProcW, DestroyWindow, BS_DEFPUSHBUTTON, BS_PUSHBUTTON, IDCANCEL, IDOK, WINDOW_EX_STYLE, WINDOW_STYLE,
+    WM_CLOSE, WM_COMMAND, WS_TABSTOP,
+};
+use tracing::error;
+
+use crate::dialog::{add_control, create_window, run_modal};
+use crate::i18n::{t, t_with};
+
+const REPO_URL: &str = "https://github.com/DeltaFoundry/TouchRelay";
+const CLASS_NAME: PCWSTR = w!("TouchRelayAbout");
+const GITHUB_ID: i32 = 100;
+const WIDTH: i32 = 420;
+const LINE_HEIGHT: i32 = 16;
+
+/// Show version, build and address details in a small window until it is closed
+pub fn show() {
+    let text = about_text();
+    let text_height = text.lines().count() as i32 * LINE_HEIGHT;
+    let Some(window) = create_window(CLASS_NAME, Some(window_proc), &t("about.title"), WIDTH, text_height + 100) else {
+        error!("Failed to open the About window");
+        return;
+    };
+
+    add_control(window, WINDOW_EX_STYLE(0), w!("STATIC"), &text, WINDOW_STYLE(0), (16, 14, WIDTH - 40, text_height), -1);
+    let buttons = [(t("about.github"), BS_PUSHBUTTON, 150, 130, GITHUB_ID), (t("dialog.close"), BS_DEFPUSHBUTTON, 290, 100, IDOK.0)];
+    for (label, style, x, width, id) in buttons {
+        add_control(window, WINDOW_EX_STYLE(0), w!("BUTTON"), &label, WS_TABSTOP | WINDOW_STYLE(style as u32), (x - 14, text_height + 26, width, 26), id);
+    }
+    run_modal(window);
+}
+
+/// Version, build details and the addresses a phone can open, one per line
+fn about_text() -> String {
+    let mut lines = vec![
+        t_with("about.version", &[("version", env!("CARGO_PKG_VERSION"))]),
+        t_with(
+            "about.build",
+            &[
+                ("date", env!("TOUCHRELAY_BUILD_DATE")),
+                ("platform", &format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH)),
+            ],
+        ),
+        t_with("about.protocol", &[("protocol", &crate::handler::PROTOCOL_VERSION.to_string())]),
+        String::new(),
+        t("about.addresses"),
+    ];
+
+    let mut urls = vec![crate::network::local_url()];
+    if !crate::network::is_loopback_only() {
+        urls.extend(crate::network::lan_addresses().into_iter().map(|(_, ip)| crate::network::url_for(ip)));
+    }
+    urls.dedup();
+    lines.extend(urls.into_iter().map(|url| format!("    {}", url)));
+    lines.join("\n")
+}
+
+unsafe extern "system" fn window_proc(window: HWND, message: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    match message {
+        WM_COMMAND => {
+            let id = (wparam.0 & 0xffff) as i32;
+            if id == GITHUB_ID {
+                if let Err(e) = open::that(REPO_URL) {
+                    error!("Failed to open GitHub page: {}", e);
+                }
+            } else if id == IDOK.0 || id == IDCANCEL.0 {
+                let _ = DestroyWindow(window);
+            }
+            LRESULT(0)
+        }
+        WM_CLOSE => {
+            let _ = DestroyWindow(window);
+            LRESULT(0)
+        }
+        _ => DefWindowProcW(window, message, wparam, lparam),
+    }
+}
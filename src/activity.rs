@@ -0,0 +1,75 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::Instant;
+use tracing::{error, info};
+use windows::Win32::Foundation::{LPARAM, LRESULT, WPARAM};
+use windows::Win32::UI::WindowsAndMessaging::{
+    CallNextHookEx, GetMessageW, SetWindowsHookExW, MSG, HC_ACTION, KBDLLHOOKSTRUCT, LLKHF_INJECTED,
+    LLMHF_INJECTED, MSLLHOOKSTRUCT, WH_KEYBOARD_LL, WH_MOUSE_LL,
+};
+
+// Reference point for activity timestamps
+static START: OnceLock<Instant> = OnceLock::new();
+
+// Milliseconds since START of the last physical (non-injected) input, 0 = never
+static LAST_PHYSICAL_INPUT: AtomicU64 = AtomicU64::new(0);
+
+fn now_ms() -> u64 {
+    START.get_or_init(Instant::now).elapsed().as_millis() as u64 + 1
+}
+
+/// Install low-level mouse/keyboard hooks that track local physical input.
+/// Must be called on a thread that runs a message loop (the tray event loop).
+pub fn install_hooks() {
+    if crate::config::get().local_input_grace_ms == 0 {
+        info!("Local input detection disabled");
+        return;
+    }
+
+    unsafe {
+        if let Err(e) = SetWindowsHookExW(WH_MOUSE_LL, Some(mouse_hook), None, 0) {
+            error!("Failed to install mouse hook: {}", e);
+        }
+        if let Err(e) = SetWindowsHookExW(WH_KEYBOARD_LL, Some(keyboard_hook), None, 0) {
+            error!("Failed to install keyboard hook: {}", e);
+        }
+    }
+
+    info!("Local input detection enabled");
+}
+
+/// Install the hooks on a thread of their own, for running without the tray event loop
+pub fn spawn_hook_thread() {
+    std::thread::spawn(|| {
+        install_hooks();
+        // The hooks are called from this thread's message loop
+        let mut message = MSG::default();
+        while unsafe { GetMessageW(&mut message, None, 0, 0) }.as_bool() {}
+    });
+}
+
+/// Check if the physical mouse or keyboard was used within the configured grace period
+pub fn is_local_input_active() -> bool {
+    let last = LAST_PHYSICAL_INPUT.load(Ordering::Relaxed);
+    last != 0 && now_ms() - last < crate::config::get().local_input_grace_ms
+}
+
+unsafe extern "system" fn mouse_hook(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    if code == HC_ACTION as i32 {
+        let info = unsafe { &*(lparam.0 as *const MSLLHOOKSTRUCT) };
+        if info.flags & LLMHF_INJECTED == 0 {
+            LAST_PHYSICAL_INPUT.store(now_ms(), Ordering::Relaxed);
+        }
+    }
+    unsafe { CallNextHookEx(None, code, wparam, lparam) }
+}
+
+unsafe extern "system" fn keyboard_hook(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    if code == HC_ACTION as i32 {
+        let info = unsafe { &*(lparam.0 as *const KBDLLHOOKSTRUCT) };
+        if !info.flags.contains(LLKHF_INJECTED) {
+            LAST_PHYSICAL_INPUT.store(now_ms(), Ordering::Relaxed);
+        }
+    }
+    unsafe { CallNextHookEx(None, code, wparam, lparam) }
+}
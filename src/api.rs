@@ -0,0 +1,312 @@
+use axum::{
+    extract::{Path, Query},
+    http::{header, HeaderMap, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+use crate::auth::constant_time_eq;
+use crate::eventlog::{self, EventId, Level};
+use crate::events::{self, AppEvent};
+use crate::handler::Dropped;
+
+/// Fields accepted by `PATCH /api/settings` (all optional)
+#[derive(Deserialize)]
+pub struct SettingsPatch {
+    start_with_windows: Option<bool>,
+    paused: Option<bool>,
+    idle_timeout_secs: Option<u64>,
+    local_input_grace_ms: Option<u64>,
+    max_clients: Option<usize>,
+    exclusive_control: Option<bool>,
+    require_pairing_approval: Option<bool>,
+}
+
+/// Query parameters of `POST /api/command`
+#[derive(Deserialize)]
+pub struct CommandParams {
+    /// Device whose permissions and profile apply (global settings if omitted or invalid)
+    #[serde(default, deserialize_with = "crate::handler::valid_device_id")]
+    device: Option<String>,
+}
+
+/// Check the `Authorization: Bearer <token>` header against the configured API token
+pub fn authorize(headers: &HeaderMap) -> Result<(), (StatusCode, &'static str)> {
+    let config = crate::config::get();
+    let Some(token) = config.api_token.as_deref() else {
+        return Err((StatusCode::FORBIDDEN, "API disabled (no api_token configured)"));
+    };
+
+    let provided = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or("");
+
+    if constant_time_eq(provided.as_bytes(), token.as_bytes()) {
+        Ok(())
+    } else {
+        warn!("Rejected API request with invalid token");
+        crate::metrics::auth_failed();
+        crate::webhooks::notify("auth_failed", json!({ "method": "api_token" }));
+        eventlog::report(Level::Warning, EventId::AuthFailed, "Rejected API request with invalid token");
+        Err((StatusCode::UNAUTHORIZED, "Invalid API token"))
+    }
+}
+
+/// Build the JSON representation of the current host settings
+fn current_settings() -> serde_json::Value {
+    let config = crate::config::get();
+    json!({
+        "start_with_windows": crate::startup::is_startup_enabled(),
+        "paused": crate::pause::is_paused(),
+        "idle_timeout_secs": config.idle_timeout_secs,
+        "local_input_grace_ms": config.local_input_grace_ms,
+        "max_clients": config.max_clients,
+        "exclusive_control": config.exclusive_control,
+        "require_pairing_approval": config.require_pairing_approval,
+    })
+}
+
+/// GET /api/settings
+pub async fn get_settings(headers: HeaderMap) -> impl IntoResponse {
+    if let Err(rejection) = authorize(&headers) {
+        return rejection.into_response();
+    }
+
+    Json(current_settings()).into_response()
+}
+
+/// PATCH /api/settings
+pub async fn patch_settings(headers: HeaderMap, Json(patch): Json<SettingsPatch>) -> impl IntoResponse {
+    if let Err(rejection) = authorize(&headers) {
+        return rejection.into_response();
+    }
+
+    if let Some(enabled) = patch.start_with_windows {
+        if let Err(e) = crate::startup::set_startup_enabled(enabled) {
+            let message = format!("Failed to update startup: {}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, message).into_response();
+        }
+    }
+
+    if let Some(paused) = patch.paused {
+        crate::pause::set_paused(paused);
+    }
+
+    let result = crate::config::update(|config| {
+        if let Some(value) = patch.idle_timeout_secs {
+            config.idle_timeout_secs = value;
+        }
+        if let Some(value) = patch.local_input_grace_ms {
+            config.local_input_grace_ms = value;
+        }
+        if let Some(value) = patch.max_clients {
+            config.max_clients = value;
+        }
+        if let Some(value) = patch.exclusive_control {
+            config.exclusive_control = value;
+        }
+        if let Some(value) = patch.require_pairing_approval {
+            config.require_pairing_approval = value;
+        }
+    });
+    if let Err(e) = result {
+        return (StatusCode::INTERNAL_SERVER_ERROR, e).into_response();
+    }
+
+    info!("Settings updated via API");
+    events::send(AppEvent::StateChanged);
+    Json(current_settings()).into_response()
+}
+
+/// GET /api/layouts
+pub async fn list_layouts(headers: HeaderMap) -> impl IntoResponse {
+    if let Err(rejection) = authorize(&headers) {
+        return rejection.into_response();
+    }
+
+    Json(json!({ "layouts": crate::layouts::list() })).into_response()
+}
+
+/// POST /api/command with a WebSocket command such as `["k", "Escape"]` as the body
+pub async fn run_command(headers: HeaderMap, Query(params): Query<CommandParams>, Json(command): Json<Value>) -> impl IntoResponse {
+    if let Err(rejection) = authorize(&headers) {
+        return rejection.into_response();
+    }
+
+    let text = command.to_string();
+    info!("Running API command {}", text);
+    match crate::handler::run_once(&text, params.device).await {
+        Ok(None) => StatusCode::NO_CONTENT.into_response(),
+        Ok(Some(reason)) => {
+            info!("API command {} dropped: {:?}", text, reason);
+            let status = match reason {
+                Dropped::NotController => StatusCode::CONFLICT,
+                Dropped::Paused | Dropped::SessionInactive | Dropped::LocalInput => StatusCode::LOCKED,
+            };
+            (status, format!("Command dropped: {:?}", reason)).into_response()
+        }
+        Err(e) => {
+            warn!("API command {} failed: {}", text, e);
+            (StatusCode::BAD_REQUEST, e).into_response()
+        }
+    }
+}
+
+/// POST /api/server/restart (answered before the server goes down)
+pub async fn restart_server(headers: HeaderMap) -> impl IntoResponse {
+    if let Err(rejection) = authorize(&headers) {
+        return rejection.into_response();
+    }
+
+    info!("Server restart requested via API");
+    events::send(AppEvent::RestartServer);
+    StatusCode::ACCEPTED.into_response()
+}
+
+/// GET /api/displays
+pub async fn list_displays(headers: HeaderMap) -> impl IntoResponse {
+    if let Err(rejection) = authorize(&headers) {
+        return rejection.into_response();
+    }
+
+    Json(crate::display::screen_info()).into_response()
+}
+
+/// GET /api/audio/sessions: applications playing sound, with their volume
+pub async fn list_audio_sessions(headers: HeaderMap) -> impl IntoResponse {
+    if let Err(rejection) = authorize(&headers) {
+        return rejection.into_response();
+    }
+
+    match crate::audio::app_volumes() {
+        Ok(sessions) => Json(json!({ "sessions": sessions })).into_response(),
+        Err(e) => {
+            warn!("{}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e).into_response()
+        }
+    }
+}
+
+/// GET /api/audio/devices: output devices, marking the default one
+pub async fn list_audio_devices(headers: HeaderMap) -> impl IntoResponse {
+    if let Err(rejection) = authorize(&headers) {
+        return rejection.into_response();
+    }
+
+    match crate::audio::output_devices() {
+        Ok(devices) => Json(json!({ "devices": devices })).into_response(),
+        Err(e) => {
+            warn!("{}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e).into_response()
+        }
+    }
+}
+
+/// GET /api/layouts/{name}
+pub async fn get_layout(headers: HeaderMap, Path(name): Path<String>) -> impl IntoResponse {
+    if let Err(rejection) = authorize(&headers) {
+        return rejection.into_response();
+    }
+
+    crate::layouts::layout_response(&name)
+}
+
+/// PUT /api/layouts/{name}
+pub async fn put_layout(
+    headers: HeaderMap,
+    Path(name): Path<String>,
+    Json(layout): Json<serde_json::Value>,
+) -> impl IntoResponse {
+    if let Err(rejection) = authorize(&headers) {
+        return rejection.into_response();
+    }
+
+    match crate::layouts::save(&name, &layout) {
+        Ok(()) => Json(layout).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e).into_response(),
+    }
+}
+
+/// DELETE /api/layouts/{name}
+pub async fn delete_layout(headers: HeaderMap, Path(name): Path<String>) -> impl IntoResponse {
+    if let Err(rejection) = authorize(&headers) {
+        return rejection.into_response();
+    }
+
+    match crate::layouts::delete(&name) {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => (StatusCode::NOT_FOUND, "Layout not found").into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e).into_response(),
+    }
+}
+
+/// Body of `POST /api/packs`: `{"path": ...}` or `{"url": ...}` plus the expected checksum
+#[derive(Deserialize)]
+pub struct PackImport {
+    #[serde(flatten)]
+    source: crate::packs::PackSource,
+    sha256: String,
+}
+
+/// Body of `PATCH /api/packs/{name}`
+#[derive(Deserialize)]
+pub struct PackPatch {
+    enabled: bool,
+}
+
+/// GET /api/packs
+pub async fn list_packs(headers: HeaderMap) -> impl IntoResponse {
+    if let Err(rejection) = authorize(&headers) {
+        return rejection.into_response();
+    }
+
+    Json(json!({ "packs": crate::packs::list() })).into_response()
+}
+
+/// POST /api/packs
+pub async fn import_pack(headers: HeaderMap, Json(request): Json<PackImport>) -> impl IntoResponse {
+    if let Err(rejection) = authorize(&headers) {
+        return rejection.into_response();
+    }
+
+    let result = tokio::task::spawn_blocking(move || crate::packs::import(&request.source, &request.sha256)).await;
+    match result {
+        Ok(Ok(pack)) => (StatusCode::CREATED, Json(pack)).into_response(),
+        Ok(Err(e)) => (StatusCode::BAD_REQUEST, e).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+/// PATCH /api/packs/{name}
+pub async fn patch_pack(
+    headers: HeaderMap,
+    Path(name): Path<String>,
+    Json(patch): Json<PackPatch>,
+) -> impl IntoResponse {
+    if let Err(rejection) = authorize(&headers) {
+        return rejection.into_response();
+    }
+
+    match crate::packs::set_enabled(&name, patch.enabled) {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e).into_response(),
+    }
+}
+
+/// DELETE /api/packs/{name}
+pub async fn delete_pack(headers: HeaderMap, Path(name): Path<String>) -> impl IntoResponse {
+    if let Err(rejection) = authorize(&headers) {
+        return rejection.into_response();
+    }
+
+    match crate::packs::remove(&name) {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => (StatusCode::NOT_FOUND, "Pack not found").into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e).into_response(),
+    }
+}
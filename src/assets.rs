@@ -1,9 +1,83 @@
-use axum::{response::IntoResponse, http::{header, StatusCode}};
+use axum::{
+    body::Bytes,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+#[cfg(feature = "gui")]
 use tray_icon::Icon;
 use tracing::{info, warn};
 
+/// Get a short fingerprint of the embedded web assets, used by clients to detect updates
+pub fn asset_version() -> &'static str {
+    static VERSION: OnceLock<String> = OnceLock::new();
+
+    VERSION.get_or_init(|| {
+        let mut hasher = DefaultHasher::new();
+        include_str!("../static/index.html").hash(&mut hasher);
+        include_str!("../static/style.css").hash(&mut hasher);
+        include_str!("../static/app.js").hash(&mut hasher);
+        format!("{:016x}", hasher.finish())
+    })
+}
+
 /// Load tray icon from embedded resources
+#[cfg(feature = "gui")]
 pub fn load_icon() -> Icon {
+    if let Some(icon) = decode_icon(|_, _, _| {}) {
+        return icon;
+    }
+
+    // Fallback: create a simple default icon
+    info!("Using default icon");
+    create_default_icon()
+}
+
+/// Load a desaturated, dimmed variant of the tray icon shown while remote input is paused
+#[cfg(feature = "gui")]
+pub fn load_paused_icon() -> Icon {
+    let grayscale = |pixel: &mut [u8]| {
+        let luma = (pixel[0] as u32 * 30 + pixel[1] as u32 * 59 + pixel[2] as u32 * 11) / 100;
+        let dimmed = (luma / 2) as u8;
+        pixel[0] = dimmed;
+        pixel[1] = dimmed;
+        pixel[2] = dimmed;
+    };
+
+    decode_icon(|pixels, _, _| pixels.chunks_exact_mut(4).for_each(grayscale)).unwrap_or_else(create_default_icon)
+}
+
+/// Load the tray icon with a green dot in the corner, shown while a client is connected
+#[cfg(feature = "gui")]
+pub fn load_connected_icon() -> Icon {
+    let add_dot = |pixels: &mut [u8], width: u32, height: u32| {
+        let radius = width.min(height) as f32 * 0.22;
+        let (center_x, center_y) = (width as f32 - radius - 1.0, height as f32 - radius - 1.0);
+        for y in 0..height {
+            for x in 0..width {
+                let distance = ((x as f32 + 0.5 - center_x).powi(2) + (y as f32 + 0.5 - center_y).powi(2)).sqrt();
+                let color = if distance <= radius {
+                    [0x10, 0xb9, 0x81, 0xff]
+                } else if distance <= radius + 1.5 {
+                    // White ring keeps the dot visible on dark and green backgrounds
+                    [0xff, 0xff, 0xff, 0xff]
+                } else {
+                    continue;
+                };
+                let offset = ((y * width + x) * 4) as usize;
+                pixels[offset..offset + 4].copy_from_slice(&color);
+            }
+        }
+    };
+
+    decode_icon(add_dot).unwrap_or_else(create_default_icon)
+}
+
+/// Decode the embedded icon, applying a transform to its RGBA pixels (given width and height)
+#[cfg(feature = "gui")]
+fn decode_icon(transform: impl Fn(&mut [u8], u32, u32)) -> Option<Icon> {
     // Load embedded icon from binary
     let icon_bytes = include_bytes!("../static/icon.ico");
 
@@ -11,10 +85,13 @@ pub fn load_icon() -> Icon {
         Ok(img) => {
             let rgba = img.to_rgba8();
             let (width, height) = rgba.dimensions();
-            match Icon::from_rgba(rgba.into_raw(), width, height) {
+            let mut pixels = rgba.into_raw();
+            transform(&mut pixels, width, height);
+
+            match Icon::from_rgba(pixels, width, height) {
                 Ok(icon) => {
                     info!("Loaded embedded icon ({}x{})", width, height);
-                    return icon;
+                    return Some(icon);
                 }
                 Err(e) => {
                     warn!("Failed to create icon from embedded image: {}", e);
@@ -26,12 +103,11 @@ pub fn load_icon() -> Icon {
         }
     }
 
-    // Fallback: create a simple default icon
-    info!("Using default icon");
-    create_default_icon()
+    None
 }
 
 /// Create a default gradient icon
+#[cfg(feature = "gui")]
 fn create_default_icon() -> Icon {
     // Create a simple 32x32 icon with a solid color
     let size = 32;
@@ -52,40 +128,210 @@ fn create_default_icon() -> Icon {
     Icon::from_rgba(rgba_data, size, size).expect("Failed to create default icon")
 }
 
-// Static file handlers (embedded at compile time)
+// Static file handlers (embedded at compile time, optionally overridden from disk)
+
+// The HTML, script and stylesheet URLs are not versioned, so browsers must revalidate them
+const REVALIDATE: &str = "no-cache";
+const ICON_MAX_AGE: &str = "public, max-age=86400";
+
+/// Strong ETag of a static file
+fn etag(bytes: &[u8]) -> String {
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    format!("\"{:016x}\"", hasher.finish())
+}
+
+/// ETag of an embedded file, computed once
+fn embedded_etag(cell: &'static OnceLock<String>, bytes: &'static [u8]) -> &'static str {
+    cell.get_or_init(|| etag(bytes))
+}
+
+/// Directory whose files replace the embedded ones: `static_dir` from the config,
+/// or a `static` folder next to the executable
+fn override_dir() -> Option<PathBuf> {
+    if let Some(dir) = &crate::config::get().static_dir {
+        return Some(dir.clone());
+    }
+    let dir = std::env::current_exe().ok()?.parent()?.join("static");
+    dir.is_dir().then_some(dir)
+}
+
+/// Contents and ETag of a static file, preferring a copy in the override directory
+fn load(name: &str, embedded: &'static [u8], embedded_etag: fn() -> &'static str) -> (Bytes, String) {
+    if let Some(dir) = override_dir() {
+        let path = dir.join(name);
+        match std::fs::read(&path) {
+            Ok(bytes) => {
+                let tag = etag(&bytes);
+                return (Bytes::from(bytes), tag);
+            }
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                warn!("Failed to read {}, using the built-in copy: {}", path.display(), e);
+            }
+            Err(_) => {}
+        }
+    }
+    (Bytes::from_static(embedded), embedded_etag().to_string())
+}
+
+/// Check whether the client's cached copy (If-None-Match) is still current
+fn is_fresh(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.split(',').any(|tag| {
+            let tag = tag.trim();
+            tag == "*" || tag.trim_start_matches("W/") == etag
+        }))
+}
+
+/// Serve a static file with caching headers, or 304 if the client already has it
+fn serve(headers: &HeaderMap, content_type: &'static str, cache_control: &'static str, etag: &str, body: Bytes) -> Response {
+    let etag = HeaderValue::from_str(etag).expect("ETag is a valid header value");
+    if is_fresh(headers, etag.to_str().unwrap_or_default()) {
+        return (
+            StatusCode::NOT_MODIFIED,
+            [(header::ETAG, etag), (header::CACHE_CONTROL, HeaderValue::from_static(cache_control))],
+        )
+            .into_response();
+    }
 
-/// Serve index.html
-pub async fn index_handler() -> impl IntoResponse {
     (
         StatusCode::OK,
-        [(header::CONTENT_TYPE, "text/html; charset=utf-8")],
-        include_str!("../static/index.html")
+        [
+            (header::CONTENT_TYPE, HeaderValue::from_static(content_type)),
+            (header::ETAG, etag),
+            (header::CACHE_CONTROL, HeaderValue::from_static(cache_control)),
+        ],
+        body,
     )
+        .into_response()
+}
+
+/// Compute the ETags of the embedded files up front and report any override directory
+pub fn init_etags() {
+    css_etag();
+    js_etag();
+    icon_etag();
+    manifest_etag();
+    service_worker_etag();
+
+    if let Some(dir) = override_dir() {
+        info!("Web files in {} replace the built-in ones", dir.display());
+    }
+}
+
+fn css_etag() -> &'static str {
+    static ETAG: OnceLock<String> = OnceLock::new();
+    embedded_etag(&ETAG, include_bytes!("../static/style.css"))
+}
+
+fn js_etag() -> &'static str {
+    static ETAG: OnceLock<String> = OnceLock::new();
+    embedded_etag(&ETAG, include_bytes!("../static/app.js"))
+}
+
+fn icon_etag() -> &'static str {
+    static ETAG: OnceLock<String> = OnceLock::new();
+    embedded_etag(&ETAG, include_bytes!("../static/icon.ico"))
+}
+
+fn manifest_etag() -> &'static str {
+    static ETAG: OnceLock<String> = OnceLock::new();
+    embedded_etag(&ETAG, include_bytes!("../static/manifest.json"))
+}
+
+fn service_worker_etag() -> &'static str {
+    static ETAG: OnceLock<String> = OnceLock::new();
+    embedded_etag(&ETAG, include_bytes!("../static/sw.js"))
+}
+
+/// PNG rendering of the embedded icon at the given size, for the web app manifest
+fn app_icon(size: u32) -> Result<Vec<u8>, String> {
+    let icon = image::load_from_memory(include_bytes!("../static/icon.ico"))
+        .map_err(|e| format!("Failed to load embedded icon: {}", e))?;
+    let mut png = Vec::new();
+    icon.resize(size, size, image::imageops::FilterType::Lanczos3)
+        .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+        .map_err(|e| format!("Failed to encode icon: {}", e))?;
+    Ok(png)
+}
+
+/// Serve a cached PNG app icon with its ETag
+fn app_icon_response(headers: &HeaderMap, cell: &'static OnceLock<Option<(Bytes, String)>>, size: u32) -> Response {
+    let icon = cell.get_or_init(|| match app_icon(size) {
+        Ok(png) => {
+            let tag = etag(&png);
+            Some((Bytes::from(png), tag))
+        }
+        Err(e) => {
+            warn!("{}", e);
+            None
+        }
+    });
+
+    match icon {
+        Some((png, etag)) => serve(headers, "image/png", ICON_MAX_AGE, etag, png.clone()),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+/// Serve index.html with the configured theme, pointing relative URLs at the base path when one is configured
+pub async fn index_handler(headers: HeaderMap) -> impl IntoResponse {
+    let embedded = include_bytes!("../static/index.html");
+    let (html, _) = load("index.html", embedded, || "");
+    let html = String::from_utf8_lossy(&html);
+    let base = crate::proxy::base_path();
+    let html = if base.is_empty() {
+        html.into_owned()
+    } else {
+        html.replacen("<head>", &format!("<head>\n    <base href=\"{}/\">", base), 1)
+    };
+    let html = crate::theme::inject(&html);
+
+    // Depends on the base path and theme, so hashed per request (it is small)
+    let etag = etag(html.as_bytes());
+    serve(&headers, "text/html; charset=utf-8", REVALIDATE, &etag, Bytes::from(html))
 }
 
 /// Serve style.css
-pub async fn css_handler() -> impl IntoResponse {
-    (
-        StatusCode::OK,
-        [(header::CONTENT_TYPE, "text/css; charset=utf-8")],
-        include_str!("../static/style.css")
-    )
+pub async fn css_handler(headers: HeaderMap) -> impl IntoResponse {
+    let (body, etag) = load("style.css", include_bytes!("../static/style.css"), css_etag);
+    serve(&headers, "text/css; charset=utf-8", REVALIDATE, &etag, body)
 }
 
 /// Serve app.js
-pub async fn js_handler() -> impl IntoResponse {
-    (
-        StatusCode::OK,
-        [(header::CONTENT_TYPE, "application/javascript; charset=utf-8")],
-        include_str!("../static/app.js")
-    )
+pub async fn js_handler(headers: HeaderMap) -> impl IntoResponse {
+    let (body, etag) = load("app.js", include_bytes!("../static/app.js"), js_etag);
+    serve(&headers, "application/javascript; charset=utf-8", REVALIDATE, &etag, body)
 }
 
 /// Serve icon.ico
-pub async fn icon_handler() -> impl IntoResponse {
-    (
-        StatusCode::OK,
-        [(header::CONTENT_TYPE, "image/x-icon")],
-        include_bytes!("../static/icon.ico").as_slice()
-    )
+pub async fn icon_handler(headers: HeaderMap) -> impl IntoResponse {
+    let (body, etag) = load("icon.ico", include_bytes!("../static/icon.ico"), icon_etag);
+    serve(&headers, "image/x-icon", ICON_MAX_AGE, &etag, body)
+}
+
+/// Serve manifest.json, which lets phones install the web interface to the home screen
+pub async fn manifest_handler(headers: HeaderMap) -> impl IntoResponse {
+    let (body, etag) = load("manifest.json", include_bytes!("../static/manifest.json"), manifest_etag);
+    serve(&headers, "application/manifest+json", REVALIDATE, &etag, body)
+}
+
+/// Serve sw.js, the service worker caching the web interface
+pub async fn service_worker_handler(headers: HeaderMap) -> impl IntoResponse {
+    let (body, etag) = load("sw.js", include_bytes!("../static/sw.js"), service_worker_etag);
+    serve(&headers, "application/javascript; charset=utf-8", REVALIDATE, &etag, body)
+}
+
+/// Serve icon-192.png
+pub async fn icon_192_handler(headers: HeaderMap) -> impl IntoResponse {
+    static ICON: OnceLock<Option<(Bytes, String)>> = OnceLock::new();
+    app_icon_response(&headers, &ICON, 192)
+}
+
+/// Serve icon-512.png
+pub async fn icon_512_handler(headers: HeaderMap) -> impl IntoResponse {
+    static ICON: OnceLock<Option<(Bytes, String)>> = OnceLock::new();
+    app_icon_response(&headers, &ICON, 512)
 }
@@ -0,0 +1,329 @@
+use serde::Serialize;
+use std::path::Path;
+use tracing::info;
+use windows::core::{Interface, HSTRING, PCWSTR, PWSTR};
+use windows::Win32::Devices::FunctionDiscovery::PKEY_Device_FriendlyName;
+use windows::Win32::Foundation::CloseHandle;
+use windows::Win32::Media::Audio::Endpoints::IAudioEndpointVolume;
+use windows::Win32::Media::Audio::{
+    eCapture, eCommunications, eConsole, eMultimedia, eRender, AudioSessionStateExpired, IAudioSessionControl2,
+    IAudioSessionManager2, IMMDevice, IMMDeviceEnumerator, ISimpleAudioVolume, MMDeviceEnumerator, DEVICE_STATE_ACTIVE,
+};
+use windows::Win32::System::Com::StructuredStorage::{PropVariantClear, PropVariantToStringAlloc};
+use windows::Win32::System::Com::{
+    CoCreateInstance, CoInitializeEx, CoTaskMemFree, CLSCTX_ALL, CLSCTX_INPROC_SERVER, COINIT_MULTITHREADED, STGM_READ,
+};
+use policy_config::{IPolicyConfig, POLICY_CONFIG_CLIENT};
+use windows::Win32::System::Threading::{
+    OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION,
+};
+
+/// Undocumented interface behind "Set as Default Device" in the Sound control panel; Windows
+/// has no public API for changing the default device
+mod policy_config {
+    #![allow(non_snake_case)]
+
+    use std::ffi::c_void;
+    use windows::core::{GUID, HRESULT, PCWSTR};
+    use windows::Win32::Media::Audio::ERole;
+
+    /// Class implementing `IPolicyConfig`
+    pub const POLICY_CONFIG_CLIENT: GUID = GUID::from_u128(0x870af99c_171d_4f9e_af0d_e63df40c2bc9);
+
+    /// Only `SetDefaultEndpoint` is used
+    #[windows_core::interface("f8679f50-850a-41cf-9c72-430f290290c8")]
+    pub unsafe trait IPolicyConfig: windows_core::IUnknown {
+        fn GetMixFormat(&self, device: PCWSTR, format: *mut *mut c_void) -> HRESULT;
+        fn GetDeviceFormat(&self, device: PCWSTR, default: i32, format: *mut *mut c_void) -> HRESULT;
+        fn ResetDeviceFormat(&self, device: PCWSTR) -> HRESULT;
+        fn SetDeviceFormat(&self, device: PCWSTR, endpoint_format: *mut c_void, mix_format: *mut c_void) -> HRESULT;
+        fn GetProcessingPeriod(&self, device: PCWSTR, default: i32, default_period: *mut i64, min_period: *mut i64) -> HRESULT;
+        fn SetProcessingPeriod(&self, device: PCWSTR, period: *mut i64) -> HRESULT;
+        fn GetShareMode(&self, device: PCWSTR, mode: *mut c_void) -> HRESULT;
+        fn SetShareMode(&self, device: PCWSTR, mode: *mut c_void) -> HRESULT;
+        fn GetPropertyValue(&self, device: PCWSTR, key: *const c_void, value: *mut c_void) -> HRESULT;
+        fn SetPropertyValue(&self, device: PCWSTR, key: *const c_void, value: *mut c_void) -> HRESULT;
+        pub fn SetDefaultEndpoint(&self, device: PCWSTR, role: ERole) -> HRESULT;
+        fn SetEndpointVisibility(&self, device: PCWSTR, visible: i32) -> HRESULT;
+    }
+}
+
+/// An application playing sound, as listed by `GET /api/audio/sessions` and `["mixer", "list"]`
+#[derive(Debug, Clone, Serialize)]
+pub struct AppVolume {
+    /// Process ID, which the mixer commands take (0 for Windows' system sounds)
+    pub pid: u32,
+    pub name: String,
+    /// Volume in percent, relative to the device volume
+    pub volume: u8,
+    pub muted: bool,
+}
+
+/// An audio output device, as listed by `GET /api/audio/devices` and `["audio_device", "list"]`
+#[derive(Debug, Clone, Serialize)]
+pub struct OutputDevice {
+    /// Windows' endpoint ID, which `["audio_device", "set", id]` takes
+    pub id: String,
+    /// Name as in the Sound settings, e.g. `Speakers (Realtek(R) Audio)`
+    pub name: String,
+    pub default: bool,
+}
+
+fn device_enumerator() -> Result<IMMDeviceEnumerator, String> {
+    unsafe {
+        // Fails harmlessly if this thread already uses COM
+        let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
+        CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_INPROC_SERVER).map_err(|e| format!("Failed to open the audio devices: {}", e))
+    }
+}
+
+/// The default output device (speakers, headphones, ...)
+fn default_output() -> Result<IMMDevice, String> {
+    unsafe {
+        device_enumerator()?
+            .GetDefaultAudioEndpoint(eRender, eMultimedia)
+            .map_err(|e| format!("No audio output device: {}", e))
+    }
+}
+
+/// The default microphone for calls, which video call apps use
+fn default_input() -> Result<IMMDevice, String> {
+    unsafe {
+        device_enumerator()?
+            .GetDefaultAudioEndpoint(eCapture, eCommunications)
+            .map_err(|e| format!("No microphone: {}", e))
+    }
+}
+
+/// Take a string Windows allocated for us
+unsafe fn take_string(text: PWSTR) -> Option<String> {
+    let string = unsafe { text.to_string() }.ok();
+    unsafe { CoTaskMemFree(Some(text.0 as *const _)) };
+    string
+}
+
+fn device_id(device: &IMMDevice) -> Option<String> {
+    unsafe { device.GetId().ok().and_then(|id| take_string(id)) }
+}
+
+fn device_name(device: &IMMDevice) -> Option<String> {
+    unsafe {
+        let store = device.OpenPropertyStore(STGM_READ).ok()?;
+        let mut value = store.GetValue(&PKEY_Device_FriendlyName).ok()?;
+        let text = PropVariantToStringAlloc(&value);
+        let _ = PropVariantClear(&mut value);
+        take_string(text.ok()?)
+    }
+}
+
+/// Output devices that are plugged in and enabled
+pub fn output_devices() -> Result<Vec<OutputDevice>, String> {
+    let default_id = default_output().ok().and_then(|device| device_id(&device));
+    let collection = unsafe { device_enumerator()?.EnumAudioEndpoints(eRender, DEVICE_STATE_ACTIVE) }
+        .map_err(|e| format!("Failed to list audio devices: {}", e))?;
+    let count = unsafe { collection.GetCount() }.map_err(|e| format!("Failed to list audio devices: {}", e))?;
+
+    let mut devices = Vec::new();
+    for i in 0..count {
+        let Ok(device) = (unsafe { collection.Item(i) }) else {
+            continue;
+        };
+        let Some(id) = device_id(&device) else {
+            continue;
+        };
+        devices.push(OutputDevice {
+            name: device_name(&device).unwrap_or_else(|| id.clone()),
+            default: default_id.as_ref() == Some(&id),
+            id,
+        });
+    }
+    Ok(devices)
+}
+
+/// Make the output device with endpoint ID `id` the default for all sound, including calls
+pub fn set_default_output(id: &str) -> Result<(), String> {
+    let device = output_devices()?
+        .into_iter()
+        .find(|device| device.id == id)
+        .ok_or_else(|| format!("Unknown audio output device: {}", id))?;
+    let id = HSTRING::from(id);
+    unsafe {
+        let policy: IPolicyConfig = CoCreateInstance(&POLICY_CONFIG_CLIENT, None, CLSCTX_ALL)
+            .map_err(|e| format!("Failed to open the audio settings: {}", e))?;
+        for role in [eConsole, eMultimedia, eCommunications] {
+            policy
+                .SetDefaultEndpoint(PCWSTR(id.as_ptr()), role)
+                .ok()
+                .map_err(|e| format!("Failed to switch to {}: {}", device.name, e))?;
+        }
+    }
+    info!("Switched audio output to {}", device.name);
+    Ok(())
+}
+
+/// Volume control of an audio device
+fn endpoint_volume(device: IMMDevice) -> Result<IAudioEndpointVolume, String> {
+    unsafe {
+        device
+            .Activate(CLSCTX_ALL, None)
+            .map_err(|e| format!("Failed to open the volume control: {}", e))
+    }
+}
+
+/// Volume of the default output device in percent, and whether it is muted
+pub fn volume() -> Result<(u8, bool), String> {
+    let endpoint = endpoint_volume(default_output()?)?;
+    unsafe {
+        let level = endpoint.GetMasterVolumeLevelScalar().map_err(|e| format!("Failed to read the volume: {}", e))?;
+        let muted = endpoint.GetMute().map_err(|e| format!("Failed to read the volume: {}", e))?;
+        Ok((to_percent(level), muted.as_bool()))
+    }
+}
+
+/// Set the default output device's volume in percent (0-100)
+pub fn set_volume(percent: u8) -> Result<(), String> {
+    unsafe {
+        endpoint_volume(default_output()?)?
+            .SetMasterVolumeLevelScalar(to_scalar(percent), std::ptr::null())
+            .map_err(|e| format!("Failed to set the volume: {}", e))
+    }
+}
+
+/// Whether the default microphone is muted
+pub fn is_mic_muted() -> Result<bool, String> {
+    let endpoint = endpoint_volume(default_input()?)?;
+    unsafe { endpoint.GetMute() }
+        .map(|muted| muted.as_bool())
+        .map_err(|e| format!("Failed to read the microphone state: {}", e))
+}
+
+/// Mute the default microphone, or unmute it, returning whether it is now muted
+pub fn toggle_mic() -> Result<bool, String> {
+    let endpoint = endpoint_volume(default_input()?)?;
+    unsafe {
+        let muted = !endpoint.GetMute().map_err(|e| format!("Failed to read the microphone state: {}", e))?.as_bool();
+        endpoint
+            .SetMute(muted, std::ptr::null())
+            .map_err(|e| format!("Failed to mute the microphone: {}", e))?;
+        info!("Microphone {}", if muted { "muted" } else { "unmuted" });
+        Ok(muted)
+    }
+}
+
+fn to_percent(level: f32) -> u8 {
+    (level.clamp(0.0, 1.0) * 100.0).round() as u8
+}
+
+fn to_scalar(percent: u8) -> f32 {
+    f32::from(percent.min(100)) / 100.0
+}
+
+/// Audio sessions on the default output device that have not ended.
+/// An application can have several (e.g. one per browser tab playing sound).
+fn sessions() -> Result<Vec<IAudioSessionControl2>, String> {
+    unsafe {
+        let manager: IAudioSessionManager2 = default_output()?
+            .Activate(CLSCTX_ALL, None)
+            .map_err(|e| format!("Failed to open the volume mixer: {}", e))?;
+        let list = manager
+            .GetSessionEnumerator()
+            .map_err(|e| format!("Failed to list audio sessions: {}", e))?;
+        let count = list.GetCount().map_err(|e| format!("Failed to list audio sessions: {}", e))?;
+
+        let mut sessions = Vec::new();
+        for i in 0..count {
+            let Ok(session) = list.GetSession(i).and_then(|session| session.cast::<IAudioSessionControl2>()) else {
+                continue;
+            };
+            if session.GetState().is_ok_and(|state| state != AudioSessionStateExpired) {
+                sessions.push(session);
+            }
+        }
+        Ok(sessions)
+    }
+}
+
+/// Name shown for a session: its own display name, else the program's file name
+fn session_name(session: &IAudioSessionControl2, pid: u32) -> String {
+    unsafe {
+        if session.IsSystemSoundsSession().is_ok() {
+            return "System sounds".to_string();
+        }
+        if let Some(text) = session.GetDisplayName().ok().and_then(|name| take_string(name)) {
+            // Resource references such as "@%SystemRoot%\..." are not readable names
+            if !text.is_empty() && !text.starts_with('@') {
+                return text;
+            }
+        }
+    }
+    process_name(pid).unwrap_or_else(|| format!("Process {}", pid))
+}
+
+/// File name of a process's executable without the extension, e.g. `Spotify`
+fn process_name(pid: u32) -> Option<String> {
+    unsafe {
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+        let mut buffer = [0u16; 1024];
+        let mut len = buffer.len() as u32;
+        let result = QueryFullProcessImageNameW(process, PROCESS_NAME_WIN32, PWSTR(buffer.as_mut_ptr()), &mut len);
+        let _ = CloseHandle(process);
+        result.ok()?;
+        let path = String::from_utf16_lossy(&buffer[..len as usize]);
+        Some(Path::new(&path).file_stem()?.to_string_lossy().into_owned())
+    }
+}
+
+/// Applications with audio sessions on the default output device, one entry per process
+pub fn app_volumes() -> Result<Vec<AppVolume>, String> {
+    let mut apps: Vec<AppVolume> = Vec::new();
+    for session in sessions()? {
+        let Ok(pid) = (unsafe { session.GetProcessId() }) else {
+            continue;
+        };
+        if apps.iter().any(|app| app.pid == pid) {
+            continue;
+        }
+        let Ok(volume) = session.cast::<ISimpleAudioVolume>() else {
+            continue;
+        };
+        let (level, muted) = unsafe { (volume.GetMasterVolume(), volume.GetMute()) };
+        apps.push(AppVolume {
+            pid,
+            name: session_name(&session, pid),
+            volume: to_percent(level.unwrap_or(1.0)),
+            muted: muted.is_ok_and(|muted| muted.as_bool()),
+        });
+    }
+    apps.sort_by_key(|app| app.name.to_lowercase());
+    Ok(apps)
+}
+
+/// Run `change` on the volume of every audio session of process `pid`
+fn change_app(pid: u32, change: impl Fn(&ISimpleAudioVolume) -> windows::core::Result<()>) -> Result<(), String> {
+    let mut found = false;
+    for session in sessions()? {
+        if unsafe { session.GetProcessId() }.ok() != Some(pid) {
+            continue;
+        }
+        let volume = session
+            .cast::<ISimpleAudioVolume>()
+            .map_err(|e| format!("Failed to open the volume of process {}: {}", pid, e))?;
+        change(&volume).map_err(|e| format!("Failed to change the volume of process {}: {}", pid, e))?;
+        found = true;
+    }
+    if found {
+        Ok(())
+    } else {
+        Err(format!("Process {} is not playing sound", pid))
+    }
+}
+
+/// Set an application's volume in percent (0-100)
+pub fn set_app_volume(pid: u32, percent: u8) -> Result<(), String> {
+    change_app(pid, |volume| unsafe { volume.SetMasterVolume(to_scalar(percent), std::ptr::null()) })
+}
+
+/// Mute or unmute an application
+pub fn set_app_muted(pid: u32, muted: bool) -> Result<(), String> {
+    change_app(pid, |volume| unsafe { volume.SetMute(muted, std::ptr::null()) })
+}
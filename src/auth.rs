@@ -0,0 +1,57 @@
+use axum::{
+    extract::{ConnectInfo, Request},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use std::net::SocketAddr;
+use tracing::warn;
+
+use crate::eventlog::{self, EventId, Level};
+
+/// Compare secrets without leaking their common prefix length through timing
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Middleware requiring HTTP Basic credentials when `[basic_auth]` is configured
+pub async fn basic_auth(request: Request, next: Next) -> Response {
+    let config = crate::config::get();
+    let Some(credentials) = &config.basic_auth else {
+        return next.run(request).await;
+    };
+
+    let expected = format!("{}:{}", credentials.username, credentials.password);
+    let provided = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Basic "))
+        .and_then(|encoded| STANDARD.decode(encoded.trim()).ok())
+        .unwrap_or_default();
+
+    if constant_time_eq(&provided, expected.as_bytes()) {
+        return next.run(request).await;
+    }
+
+    // Browsers send a first request without credentials, only log actual failures
+    if !provided.is_empty() {
+        let client = request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| crate::proxy::client_addr(*addr, request.headers()).ip().to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        warn!("Rejected invalid credentials from {}", client);
+        crate::metrics::auth_failed();
+        crate::webhooks::notify("auth_failed", serde_json::json!({ "ip": client, "method": "basic" }));
+        eventlog::report(Level::Warning, EventId::AuthFailed, &format!("Rejected invalid credentials from {}", client));
+    }
+
+    (
+        StatusCode::UNAUTHORIZED,
+        [(header::WWW_AUTHENTICATE, "Basic realm=\"TouchRelay\", charset=\"UTF-8\"")],
+        "Authentication required",
+    )
+        .into_response()
+}
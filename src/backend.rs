@@ -0,0 +1,78 @@
+use enigo::{Axis, Button, Coordinate, Direction, Enigo, Key, Keyboard, Mouse};
+use tracing::info;
+
+/// Destination for injected input: the real desktop, or a mock during soak tests
+pub trait InputBackend: Send {
+    fn move_mouse(&mut self, dx: i32, dy: i32) -> Result<(), String>;
+    fn button(&mut self, button: Button, direction: Direction) -> Result<(), String>;
+    fn scroll(&mut self, amount: i32, axis: Axis) -> Result<(), String>;
+    fn text(&mut self, text: &str) -> Result<(), String>;
+    fn key(&mut self, key: Key, direction: Direction) -> Result<(), String>;
+}
+
+impl InputBackend for Enigo {
+    fn move_mouse(&mut self, dx: i32, dy: i32) -> Result<(), String> {
+        Mouse::move_mouse(self, dx, dy, Coordinate::Rel).map_err(injection_failed)
+    }
+
+    fn button(&mut self, button: Button, direction: Direction) -> Result<(), String> {
+        Mouse::button(self, button, direction).map_err(injection_failed)
+    }
+
+    fn scroll(&mut self, amount: i32, axis: Axis) -> Result<(), String> {
+        Mouse::scroll(self, amount, axis).map_err(injection_failed)
+    }
+
+    fn text(&mut self, text: &str) -> Result<(), String> {
+        Keyboard::text(self, text).map_err(injection_failed)
+    }
+
+    fn key(&mut self, key: Key, direction: Direction) -> Result<(), String> {
+        Keyboard::key(self, key, direction).map_err(injection_failed)
+    }
+}
+
+/// Report a failed injection, which may mean an administrator window is in front
+fn injection_failed(e: enigo::InputError) -> String {
+    crate::elevation::note_failed_input();
+    e.to_string()
+}
+
+/// Backend for Remote Desktop sessions, where relative mouse moves sent with
+/// SendInput are not applied reliably; moves are made absolute instead
+struct RemoteSessionBackend(Enigo);
+
+impl InputBackend for RemoteSessionBackend {
+    fn move_mouse(&mut self, dx: i32, dy: i32) -> Result<(), String> {
+        let (x, y) = Mouse::location(&self.0).map_err(|e| e.to_string())?;
+        Mouse::move_mouse(&mut self.0, x + dx, y + dy, Coordinate::Abs).map_err(injection_failed)
+    }
+
+    fn button(&mut self, button: Button, direction: Direction) -> Result<(), String> {
+        InputBackend::button(&mut self.0, button, direction)
+    }
+
+    fn scroll(&mut self, amount: i32, axis: Axis) -> Result<(), String> {
+        InputBackend::scroll(&mut self.0, amount, axis)
+    }
+
+    fn text(&mut self, text: &str) -> Result<(), String> {
+        InputBackend::text(&mut self.0, text)
+    }
+
+    fn key(&mut self, key: Key, direction: Direction) -> Result<(), String> {
+        InputBackend::key(&mut self.0, key, direction)
+    }
+}
+
+/// Create the backend that injects into the local desktop
+pub fn desktop() -> Result<Box<dyn InputBackend>, String> {
+    let enigo = Enigo::new(&enigo::Settings::default())
+        .map_err(|e| format!("Failed to create Enigo instance: {}", e))?;
+
+    if crate::winsession::is_remote() {
+        info!("Remote Desktop session detected, using absolute pointer moves");
+        return Ok(Box::new(RemoteSessionBackend(enigo)));
+    }
+    Ok(Box::new(enigo))
+}
@@ -0,0 +1,238 @@
+use tracing::{debug, info};
+use windows::core::{w, BOOL, BSTR};
+use windows::Win32::Devices::Display::{
+    DestroyPhysicalMonitors, GetMonitorBrightness, GetNumberOfPhysicalMonitorsFromHMONITOR,
+    GetPhysicalMonitorsFromHMONITOR, SetMonitorBrightness, PHYSICAL_MONITOR,
+};
+use windows::Win32::Foundation::{LPARAM, RECT};
+use windows::Win32::Graphics::Gdi::{EnumDisplayMonitors, HDC, HMONITOR};
+use windows::Win32::System::Com::{
+    CoCreateInstance, CoInitializeEx, CoSetProxyBlanket, CoTaskMemFree, CLSCTX_INPROC_SERVER, COINIT_MULTITHREADED, EOAC_NONE,
+    RPC_C_AUTHN_LEVEL_CALL, RPC_C_IMP_LEVEL_IMPERSONATE,
+};
+use windows::Win32::System::Rpc::{RPC_C_AUTHN_WINNT, RPC_C_AUTHZ_NONE};
+use windows::Win32::System::Variant::{VariantClear, VariantToStringAlloc, VariantToUInt32, VARIANT, VT_I4};
+use windows::Win32::System::Wmi::{
+    IWbemClassObject, IWbemLocator, IWbemServices, WbemLocator, WBEM_FLAG_FORWARD_ONLY, WBEM_FLAG_RETURN_IMMEDIATELY,
+    WBEM_GENERIC_FLAG_TYPE, WBEM_INFINITE,
+};
+
+/// What a client asked for with `["brightness", ...]`
+pub enum Change {
+    /// Set the level in percent
+    To(u8),
+    /// Change the level by this many percentage points
+    By(i32),
+    /// Only report the level
+    Get,
+}
+
+/// Apply a brightness change, returning the new level. Slow with external monitors, since
+/// DDC/CI talks to each one over its cable.
+pub fn apply(change: Change) -> Result<u8, String> {
+    match change {
+        Change::To(level) => set_brightness(level).map(|()| level.min(100)),
+        Change::By(delta) => {
+            let level = (i32::from(brightness()?) + delta).clamp(0, 100) as u8;
+            set_brightness(level)?;
+            Ok(level)
+        }
+        Change::Get => brightness(),
+    }
+}
+
+/// Brightness in percent of the first screen that reports one: the built-in panel, else an
+/// external monitor
+fn brightness() -> Result<u8, String> {
+    if let Ok(level) = panel_brightness() {
+        return Ok(level);
+    }
+    let monitors = physical_monitors();
+    let level = monitors.iter().find_map(|monitor| monitor_brightness(monitor).map(|(level, _, _)| level));
+    release(&monitors);
+    level.ok_or_else(|| "No screen with adjustable brightness (external monitors need DDC/CI turned on)".to_string())
+}
+
+/// Set every screen's brightness in percent (0-100), failing only if none could be changed
+fn set_brightness(percent: u8) -> Result<(), String> {
+    let percent = percent.min(100);
+    let mut changed = match set_panel_brightness(percent) {
+        Ok(()) => true,
+        Err(e) => {
+            debug!("{}", e);
+            false
+        }
+    };
+    let monitors = physical_monitors();
+    for monitor in &monitors {
+        changed |= set_monitor_brightness(monitor, percent);
+    }
+    release(&monitors);
+
+    if !changed {
+        return Err("No screen with adjustable brightness (external monitors need DDC/CI turned on)".to_string());
+    }
+    info!("Brightness set to {}%", percent);
+    Ok(())
+}
+
+// Laptop and tablet panels are controlled through WMI
+
+fn wmi() -> Result<IWbemServices, String> {
+    unsafe {
+        // Fails harmlessly if this thread already uses COM
+        let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
+        let locator: IWbemLocator = CoCreateInstance(&WbemLocator, None, CLSCTX_INPROC_SERVER)
+            .map_err(|e| format!("Failed to open WMI: {}", e))?;
+        let services = locator
+            .ConnectServer(&BSTR::from("ROOT\\WMI"), &BSTR::new(), &BSTR::new(), &BSTR::new(), 0, &BSTR::new(), None)
+            .map_err(|e| format!("Failed to open WMI: {}", e))?;
+        // The brightness methods act on our behalf, which the default identify level does not allow
+        CoSetProxyBlanket(
+            &services,
+            RPC_C_AUTHN_WINNT,
+            RPC_C_AUTHZ_NONE,
+            None,
+            RPC_C_AUTHN_LEVEL_CALL,
+            RPC_C_IMP_LEVEL_IMPERSONATE,
+            None,
+            EOAC_NONE,
+        )
+        .map_err(|e| format!("Failed to open WMI: {}", e))?;
+        Ok(services)
+    }
+}
+
+/// First result of a WQL query, if there is one
+fn query_first(services: &IWbemServices, query: &str) -> Result<IWbemClassObject, String> {
+    unsafe {
+        let results = services
+            .ExecQuery(
+                &BSTR::from("WQL"),
+                &BSTR::from(query),
+                WBEM_FLAG_FORWARD_ONLY | WBEM_FLAG_RETURN_IMMEDIATELY,
+                None,
+            )
+            .map_err(|e| format!("WMI query failed: {}", e))?;
+        let mut objects = [None];
+        let mut returned = 0;
+        let _ = results.Next(WBEM_INFINITE, &mut objects, &mut returned);
+        let [object] = objects;
+        object.ok_or_else(|| "No built-in display with brightness control".to_string())
+    }
+}
+
+fn panel_brightness() -> Result<u8, String> {
+    let object = query_first(&wmi()?, "SELECT CurrentBrightness FROM WmiMonitorBrightness WHERE Active = TRUE")?;
+    unsafe {
+        let mut value = VARIANT::default();
+        object
+            .Get(w!("CurrentBrightness"), 0, &mut value, None, None)
+            .map_err(|e| format!("Failed to read the display brightness: {}", e))?;
+        let level = VariantToUInt32(&value);
+        let _ = VariantClear(&mut value);
+        level
+            .map(|level| level.min(100) as u8)
+            .map_err(|e| format!("Failed to read the display brightness: {}", e))
+    }
+}
+
+fn set_panel_brightness(percent: u8) -> Result<(), String> {
+    let services = wmi()?;
+    let instance = query_first(&services, "SELECT * FROM WmiMonitorBrightnessMethods WHERE Active = TRUE")?;
+    unsafe {
+        let mut path = VARIANT::default();
+        instance
+            .Get(w!("__PATH"), 0, &mut path, None, None)
+            .map_err(|e| format!("Failed to find the display: {}", e))?;
+        let text = VariantToStringAlloc(&path);
+        let _ = VariantClear(&mut path);
+        let text = text.map_err(|e| format!("Failed to find the display: {}", e))?;
+        let path = text.to_string().unwrap_or_default();
+        CoTaskMemFree(Some(text.0 as *const _));
+
+        let mut class = None;
+        services
+            .GetObject(&BSTR::from("WmiMonitorBrightnessMethods"), WBEM_GENERIC_FLAG_TYPE(0), None, Some(&mut class), None)
+            .map_err(|e| format!("Failed to open the brightness control: {}", e))?;
+        let class = class.ok_or("Failed to open the brightness control")?;
+        let mut signature = None;
+        class
+            .GetMethod(w!("WmiSetBrightness"), 0, &mut signature, std::ptr::null_mut())
+            .map_err(|e| format!("Failed to open the brightness control: {}", e))?;
+        let params = signature
+            .ok_or("Failed to open the brightness control")?
+            .SpawnInstance(0)
+            .map_err(|e| format!("Failed to open the brightness control: {}", e))?;
+        params
+            .Put(w!("Timeout"), 0, &int_variant(0), 0)
+            .and_then(|()| params.Put(w!("Brightness"), 0, &int_variant(i32::from(percent)), 0))
+            .map_err(|e| format!("Failed to set the display brightness: {}", e))?;
+
+        services
+            .ExecMethod(&BSTR::from(path), &BSTR::from("WmiSetBrightness"), WBEM_GENERIC_FLAG_TYPE(0), None, &params, None, None)
+            .map_err(|e| format!("Failed to set the display brightness: {}", e))
+    }
+}
+
+fn int_variant(value: i32) -> VARIANT {
+    let mut variant = VARIANT::default();
+    unsafe {
+        let inner = &mut *variant.Anonymous.Anonymous;
+        inner.vt = VT_I4;
+        inner.Anonymous.lVal = value;
+    }
+    variant
+}
+
+// External monitors are controlled over DDC/CI, which most have (sometimes off in their own menu)
+
+unsafe extern "system" fn collect_handle(monitor: HMONITOR, _hdc: HDC, _rect: *mut RECT, data: LPARAM) -> BOOL {
+    let handles = &mut *(data.0 as *mut Vec<HMONITOR>);
+    handles.push(monitor);
+    BOOL(1)
+}
+
+/// Physical monitors behind every display; pass them to `release` when done
+fn physical_monitors() -> Vec<PHYSICAL_MONITOR> {
+    let mut handles: Vec<HMONITOR> = Vec::new();
+    let mut monitors = Vec::new();
+    unsafe {
+        let _ = EnumDisplayMonitors(None, None, Some(collect_handle), LPARAM(&mut handles as *mut _ as isize));
+        for handle in handles {
+            let mut count = 0;
+            if GetNumberOfPhysicalMonitorsFromHMONITOR(handle, &mut count).is_err() || count == 0 {
+                continue;
+            }
+            let mut physical = vec![PHYSICAL_MONITOR::default(); count as usize];
+            if GetPhysicalMonitorsFromHMONITOR(handle, &mut physical).is_ok() {
+                monitors.extend(physical);
+            }
+        }
+    }
+    monitors
+}
+
+fn release(monitors: &[PHYSICAL_MONITOR]) {
+    if !monitors.is_empty() {
+        let _ = unsafe { DestroyPhysicalMonitors(monitors) };
+    }
+}
+
+/// Brightness in percent with the monitor's own minimum and maximum
+fn monitor_brightness(monitor: &PHYSICAL_MONITOR) -> Option<(u8, u32, u32)> {
+    let (mut min, mut current, mut max) = (0, 0, 0);
+    if unsafe { GetMonitorBrightness(monitor.hPhysicalMonitor, &mut min, &mut current, &mut max) } == 0 || max <= min {
+        return None;
+    }
+    let percent = (current.clamp(min, max) - min) * 100 / (max - min);
+    Some((percent as u8, min, max))
+}
+
+fn set_monitor_brightness(monitor: &PHYSICAL_MONITOR, percent: u8) -> bool {
+    let Some((_, min, max)) = monitor_brightness(monitor) else {
+        return false;
+    };
+    let value = min + (max - min) * u32::from(percent) / 100;
+    unsafe { SetMonitorBrightness(monitor.hPhysicalMonitor, value) != 0 }
+}
@@ -0,0 +1,110 @@
+use axum::{extract::Query, http::StatusCode, response::IntoResponse, Json};
+use serde::Deserialize;
+use serde_json::json;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::info;
+
+use crate::config::Calibration;
+
+// Pointer multiplier the web client uses for an uncalibrated device
+const DEFAULT_SENSITIVITY: f64 = 1.8;
+// Range of the sensitivity slider in the web client
+const MIN_SENSITIVITY: f64 = 0.5;
+const MAX_SENSITIVITY: f64 = 3.0;
+
+/// Device the calibration results belong to
+#[derive(Deserialize)]
+pub struct DeviceQuery {
+    device: String,
+}
+
+/// Raw measurements collected by the calibration flow on the phone
+#[derive(Deserialize)]
+pub struct CalibrationSamples {
+    /// Length of the on-screen drag track in CSS pixels
+    drag_expected_px: f64,
+    /// Sum of touch deltas reported while dragging along the track
+    drag_measured_px: f64,
+    /// Velocities of the flicks performed (CSS pixels per millisecond)
+    flick_velocities: Vec<f64>,
+    /// Round-trip times of `GET /calibration/ping` requests in milliseconds
+    round_trips_ms: Vec<f64>,
+}
+
+/// Value at the given fraction (0.0 - 1.0) of a sorted sample list
+fn percentile(sorted: &[f64], fraction: f64) -> f64 {
+    let index = ((sorted.len() - 1) as f64 * fraction).round() as usize;
+    sorted[index]
+}
+
+/// Sort samples after checking they are usable
+fn sorted_samples(name: &str, samples: &[f64]) -> Result<Vec<f64>, String> {
+    if samples.is_empty() {
+        return Err(format!("No {} samples", name));
+    }
+    if samples.iter().any(|value| !value.is_finite() || *value < 0.0) {
+        return Err(format!("Invalid {} sample", name));
+    }
+    let mut sorted = samples.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    Ok(sorted)
+}
+
+/// Turn raw samples into calibration results
+fn evaluate(samples: &CalibrationSamples) -> Result<Calibration, String> {
+    if !(samples.drag_expected_px > 0.0 && samples.drag_measured_px > 0.0) {
+        return Err("Invalid drag measurement".to_string());
+    }
+    let flicks = sorted_samples("flick", &samples.flick_velocities)?;
+    let round_trips = sorted_samples("latency", &samples.round_trips_ms)?;
+
+    // Ignore the odd accidental tap or stalled request at either end
+    Ok(Calibration {
+        delta_scale: (samples.drag_measured_px / samples.drag_expected_px).clamp(0.25, 4.0),
+        flick_velocity_min: percentile(&flicks, 0.1),
+        flick_velocity_max: percentile(&flicks, 0.9),
+        latency_ms: percentile(&round_trips, 0.5) / 2.0,
+    })
+}
+
+/// GET /calibration/ping
+pub async fn ping() -> impl IntoResponse {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or(0);
+    Json(json!({ "time_ms": now }))
+}
+
+/// POST /calibration?device=<id>
+pub async fn submit(Query(query): Query<DeviceQuery>, Json(samples): Json<CalibrationSamples>) -> impl IntoResponse {
+    let calibration = match evaluate(&samples) {
+        Ok(calibration) => calibration,
+        Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
+    };
+
+    // Unknown devices may not add themselves while pairing approval is required
+    let config = crate::config::get();
+    if !config.devices.contains_key(&query.device) && config.require_pairing_approval {
+        return (StatusCode::FORBIDDEN, "Device is not paired").into_response();
+    }
+
+    // Scale the pointer so the same finger travel moves the cursor the same distance on every phone
+    let sensitivity = (DEFAULT_SENSITIVITY / calibration.delta_scale).clamp(MIN_SENSITIVITY, MAX_SENSITIVITY);
+    let sensitivity = (sensitivity * 10.0).round() / 10.0;
+
+    let mut profile = None;
+    let result = crate::config::update(|config| {
+        let device = config.device_entry(&query.device);
+        let stored = device.profile.get_or_insert_with(Default::default);
+        stored.sensitivity = Some(sensitivity);
+        stored.calibration = Some(calibration);
+        profile = Some(stored.clone());
+    });
+    if let Err(e) = result {
+        return (StatusCode::INTERNAL_SERVER_ERROR, e).into_response();
+    }
+
+    info!("Calibrated device {} (sensitivity {:.1})", query.device, sensitivity);
+    Json(json!({ "profile": profile })).into_response()
+}
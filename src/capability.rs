@@ -0,0 +1,66 @@
+use serde::{Deserialize, Deserializer, Serialize};
+use tracing::warn;
+
+/// Command categories that can be granted to a device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Capability {
+    Pointer,
+    Keyboard,
+    Audio,
+    Power,
+    File,
+    Passthrough,
+}
+
+impl Capability {
+    /// Capabilities granted to devices without an explicit policy
+    pub fn defaults() -> Vec<Capability> {
+        vec![Capability::Pointer, Capability::Keyboard, Capability::Audio]
+    }
+
+    /// Check whether this capability was compiled into the binary (see Cargo features)
+    pub fn is_compiled_in(self) -> bool {
+        match self {
+            Capability::Pointer | Capability::Keyboard | Capability::Audio => true,
+            Capability::Power => cfg!(feature = "cap-power"),
+            Capability::File => cfg!(feature = "cap-file"),
+            // Passthrough additionally needs a `[passthrough]` program configured
+            Capability::Passthrough => cfg!(feature = "cap-passthrough"),
+        }
+    }
+
+    /// Look up a capability by its name in the config, e.g. `"power"`
+    pub fn from_name(name: &str) -> Option<Capability> {
+        serde_json::from_value(serde_json::Value::String(name.trim().to_lowercase())).ok()
+    }
+
+    /// Get the capability required by a WebSocket command (None = always allowed)
+    pub fn for_command(cmd: &str) -> Option<Capability> {
+        match cmd {
+            "m" | "j" | "b" | "w" | "sd" => Some(Capability::Pointer),
+            "t" | "k" | "kd" | "media" => Some(Capability::Keyboard),
+            "vol" | "mixer" | "audio_device" | "mic" => Some(Capability::Audio),
+            "f" => Some(Capability::File),
+            "display" | "brightness" => Some(Capability::Power),
+            "x" => Some(Capability::Passthrough),
+            _ => None,
+        }
+    }
+}
+
+/// Read a list of capability names, skipping ones this version does not know (such as the
+/// former `clipboard` and `shell`) rather than rejecting the whole config file
+pub fn deserialize_list<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Capability>, D::Error> {
+    let names = Vec::<String>::deserialize(deserializer)?;
+    Ok(names
+        .iter()
+        .filter_map(|name| {
+            let capability = Capability::from_name(name);
+            if capability.is_none() {
+                warn!("Ignoring unknown capability: {}", name);
+            }
+            capability
+        })
+        .collect())
+}
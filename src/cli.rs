@@ -0,0 +1,137 @@
+use clap::Parser;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+static ARGS: OnceLock<Args> = OnceLock::new();
+
+/// Command-line options; settings given here win over config.toml and are not saved to it
+#[derive(Parser, Debug)]
+#[command(name = "touch-relay", version, about = "Control this PC from a phone's browser")]
+pub struct Args {
+    /// Port to listen on (overrides `port` and TOUCHRELAY_PORT)
+    #[arg(long)]
+    pub port: Option<u16>,
+
+    /// IP address or network adapter name to listen on (overrides `bind`)
+    #[arg(long, value_name = "ADDRESS")]
+    pub bind: Option<String>,
+
+    /// Configuration file to use instead of %APPDATA%\TouchRelay\config.toml
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// Run the server without a tray icon until Ctrl+C is pressed
+    #[arg(long)]
+    pub no_tray: bool,
+
+    /// Log filter, e.g. "debug" (overrides `log_level` and RUST_LOG)
+    #[arg(long, value_name = "FILTER", value_parser = parse_filter)]
+    pub log_level: Option<String>,
+
+    /// Start TouchRelay when signing in to Windows, using the `[startup]` settings, and exit
+    #[arg(long, conflicts_with = "disable_startup")]
+    pub enable_startup: bool,
+
+    /// Stop starting TouchRelay when signing in to Windows and exit
+    #[arg(long)]
+    pub disable_startup: bool,
+
+    /// Started by "Start with Windows": wait for `[startup] delay_secs` before starting
+    /// the server and skip anything meant for a first manual launch
+    #[arg(long)]
+    pub autostart: bool,
+
+    /// Check GitHub for a newer release, print the result and exit
+    #[arg(long)]
+    pub check_update: bool,
+
+    /// Allow phones through Windows Firewall with an inbound rule for the port and exit
+    /// (needs administrator rights)
+    #[arg(long, conflicts_with = "remove_firewall_rule")]
+    pub add_firewall_rule: bool,
+
+    /// Delete the firewall rule added by --add-firewall-rule or the tray menu and exit
+    /// (needs administrator rights)
+    #[arg(long)]
+    pub remove_firewall_rule: bool,
+
+    /// Restarted as administrator from the tray: wait for the previous instance to quit
+    /// and make the startup entry start TouchRelay as administrator too
+    #[arg(long, hide = true)]
+    pub elevated_restart: bool,
+
+    /// Install and start the TouchRelay Windows service, which starts TouchRelay for
+    /// whoever signs in at the console (needs administrator rights)
+    #[arg(long, conflicts_with = "uninstall_service")]
+    pub install_service: bool,
+
+    /// Stop and remove the TouchRelay Windows service (needs administrator rights)
+    #[arg(long)]
+    pub uninstall_service: bool,
+
+    /// Run as the service (used by the service manager)
+    #[arg(long, hide = true)]
+    pub service: bool,
+
+    /// Re-inject a recorded session and exit
+    #[arg(long, value_name = "FILE")]
+    pub replay: Option<PathBuf>,
+
+    /// Playback speed factor for --replay
+    #[arg(long, value_name = "FACTOR", default_value_t = 1.0, value_parser = parse_speed, requires = "replay")]
+    pub speed: f64,
+
+    /// Type a text file from `files_dir` and exit
+    #[cfg(feature = "cap-file")]
+    #[arg(long, value_name = "NAME")]
+    pub type_file: Option<String>,
+
+    /// Paste the file through the clipboard instead of typing it (with --type-file)
+    #[cfg(feature = "cap-file")]
+    #[arg(long, requires = "type_file")]
+    pub paste: bool,
+
+    /// Soak-test the message handler against a mock backend
+    #[arg(long, hide = true)]
+    pub selftest: bool,
+
+    #[arg(long, hide = true, requires = "selftest")]
+    pub duration: Option<u64>,
+
+    #[arg(long, hide = true, requires = "selftest")]
+    pub rate: Option<u64>,
+
+    #[arg(long, hide = true, requires = "selftest")]
+    pub seed: Option<u64>,
+}
+
+fn parse_filter(value: &str) -> Result<String, String> {
+    tracing_subscriber::EnvFilter::try_new(value).map_err(|e| e.to_string())?;
+    Ok(value.to_string())
+}
+
+fn parse_speed(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(speed) if speed > 0.0 && speed.is_finite() => Ok(speed),
+        _ => Err("must be a positive number".to_string()),
+    }
+}
+
+/// Get the parsed command line (parsed on first use; exits on invalid arguments)
+pub fn get() -> &'static Args {
+    ARGS.get_or_init(|| {
+        // Release builds have no console of their own: show --help, errors and
+        // --no-tray logs in the console TouchRelay was started from, if any
+        if std::env::args_os().len() > 1 {
+            attach_console();
+        }
+        Args::parse()
+    })
+}
+
+fn attach_console() {
+    use windows::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+
+    // Fails harmlessly when there is no parent console or we already have one
+    let _ = unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
+}
@@ -0,0 +1,324 @@
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::sync::Notify;
+use tracing::{info, warn};
+use yawc::close::CloseCode;
+use yawc::frame::Frame;
+
+// How long the current controller has to answer a takeover request before the host is asked
+const TAKEOVER_TIMEOUT: Duration = Duration::from_secs(10);
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+static REGISTRY: OnceLock<Mutex<Registry>> = OnceLock::new();
+
+/// A connected WebSocket client
+struct Client {
+    sender: UnboundedSender<Frame>,
+    // Tells the session to let go of held keys (pause, loss of control)
+    release: Arc<Notify>,
+    device_id: Option<String>,
+    // None for one-off API commands and replays; shown in the tray and takeover prompts
+    ip: Option<IpAddr>,
+    // One-off command rather than a connection: left out of counts, metrics and events
+    one_shot: bool,
+}
+
+/// A connected WebSocket client as shown in the tray
+#[cfg(feature = "gui")]
+#[derive(Debug, Clone)]
+pub struct ClientInfo {
+    pub id: u64,
+    pub ip: IpAddr,
+    /// Device name from the config, else the device ID
+    pub name: Option<String>,
+}
+
+/// All connected clients and which one controls input in exclusive mode
+#[derive(Default)]
+struct Registry {
+    clients: HashMap<u64, Client>,
+    controller: Option<u64>,
+    // Client currently asking to take over control
+    pending_takeover: Option<u64>,
+}
+
+impl Registry {
+    /// Clients that are real connections, i.e. not one-off commands
+    fn connections(&self) -> usize {
+        self.clients.values().filter(|client| !client.one_shot).count()
+    }
+
+    /// Oldest client that is a real connection, the next in line for control
+    fn oldest_connection(&self) -> Option<u64> {
+        self.clients.iter().filter(|(_, client)| !client.one_shot).map(|(id, _)| *id).min()
+    }
+
+    /// Active controller in exclusive mode. If there is none yet, e.g. because exclusive
+    /// control was switched on while clients were connected, the oldest client gets it.
+    fn controller(&mut self) -> Option<u64> {
+        if self.controller.is_none() {
+            self.controller = self.oldest_connection();
+            if let Some(id) = self.controller {
+                info!("Client {} is now the active controller", id);
+                for (other, client) in &self.clients {
+                    client.send(&if *other == id { json!(["control"]) } else { json!(["busy", "controlled"]) });
+                }
+            }
+        }
+        self.controller
+    }
+}
+
+impl Client {
+    fn send(&self, msg: &Value) {
+        let _ = self.sender.send(Frame::text(msg.to_string()));
+    }
+}
+
+fn registry() -> &'static Mutex<Registry> {
+    REGISTRY.get_or_init(|| Mutex::new(Registry::default()))
+}
+
+/// Register a new connection and return its ID and outbound message queue.
+/// Fails if `limited` and the configured connection limit has been reached.
+pub fn register(limited: bool, device_id: Option<&str>, ip: Option<IpAddr>) -> Result<(u64, UnboundedReceiver<Frame>), String> {
+    add(limited, device_id, ip, false)
+}
+
+/// Register a one-off command (REST API, MQTT, replay) so pause, policy and exclusive
+/// control apply to it, without it showing up as a connected client
+pub fn register_one_shot(device_id: Option<&str>) -> Result<(u64, UnboundedReceiver<Frame>), String> {
+    add(false, device_id, None, true)
+}
+
+fn add(limited: bool, device_id: Option<&str>, ip: Option<IpAddr>, one_shot: bool) -> Result<(u64, UnboundedReceiver<Frame>), String> {
+    let config = crate::config::get();
+    let mut registry = registry().lock().unwrap();
+
+    if limited && config.max_clients > 0 && registry.connections() >= config.max_clients {
+        crate::metrics::connection_rejected();
+        return Err(format!("Connection limit reached ({} clients)", config.max_clients));
+    }
+
+    let id = NEXT_ID.fetch_add(1, Ordering::SeqCst);
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let client = Client { sender, release: Arc::new(Notify::new()), device_id: device_id.map(str::to_string), ip, one_shot };
+    registry.clients.insert(id, client);
+
+    // The first client in exclusive mode becomes the controller; one-off commands never do
+    if config.exclusive_control && registry.controller.is_none() && !one_shot {
+        registry.controller = Some(id);
+    }
+    drop(registry);
+
+    if !one_shot {
+        crate::metrics::connection_opened();
+        crate::events::send(crate::events::AppEvent::ClientsChanged);
+    }
+    Ok((id, receiver))
+}
+
+/// Remove a connection from the registry, handing control to the next client if needed
+pub fn unregister(id: u64) {
+    let mut registry = registry().lock().unwrap();
+    let one_shot = registry.clients.remove(&id).is_some_and(|client| client.one_shot);
+
+    if registry.pending_takeover == Some(id) {
+        registry.pending_takeover = None;
+    }
+
+    if registry.controller == Some(id) {
+        // Oldest remaining client takes over
+        registry.controller = registry.oldest_connection();
+        if let Some(next) = registry.controller {
+            info!("Client {} is now the active controller", next);
+            registry.clients[&next].send(&json!(["control"]));
+        }
+    }
+    drop(registry);
+
+    if !one_shot {
+        crate::events::send(crate::events::AppEvent::ClientsChanged);
+    }
+}
+
+/// Check whether a client may inject input (always true unless exclusive mode is on)
+pub fn has_control(id: u64) -> bool {
+    if !crate::config::get().exclusive_control {
+        return true;
+    }
+    let mut registry = registry().lock().unwrap();
+    let controller = registry.controller();
+    // One-off commands may only act while no connected client holds control
+    controller == Some(id) || (controller.is_none() && registry.clients.get(&id).is_some_and(|client| client.one_shot))
+}
+
+/// Ask the current controller to hand over control to another client.
+/// If the controller does not answer in time, the PC user is asked instead.
+pub fn request_takeover(requester: u64) -> Result<(), String> {
+    if !crate::config::get().exclusive_control {
+        return Err("Takeover requires exclusive control mode".to_string());
+    }
+
+    let mut state = registry().lock().unwrap();
+    if state.clients.get(&requester).is_some_and(|client| client.one_shot) {
+        return Err("One-off commands cannot take control".to_string());
+    }
+    let Some(controller) = state.controller() else {
+        return Err("No active controller".to_string());
+    };
+    if controller == requester {
+        return Err("Client already has control".to_string());
+    }
+    if state.pending_takeover.is_some() {
+        return Err("Another takeover request is pending".to_string());
+    }
+
+    state.pending_takeover = Some(requester);
+    state.clients[&controller].send(&json!(["takeover_request", requester]));
+    info!("Client {} requested control from client {}", requester, controller);
+    drop(state);
+
+    // Fall back to the PC user if the controller stays silent
+    tokio::spawn(async move {
+        tokio::time::sleep(TAKEOVER_TIMEOUT).await;
+        let name = {
+            let registry = registry().lock().unwrap();
+            if registry.pending_takeover != Some(requester) {
+                return;
+            }
+            // Name the device as the tray's Connected Devices list does
+            let client = registry.clients.get(&requester);
+            let device_id = client.and_then(|client| client.device_id.as_deref());
+            crate::config::get()
+                .device_name(device_id)
+                .or_else(|| client.and_then(|client| client.ip).map(|ip| ip.to_string()))
+                .unwrap_or_else(|| requester.to_string())
+        };
+
+        let prompt = crate::i18n::t_with("dialog.takeover_request", &[("device", &name)]);
+        let approved = tokio::task::spawn_blocking(move || crate::dialog::confirm("TouchRelay", &prompt))
+            .await
+            .unwrap_or(false);
+        resolve_takeover(requester, approved);
+    });
+
+    Ok(())
+}
+
+/// Answer a takeover request on behalf of the current controller
+pub fn reply_takeover(controller: u64, requester: u64, approved: bool) -> Result<(), String> {
+    if registry().lock().unwrap().controller != Some(controller) {
+        return Err("Only the active controller can answer takeover requests".to_string());
+    }
+
+    resolve_takeover(requester, approved);
+    Ok(())
+}
+
+/// Complete a pending takeover, handing control over if approved
+fn resolve_takeover(requester: u64, approved: bool) {
+    let mut registry = registry().lock().unwrap();
+    if registry.pending_takeover != Some(requester) {
+        return;
+    }
+    registry.pending_takeover = None;
+
+    if !registry.clients.contains_key(&requester) {
+        return;
+    }
+
+    if !approved {
+        warn!("Takeover by client {} denied", requester);
+        registry.clients[&requester].send(&json!(["takeover_denied"]));
+        return;
+    }
+
+    if let Some(previous) = registry.controller.replace(requester) {
+        if let Some(client) = registry.clients.get(&previous) {
+            client.send(&json!(["busy", "controlled"]));
+            client.release.notify_one();
+        }
+    }
+    registry.clients[&requester].send(&json!(["control"]));
+    info!("Client {} is now the active controller", requester);
+}
+
+/// Signal that fires when a client's session should release its held input
+pub fn release_signal(id: u64) -> Option<Arc<Notify>> {
+    registry().lock().unwrap().clients.get(&id).map(|client| client.release.clone())
+}
+
+/// Make every session let go of the keys it holds down, e.g. when input is paused
+pub fn release_all_input() {
+    for client in registry().lock().unwrap().clients.values() {
+        client.release.notify_one();
+    }
+}
+
+/// Send a JSON message to one client, if it is still connected
+pub fn send_to(id: u64, msg: &Value) {
+    if let Some(client) = registry().lock().unwrap().clients.get(&id) {
+        client.send(msg);
+    }
+}
+
+/// Send a JSON message to every connected client
+pub fn broadcast(msg: &Value) {
+    for client in registry().lock().unwrap().clients.values() {
+        client.send(msg);
+    }
+}
+
+/// Send every client its input profile again, e.g. after the config file changed
+pub fn send_profiles() {
+    let config = crate::config::get();
+    for client in registry().lock().unwrap().clients.values() {
+        if let Some(profile) = config.profile_for(client.device_id.as_deref()) {
+            client.send(&json!(["profile", profile]));
+        }
+    }
+}
+
+/// Number of connected clients
+pub fn count() -> usize {
+    registry().lock().unwrap().connections()
+}
+
+/// WebSocket clients in the order they connected
+#[cfg(feature = "gui")]
+pub fn list() -> Vec<ClientInfo> {
+    let config = crate::config::get();
+    let registry = registry().lock().unwrap();
+    let mut clients: Vec<ClientInfo> = registry
+        .clients
+        .iter()
+        .filter_map(|(id, client)| {
+            let name = config.device_name(client.device_id.as_deref());
+            Some(ClientInfo { id: *id, ip: client.ip?, name })
+        })
+        .collect();
+    clients.sort_by_key(|client| client.id);
+    clients
+}
+
+/// Close one client's connection; the web client does not reconnect on its own
+#[cfg(feature = "gui")]
+pub fn disconnect(id: u64) {
+    if let Some(client) = registry().lock().unwrap().clients.get(&id) {
+        info!("Disconnecting client {}", id);
+        let _ = client.sender.send(Frame::close(CloseCode::Policy, "Disconnected by the PC"));
+    }
+}
+
+/// Ask every connected client to disconnect with a close frame carrying `reason`
+pub fn close_all(reason: &str) {
+    for client in registry().lock().unwrap().clients.values() {
+        let _ = client.sender.send(Frame::close(CloseCode::Away, reason));
+    }
+}
@@ -0,0 +1,539 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock, RwLock};
+use tracing::{info, warn};
+
+use crate::capability::Capability;
+
+const CONFIG_FILE: &str = "config.toml";
+// Copy of a config file that failed to parse, kept before defaults are saved over it
+const INVALID_CONFIG_FILE: &str = "config.toml.invalid";
+
+static CONFIG: OnceLock<RwLock<Arc<Config>>> = OnceLock::new();
+// Set when this run had to create the config file
+static CREATED: AtomicBool = AtomicBool::new(false);
+
+/// Application configuration loaded from `%APPDATA%\TouchRelay\config.toml`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Port the web server listens on
+    pub port: u16,
+    /// Log filter such as "debug" or "touch_relay=debug,info" (`RUST_LOG` wins when set)
+    pub log_level: Option<String>,
+    /// Also write the log to daily files in `%APPDATA%\TouchRelay\logs` (the last 7 are kept)
+    pub log_files: bool,
+    /// Language of the tray menu and notifications, e.g. "de" (None = Windows display language)
+    pub language: Option<String>,
+    /// Pointer speed for devices whose profile doesn't set `sensitivity` (None = client default)
+    pub sensitivity: Option<f64>,
+    /// Multiplier the PC applies to every pointer move, e.g. 0.5 for a 4K monitor
+    pub pointer_speed: f64,
+    /// Capabilities granted to devices that are not listed in `devices`
+    #[serde(deserialize_with = "crate::capability::deserialize_list")]
+    pub default_capabilities: Vec<Capability>,
+    /// Trusted devices keyed by the device ID reported by the web client
+    pub devices: HashMap<String, DeviceConfig>,
+    /// System-wide hotkey that suspends/resumes all remote input
+    pub kill_switch_hotkey: String,
+    /// Directory whose text files may be typed with the `f` command (default `%APPDATA%\TouchRelay\files`)
+    pub files_dir: Option<PathBuf>,
+    /// Directory with replacements for the built-in web files (default: `static` next to the exe)
+    pub static_dir: Option<PathBuf>,
+    /// Ignore remote pointer commands for this long after local mouse/keyboard use (0 = off)
+    pub local_input_grace_ms: u64,
+    /// Close connections that send nothing (not even a ping) for this long (0 = never)
+    pub idle_timeout_secs: u64,
+    /// Send a WebSocket ping this often and drop clients whose pong is missing by the next one (0 = off)
+    pub ping_interval_secs: u64,
+    /// Require the host to approve devices that are not yet listed in `devices`
+    pub require_pairing_approval: bool,
+    /// Maximum number of simultaneous clients (0 = unlimited)
+    pub max_clients: usize,
+    /// Show a Windows notification whenever a device connects
+    pub notify_on_connect: bool,
+    /// Only the first connected client may inject input; others wait their turn
+    pub exclusive_control: bool,
+    /// Maximum number of keys one client may hold down at the same time
+    pub max_held_keys: usize,
+    /// Bearer token required by the `/api` endpoints (API disabled when unset)
+    pub api_token: Option<String>,
+    /// Also write significant events to the Windows Application event log
+    pub event_log: bool,
+    /// Only listen on 127.0.0.1 (for access through SSH/Tailscale tunnels)
+    pub loopback_only: bool,
+    /// IP address or network adapter name (e.g. "Wi-Fi") to listen on instead of all interfaces
+    pub bind: Option<String>,
+    /// LAN address shown in the tooltip, QR code and "Open Web Interface" (None = detect)
+    pub advertise_ip: Option<IpAddr>,
+    /// Advertise the server on the LAN via mDNS as `touchrelay.local`
+    pub mdns: bool,
+    /// UDP port answering LAN discovery probes (0 = off)
+    pub discovery_port: u16,
+    /// Loopback-only WebSocket port for trusted native companion apps (0 = off)
+    pub companion_port: u16,
+    /// Path prefix to serve under when a reverse proxy forwards e.g. `/touchrelay` unchanged
+    pub base_path: String,
+    /// Reverse proxies whose X-Forwarded-For/X-Forwarded-Proto headers are trusted
+    pub trusted_proxies: Vec<IpAddr>,
+    /// URL shown in the tooltip and QR code instead of the LAN address (e.g. the proxy's https URL)
+    pub public_url: Option<String>,
+    /// Local program receiving `["x", ...]` messages (None = off)
+    pub passthrough: Option<PassthroughConfig>,
+    /// Offer permessage-deflate compression on WebSocket connections
+    pub ws_compression: bool,
+    /// Scroll multipliers and direction for devices without their own `scroll` profile setting
+    pub scroll: ScrollSettings,
+    /// Cursor speed in pixels per second at full joystick deflection
+    pub joystick_speed: f64,
+    /// Record the commands of every session to `recordings\` for later replay
+    pub record_sessions: bool,
+    /// Installed community packs that are switched off
+    pub disabled_packs: Vec<String>,
+    /// MQTT broker to publish state to and receive commands from (None = off)
+    pub mqtt: Option<MqttConfig>,
+    /// URLs receiving a JSON POST when a client connects, disconnects or fails authentication
+    pub webhooks: Vec<String>,
+    /// Username/password required for the web interface and WebSocket
+    pub basic_auth: Option<BasicAuth>,
+    /// How "Start with Windows" launches TouchRelay
+    pub startup: StartupSettings,
+    /// Checking GitHub for new releases
+    pub updates: UpdateSettings,
+    /// Colors and button size of the web interface
+    pub theme: ThemeSettings,
+}
+
+/// How "Start with Windows" is registered
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StartupSettings {
+    /// `registry` (Run key) or `task` (Task Scheduler, supports elevation and a delay)
+    pub method: StartupMethod,
+    /// Start with administrator rights (task only; enabling it needs administrator rights)
+    pub elevated: bool,
+    /// Wait this long after signing in before starting the server (the tray icon shows right away
+    /// with the Run key; the task starts TouchRelay late)
+    pub delay_secs: u64,
+}
+
+/// Look of the web interface, applied to the page as it is served
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ThemeSettings {
+    /// Color of buttons and controls as `#rgb` or `#rrggbb` (None = the built-in slate)
+    pub accent: Option<String>,
+    pub mode: ThemeMode,
+    /// Size factor for buttons (0.75 - 2.0)
+    pub button_scale: f64,
+}
+
+impl Default for ThemeSettings {
+    fn default() -> Self {
+        Self {
+            accent: None,
+            mode: ThemeMode::Light,
+            button_scale: 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemeMode {
+    #[default]
+    Light,
+    Dark,
+}
+
+/// Checking for and installing new releases
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UpdateSettings {
+    /// Look for a new release at startup and then every `interval_hours`
+    pub check: bool,
+    pub interval_hours: u64,
+    /// Download a new release in the background and replace the exe when TouchRelay quits
+    pub install_on_quit: bool,
+}
+
+impl Default for UpdateSettings {
+    fn default() -> Self {
+        Self {
+            check: true,
+            interval_hours: 24,
+            install_on_quit: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StartupMethod {
+    #[default]
+    Registry,
+    Task,
+}
+
+/// HTTP Basic credentials protecting the web interface
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BasicAuth {
+    pub username: String,
+    pub password: String,
+}
+
+/// Host-side program that `["x", ...]` messages are bridged to, one connection per session
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PassthroughConfig {
+    /// TCP address to connect to, e.g. "127.0.0.1:9000"
+    pub tcp: Option<String>,
+    /// Program and arguments to start instead, talking over stdin/stdout
+    pub command: Vec<String>,
+}
+
+/// MQTT broker connection for home automation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MqttConfig {
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Client ID (default `touchrelay-<computer name>`)
+    pub client_id: Option<String>,
+    /// Prefix of all topics (default `touchrelay/<computer name>`)
+    pub topic_prefix: Option<String>,
+    /// Home Assistant MQTT discovery prefix (None or empty = don't announce entities)
+    pub discovery_prefix: Option<String>,
+}
+
+impl Default for MqttConfig {
+    fn default() -> Self {
+        Self {
+            host: "localhost".to_string(),
+            port: 1883,
+            username: None,
+            password: None,
+            client_id: None,
+            topic_prefix: None,
+            discovery_prefix: Some("homeassistant".to_string()),
+        }
+    }
+}
+
+/// Settings for a single trusted device
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeviceConfig {
+    /// Friendly name shown in logs
+    pub name: Option<String>,
+    /// Command categories this device may use
+    #[serde(deserialize_with = "crate::capability::deserialize_list")]
+    pub capabilities: Vec<Capability>,
+    /// Input preferences applied automatically when the device connects
+    pub profile: Option<InputProfile>,
+}
+
+/// Per-device input preferences pushed to the web client at connect
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InputProfile {
+    /// Pointer movement multiplier (0.5 - 3.0)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sensitivity: Option<f64>,
+    /// Invert the two-finger scroll direction
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub natural_scroll: Option<bool>,
+    /// Gesture overrides: `tap`, `double_tap` or `two_finger_tap` mapped to a command array
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub gestures: HashMap<String, serde_json::Value>,
+    /// Layout preference: `full` or `touchpad`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub layout: Option<String>,
+    /// Measurements from the calibration wizard
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub calibration: Option<Calibration>,
+    /// Scroll multipliers and direction, replacing the global `[scroll]` settings
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scroll: Option<ScrollSettings>,
+}
+
+/// Wheel multipliers and direction per axis, applied when scroll input is injected
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScrollSettings {
+    /// Multiplier for vertical wheel steps
+    pub vertical: f64,
+    /// Multiplier for horizontal wheel steps
+    pub horizontal: f64,
+    /// Reverse the vertical direction
+    pub invert_vertical: bool,
+    /// Reverse the horizontal direction
+    pub invert_horizontal: bool,
+}
+
+impl Default for ScrollSettings {
+    fn default() -> Self {
+        Self {
+            vertical: 1.0,
+            horizontal: 1.0,
+            invert_vertical: false,
+            invert_horizontal: false,
+        }
+    }
+}
+
+/// Touch characteristics measured for a device by the calibration wizard
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Calibration {
+    /// Touch delta reported per CSS pixel of finger travel
+    pub delta_scale: f64,
+    /// Slowest and fastest deliberate flick (CSS pixels per millisecond)
+    pub flick_velocity_min: f64,
+    pub flick_velocity_max: f64,
+    /// Typical one-way network latency in milliseconds
+    pub latency_ms: f64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            port: 8000,
+            log_level: None,
+            log_files: true,
+            language: None,
+            sensitivity: None,
+            pointer_speed: 1.0,
+            default_capabilities: Capability::defaults(),
+            devices: HashMap::new(),
+            kill_switch_hotkey: "Ctrl+Alt+F12".to_string(),
+            files_dir: None,
+            static_dir: None,
+            local_input_grace_ms: 500,
+            idle_timeout_secs: 120,
+            ping_interval_secs: 15,
+            require_pairing_approval: false,
+            max_clients: 0,
+            notify_on_connect: true,
+            exclusive_control: false,
+            max_held_keys: 8,
+            api_token: None,
+            event_log: false,
+            loopback_only: false,
+            bind: None,
+            advertise_ip: None,
+            mdns: true,
+            discovery_port: 48654,
+            companion_port: 0,
+            base_path: String::new(),
+            trusted_proxies: Vec::new(),
+            public_url: None,
+            passthrough: None,
+            ws_compression: true,
+            scroll: ScrollSettings::default(),
+            joystick_speed: 1200.0,
+            record_sessions: false,
+            disabled_packs: Vec::new(),
+            mqtt: None,
+            webhooks: Vec::new(),
+            basic_auth: None,
+            startup: StartupSettings::default(),
+            updates: UpdateSettings::default(),
+            theme: ThemeSettings::default(),
+        }
+    }
+}
+
+impl Default for DeviceConfig {
+    fn default() -> Self {
+        Self {
+            name: None,
+            capabilities: Capability::defaults(),
+            profile: None,
+        }
+    }
+}
+
+impl Config {
+    /// Load configuration from disk, falling back to defaults (locked down if the file is broken)
+    fn load() -> Self {
+        let path = config_path();
+
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(_) => {
+                // Write the defaults out so there is a file listing every setting to edit
+                info!("No config file at {}, creating one with defaults", path.display());
+                CREATED.store(true, Ordering::Relaxed);
+                let config = Self::default();
+                if let Err(e) = config.save() {
+                    warn!("{}", e);
+                }
+                return config;
+            }
+        };
+
+        match toml::from_str(&text) {
+            Ok(config) => {
+                info!("Loaded config from {}", path.display());
+                config
+            }
+            Err(e) => {
+                // Keep the user's edits around: the next change from the tray would overwrite the file
+                let backup = path.with_file_name(INVALID_CONFIG_FILE);
+                warn!("Failed to parse {} (copy kept as {}): {}", path.display(), backup.display(), e);
+                if let Err(e) = std::fs::copy(&path, &backup) {
+                    warn!("Failed to back up {}: {}", path.display(), e);
+                }
+                // The file's authentication and device restrictions are lost with it, so fail
+                // closed: only this PC can connect and input stays paused until it is fixed
+                warn!("Accepting connections from this PC only, with remote input paused");
+                crate::pause::set_paused(true);
+                // Translations read the config, so they can only be looked up once this load is done
+                let error = e.to_string();
+                std::thread::spawn(move || {
+                    crate::notify::show(
+                        &crate::i18n::t("notify.config_invalid_title"),
+                        &crate::i18n::t_with("notify.config_invalid", &[("error", &error)]),
+                    );
+                });
+                Self { loopback_only: true, ..Self::default() }
+            }
+        }
+    }
+
+    /// Save configuration to disk
+    fn save(&self) -> Result<(), String> {
+        let path = config_path();
+        let text = toml::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize config: {}", e))?;
+
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        }
+        std::fs::write(&path, text)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+
+        info!("Saved config to {}", path.display());
+        Ok(())
+    }
+
+    /// Check whether a device may use the given capability
+    pub fn is_allowed(&self, device_id: Option<&str>, capability: Capability) -> bool {
+        let capabilities = device_id
+            .and_then(|id| self.devices.get(id))
+            .map(|device| &device.capabilities)
+            .unwrap_or(&self.default_capabilities);
+
+        capabilities.contains(&capability)
+    }
+
+    /// Settings of a device, added with `default_capabilities` if it is not listed yet
+    pub fn device_entry(&mut self, device_id: &str) -> &mut DeviceConfig {
+        let capabilities = &self.default_capabilities;
+        self.devices.entry(device_id.to_string()).or_insert_with(|| DeviceConfig {
+            capabilities: capabilities.clone(),
+            ..DeviceConfig::default()
+        })
+    }
+
+    /// Friendly name of a device from `devices`, else its device ID
+    pub fn device_name(&self, device_id: Option<&str>) -> Option<String> {
+        let id = device_id?;
+        Some(self.devices.get(id).and_then(|device| device.name.clone()).unwrap_or_else(|| id.to_string()))
+    }
+
+    /// Get the allowlisted directory for file typing
+    #[cfg(feature = "cap-file")]
+    pub fn files_dir(&self) -> PathBuf {
+        self.files_dir
+            .clone()
+            .unwrap_or_else(|| config_dir().join("files"))
+    }
+
+    /// Get the input profile stored for a device, with the global `sensitivity` filled in
+    pub fn profile_for(&self, device_id: Option<&str>) -> Option<InputProfile> {
+        let mut profile = device_id
+            .and_then(|id| self.devices.get(id))
+            .and_then(|device| device.profile.clone());
+        if let Some(sensitivity) = self.sensitivity {
+            profile.get_or_insert_with(InputProfile::default).sensitivity.get_or_insert(sensitivity);
+        }
+        profile
+    }
+
+    /// Scroll settings for a device: its profile's, or the global ones
+    pub fn scroll_for(&self, device_id: Option<&str>) -> ScrollSettings {
+        device_id
+            .and_then(|id| self.devices.get(id))
+            .and_then(|device| device.profile.as_ref())
+            .and_then(|profile| profile.scroll)
+            .unwrap_or(self.scroll)
+    }
+}
+
+fn lock() -> &'static RwLock<Arc<Config>> {
+    CONFIG.get_or_init(|| RwLock::new(Arc::new(Config::load())))
+}
+
+/// Lowest and highest `pointer_speed`
+pub const POINTER_SPEED_RANGE: (f64, f64) = (0.1, 5.0);
+
+/// Get the current configuration
+pub fn get() -> Arc<Config> {
+    Arc::clone(&lock().read().unwrap())
+}
+
+/// Modify the configuration and save it to disk
+pub fn update(f: impl FnOnce(&mut Config)) -> Result<(), String> {
+    let mut current = lock().write().unwrap();
+    let mut config = Config::clone(&current);
+    f(&mut config);
+    config.save()?;
+    *current = Arc::new(config);
+    Ok(())
+}
+
+/// Switch this process to the built-in defaults without touching `config.toml`,
+/// so the selftest behaves the same whatever the PC is configured with
+pub fn use_defaults() {
+    *lock().write().unwrap() = Arc::new(Config::default());
+}
+
+/// Re-read the configuration file, picking up changes made outside the app.
+/// The current settings stay in place if the file cannot be read or parsed.
+pub fn reload() -> Result<(), String> {
+    let path = config_path();
+    let text = std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let config = toml::from_str(&text).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+    *lock().write().unwrap() = Arc::new(config);
+    Ok(())
+}
+
+/// Whether this run created the config file, i.e. TouchRelay is starting for the first time
+#[cfg(feature = "gui")]
+pub fn is_first_run() -> bool {
+    get();
+    CREATED.load(Ordering::Relaxed)
+}
+
+/// Get the directory holding TouchRelay's configuration
+pub fn config_dir() -> PathBuf {
+    std::env::var_os("APPDATA")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("TouchRelay")
+}
+
+/// Get the path of the configuration file
+pub fn config_path() -> PathBuf {
+    match &crate::cli::get().config {
+        Some(path) => path.clone(),
+        None => config_dir().join(CONFIG_FILE),
+    }
+}
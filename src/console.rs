@@ -0,0 +1,50 @@
+use tracing::{error, info};
+use windows::core::w;
+use windows::Win32::System::Console::{
+    AllocConsole, FreeConsole, GetConsoleMode, GetConsoleWindow, GetStdHandle, SetConsoleCtrlHandler,
+    SetConsoleMode, SetConsoleTitleW, CONSOLE_MODE, ENABLE_VIRTUAL_TERMINAL_PROCESSING, STD_OUTPUT_HANDLE,
+};
+use windows::Win32::UI::WindowsAndMessaging::{DeleteMenu, GetSystemMenu, MF_BYCOMMAND, SC_CLOSE};
+
+/// Whether this process has a console window showing the log
+pub fn is_open() -> bool {
+    !unsafe { GetConsoleWindow() }.is_invalid()
+}
+
+/// Open a console window showing the live log, or close it
+pub fn toggle() {
+    if is_open() {
+        // The log writes to stdout, which has nowhere to go once the console is gone
+        info!("Closing debug console");
+        if let Err(e) = unsafe { FreeConsole() } {
+            error!("Failed to close the debug console: {}", e);
+        }
+    } else {
+        open();
+    }
+}
+
+fn open() {
+    unsafe {
+        if let Err(e) = AllocConsole() {
+            error!("Failed to open a debug console: {}", e);
+            return;
+        }
+        let _ = SetConsoleTitleW(w!("TouchRelay Debug Console"));
+
+        // Ctrl+C or the close button would end TouchRelay along with the console,
+        // so it is closed from the tray menu instead
+        let _ = SetConsoleCtrlHandler(None, true);
+        let system_menu = GetSystemMenu(GetConsoleWindow(), false);
+        let _ = DeleteMenu(system_menu, SC_CLOSE, MF_BYCOMMAND);
+
+        // Show the log's colors instead of escape codes
+        if let Ok(output) = GetStdHandle(STD_OUTPUT_HANDLE) {
+            let mut mode = CONSOLE_MODE::default();
+            if GetConsoleMode(output, &mut mode).is_ok() {
+                let _ = SetConsoleMode(output, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING);
+            }
+        }
+    }
+    info!("Debug console opened, close it from the tray menu");
+}
@@ -0,0 +1,78 @@
+use std::backtrace::Backtrace;
+use std::panic::PanicHookInfo;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::error;
+
+/// Name of the thread running the web server, whose panic leaves TouchRelay without a server
+pub const SERVER_THREAD: &str = "server";
+
+/// Save a crash report for every panic and tell the user when the app or its server is affected
+pub fn install_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        // Still print to stderr for console runs
+        default_hook(info);
+
+        let thread = std::thread::current();
+        let thread_name = thread.name().unwrap_or("unnamed");
+        let message = info.payload_as_str().unwrap_or("unknown error");
+        let location = info.location().map_or("unknown".to_string(), |location| location.to_string());
+        error!("Panic in thread {} at {}: {}", thread_name, location, message);
+
+        let report = report(info, thread_name, message, &location);
+        let saved = match write_report(&report) {
+            Ok(path) => {
+                error!("Crash report saved to {}", path.display());
+                crate::i18n::t_loaded_with("dialog.crash_report_saved", &[("path", &path.display().to_string())])
+            }
+            Err(e) => {
+                error!("{}", e);
+                crate::i18n::t_loaded_with("dialog.crash_report_failed", &[])
+            }
+        };
+
+        // Other threads (e.g. one connection's task) fail on their own and only get the report
+        let is_main = thread_name == "main";
+        if !(is_main || thread_name == SERVER_THREAD) || !shows_dialogs() {
+            return;
+        }
+        let key = if is_main { "dialog.crash_app" } else { "dialog.crash_server" };
+        let text = crate::i18n::t_loaded_with(key, &[("error", message), ("report", &saved)]);
+        if is_main {
+            // The process ends once this thread has unwound
+            crate::dialog::error("TouchRelay", &text);
+        } else {
+            std::thread::spawn(move || crate::dialog::error("TouchRelay", &text));
+        }
+    }));
+}
+
+/// Whether a message box can be seen: only the tray runs on the user's desktop
+fn shows_dialogs() -> bool {
+    let args = crate::cli::get();
+    cfg!(feature = "gui") && !args.no_tray && !args.service
+}
+
+fn report(info: &PanicHookInfo, thread_name: &str, message: &str, location: &str) -> String {
+    format!(
+        "TouchRelay {} crashed\n\nThread: {}\nLocation: {}\nMessage: {}\nDetails: {}\n\nBacktrace:\n{}\n",
+        env!("CARGO_PKG_VERSION"),
+        thread_name,
+        location,
+        message,
+        info,
+        Backtrace::force_capture(),
+    )
+}
+
+/// Write the report next to the log files as `crash-<unix time>.txt`
+fn write_report(report: &str) -> Result<PathBuf, String> {
+    let dir = crate::logging::log_dir();
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let path = dir.join(format!("crash-{}.txt", timestamp));
+    std::fs::create_dir_all(&dir)
+        .and_then(|()| std::fs::write(&path, report))
+        .map_err(|e| format!("Failed to save crash report to {}: {}", path.display(), e))?;
+    Ok(path)
+}
@@ -0,0 +1,216 @@
+use windows::core::HSTRING;
+use windows::Win32::UI::WindowsAndMessaging::{
+    MessageBoxW, IDYES, MB_ICONERROR, MB_ICONQUESTION, MB_OK, MB_SETFOREGROUND, MB_TOPMOST, MB_YESNO,
+};
+
+#[cfg(feature = "gui")]
+pub use prompt::prompt;
+#[cfg(feature = "gui")]
+pub use window::{add_control, create_window, run_modal};
+
+/// Show a blocking Yes/No prompt on top of all windows and return whether Yes was chosen
+pub fn confirm(title: &str, text: &str) -> bool {
+    let result = unsafe {
+        MessageBoxW(
+            None,
+            &HSTRING::from(text),
+            &HSTRING::from(title),
+            MB_YESNO | MB_ICONQUESTION | MB_TOPMOST | MB_SETFOREGROUND,
+        )
+    };
+
+    result == IDYES
+}
+
+/// Show a blocking error message on top of all windows
+pub fn error(title: &str, text: &str) {
+    unsafe {
+        MessageBoxW(
+            None,
+            &HSTRING::from(text),
+            &HSTRING::from(title),
+            MB_OK | MB_ICONERROR | MB_TOPMOST | MB_SETFOREGROUND,
+        );
+    }
+}
+
+#[cfg(feature = "gui")]
+mod window {
+    use windows::core::{HSTRING, PCWSTR};
+    use windows::Win32::Foundation::{HINSTANCE, HWND, LPARAM, WPARAM};
+    use windows::Win32::Graphics::Gdi::{GetStockObject, COLOR_BTNFACE, DEFAULT_GUI_FONT, HBRUSH};
+    use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+    use windows::Win32::UI::WindowsAndMessaging::{
+        CreateWindowExW, DispatchMessageW, GetMessageW, GetSystemMetrics, IsDialogMessageW, IsWindow, LoadCursorW,
+        PostQuitMessage, RegisterClassW, SendMessageW, SetForegroundWindow, ShowWindow, TranslateMessage, HMENU,
+        IDC_ARROW, MSG, SM_CXSCREEN, SM_CYSCREEN, SW_SHOW, WINDOW_EX_STYLE, WINDOW_STYLE, WM_SETFONT, WNDCLASSW,
+        WNDPROC, WS_CAPTION, WS_CHILD, WS_EX_DLGMODALFRAME, WS_EX_TOPMOST, WS_POPUP, WS_SYSMENU, WS_VISIBLE,
+    };
+
+    /// Create a hidden dialog-style window of the given size, centered on the primary screen
+    pub fn create_window(class_name: PCWSTR, window_proc: WNDPROC, title: &str, width: i32, height: i32) -> Option<HWND> {
+        unsafe {
+            let instance: HINSTANCE = GetModuleHandleW(None).ok()?.into();
+            let class = WNDCLASSW {
+                lpfnWndProc: window_proc,
+                hInstance: instance,
+                lpszClassName: class_name,
+                hbrBackground: HBRUSH((COLOR_BTNFACE.0 + 1) as isize as *mut _),
+                hCursor: LoadCursorW(None, IDC_ARROW).unwrap_or_default(),
+                ..Default::default()
+            };
+            // Fails harmlessly when an earlier window already registered the class
+            RegisterClassW(&class);
+
+            let x = (GetSystemMetrics(SM_CXSCREEN) - width) / 2;
+            let y = (GetSystemMetrics(SM_CYSCREEN) - height) / 2;
+            CreateWindowExW(
+                WS_EX_TOPMOST | WS_EX_DLGMODALFRAME,
+                class_name,
+                &HSTRING::from(title),
+                WS_POPUP | WS_CAPTION | WS_SYSMENU,
+                x,
+                y,
+                width,
+                height,
+                None,
+                None,
+                Some(instance),
+                None,
+            )
+            .ok()
+        }
+    }
+
+    /// Add a child control such as a `STATIC`, `EDIT` or `BUTTON` at `(x, y, width, height)`
+    pub fn add_control(
+        window: HWND,
+        ex_style: WINDOW_EX_STYLE,
+        class: PCWSTR,
+        text: &str,
+        style: WINDOW_STYLE,
+        bounds: (i32, i32, i32, i32),
+        id: i32,
+    ) -> Option<HWND> {
+        unsafe {
+            let instance: HINSTANCE = GetModuleHandleW(None).ok()?.into();
+            let control = CreateWindowExW(
+                ex_style,
+                class,
+                &HSTRING::from(text),
+                WS_CHILD | WS_VISIBLE | style,
+                bounds.0,
+                bounds.1,
+                bounds.2,
+                bounds.3,
+                Some(window),
+                Some(HMENU(id as isize as *mut _)),
+                Some(instance),
+                None,
+            )
+            .ok()?;
+            let font = GetStockObject(DEFAULT_GUI_FONT);
+            SendMessageW(control, WM_SETFONT, Some(WPARAM(font.0 as usize)), Some(LPARAM(1)));
+            Some(control)
+        }
+    }
+
+    /// Show the window and block until it is destroyed; Enter, Escape and Tab work as in dialogs
+    pub fn run_modal(window: HWND) {
+        unsafe {
+            let _ = ShowWindow(window, SW_SHOW);
+            let _ = SetForegroundWindow(window);
+
+            let mut message = MSG::default();
+            while IsWindow(Some(window)).as_bool() {
+                if !GetMessageW(&mut message, None, 0, 0).as_bool() {
+                    // Leave WM_QUIT for the main loop
+                    PostQuitMessage(message.wParam.0 as i32);
+                    break;
+                }
+                if !IsDialogMessageW(window, &message).as_bool() {
+                    let _ = TranslateMessage(&message);
+                    DispatchMessageW(&message);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "gui")]
+mod prompt {
+    use std::sync::Mutex;
+    use windows::core::{w, PCWSTR};
+    use windows::Win32::Foundation::{HWND, LPARAM, LRESULT, WPARAM};
+    use windows::Win32::UI::Controls::EM_SETSEL;
+    use windows::Win32::UI::Input::KeyboardAndMouse::SetFocus;
+    use windows::Win32::UI::WindowsAndMessaging::{
+        DefWindowProcW, DestroyWindow, GetDlgItem, GetWindowTextW, SendMessageW, BS_DEFPUSHBUTTON, BS_PUSHBUTTON,
+        ES_AUTOHSCROLL, IDCANCEL, IDOK, WINDOW_EX_STYLE, WINDOW_STYLE, WM_CLOSE, WM_COMMAND, WS_EX_CLIENTEDGE,
+        WS_TABSTOP,
+    };
+
+    use super::window::{add_control, create_window, run_modal};
+    use crate::i18n::t;
+
+    const CLASS_NAME: PCWSTR = w!("TouchRelayPrompt");
+    const EDIT_ID: i32 = 100;
+
+    /// Text of the edit box when OK was pressed (one prompt is open at a time, on the tray thread)
+    static RESULT: Mutex<Option<String>> = Mutex::new(None);
+
+    /// Ask for a line of text in a small window on top of all others.
+    /// Blocks until it is closed and returns the text, or None if cancelled.
+    pub fn prompt(title: &str, text: &str, initial: &str) -> Option<String> {
+        *RESULT.lock().unwrap() = None;
+        let window = create_window(CLASS_NAME, Some(window_proc), title, 380, 160)?;
+
+        add_control(window, WINDOW_EX_STYLE(0), w!("STATIC"), text, WINDOW_STYLE(0), (12, 12, 340, 20), -1);
+        let edit = add_control(
+            window,
+            WS_EX_CLIENTEDGE,
+            w!("EDIT"),
+            initial,
+            WS_TABSTOP | WINDOW_STYLE(ES_AUTOHSCROLL as u32),
+            (12, 38, 340, 22),
+            EDIT_ID,
+        );
+        let buttons = [(t("dialog.ok"), BS_DEFPUSHBUTTON, 196, IDOK.0), (t("dialog.cancel"), BS_PUSHBUTTON, 277, IDCANCEL.0)];
+        for (label, style, x, id) in buttons {
+            add_control(window, WINDOW_EX_STYLE(0), w!("BUTTON"), &label, WS_TABSTOP | WINDOW_STYLE(style as u32), (x, 78, 75, 26), id);
+        }
+
+        if let Some(edit) = edit {
+            unsafe {
+                let _ = SetFocus(Some(edit));
+                SendMessageW(edit, EM_SETSEL, Some(WPARAM(0)), Some(LPARAM(-1)));
+            }
+        }
+        run_modal(window);
+        RESULT.lock().unwrap().take()
+    }
+
+    unsafe extern "system" fn window_proc(window: HWND, message: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+        match message {
+            WM_COMMAND => {
+                let id = (wparam.0 & 0xffff) as i32;
+                if id == IDOK.0 {
+                    if let Ok(edit) = GetDlgItem(Some(window), EDIT_ID) {
+                        let mut buffer = [0u16; 256];
+                        let len = GetWindowTextW(edit, &mut buffer);
+                        *RESULT.lock().unwrap() = Some(String::from_utf16_lossy(&buffer[..len as usize]));
+                    }
+                    let _ = DestroyWindow(window);
+                } else if id == IDCANCEL.0 {
+                    let _ = DestroyWindow(window);
+                }
+                LRESULT(0)
+            }
+            WM_CLOSE => {
+                let _ = DestroyWindow(window);
+                LRESULT(0)
+            }
+            _ => DefWindowProcW(window, message, wparam, lparam),
+        }
+    }
+}
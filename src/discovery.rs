@@ -0,0 +1,69 @@
+use serde_json::json;
+use std::net::{IpAddr, SocketAddr};
+use tokio::net::UdpSocket;
+use tracing::{debug, info, warn};
+
+// Payload native clients broadcast to find TouchRelay hosts
+const PROBE: &[u8] = b"TOUCHRELAY_DISCOVER";
+
+/// Find the local address this PC uses to reach `peer`
+async fn local_ip_for(peer: SocketAddr) -> Option<IpAddr> {
+    let bind: SocketAddr = if peer.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" }.parse().ok()?;
+    let socket = UdpSocket::bind(bind).await.ok()?;
+    socket.connect(peer).await.ok()?;
+    socket.local_addr().ok().map(|addr| addr.ip())
+}
+
+/// Answer LAN discovery probes with this server's name, address and port
+pub async fn run() {
+    let port = crate::config::get().discovery_port;
+    if port == 0 || crate::network::is_loopback_only() {
+        return;
+    }
+
+    let socket = match UdpSocket::bind(("0.0.0.0", port)).await {
+        Ok(socket) => socket,
+        Err(e) => {
+            warn!("Failed to start discovery responder on UDP port {}: {}", port, e);
+            return;
+        }
+    };
+    info!("Answering discovery probes on UDP port {}", port);
+
+    let name = std::env::var("COMPUTERNAME").unwrap_or_else(|_| "PC".to_string());
+    let mut buf = [0u8; 64];
+    loop {
+        let (len, peer) = match socket.recv_from(&mut buf).await {
+            Ok(received) => received,
+            Err(e) => {
+                // Windows reports ICMP port unreachable for earlier replies here
+                debug!("Discovery receive error: {}", e);
+                continue;
+            }
+        };
+        if &buf[..len] != PROBE {
+            continue;
+        }
+
+        let ip = match crate::network::bound_ip().filter(|ip| !ip.is_unspecified()) {
+            Some(ip) => Some(ip),
+            None => local_ip_for(peer).await,
+        };
+        let Some(ip) = ip else {
+            continue;
+        };
+
+        let http_port = crate::network::port();
+        let reply = json!({
+            "name": name,
+            "ip": ip,
+            "port": http_port,
+            "url": format!("http://{}/", SocketAddr::new(ip, http_port)),
+            "version": env!("CARGO_PKG_VERSION"),
+        });
+        debug!("Answering discovery probe from {}", peer);
+        if let Err(e) = socket.send_to(reply.to_string().as_bytes(), peer).await {
+            debug!("Failed to answer discovery probe from {}: {}", peer, e);
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use serde::Serialize;
+use std::time::Duration;
+use tracing::info;
+use windows::core::BOOL;
+use windows::Win32::Foundation::{LPARAM, RECT};
+use windows::Win32::Graphics::Gdi::{EnumDisplayMonitors, GetMonitorInfoW, HDC, HMONITOR, MONITORINFO};
+use windows::Win32::UI::HiDpi::{GetDpiForMonitor, MDT_EFFECTIVE_DPI};
+use windows::Win32::UI::WindowsAndMessaging::MONITORINFOF_PRIMARY;
+
+// DPI that corresponds to 100% scaling
+const BASE_DPI: f64 = 96.0;
+// How often the monitor layout is checked for projectors being plugged in, resolution changes, etc.
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// A monitor attached to the host, in virtual desktop coordinates
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Monitor {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+    /// Windows display scaling (1.0 = 100%)
+    pub scale: f64,
+    pub primary: bool,
+}
+
+unsafe extern "system" fn collect_monitor(monitor: HMONITOR, _hdc: HDC, _rect: *mut RECT, data: LPARAM) -> BOOL {
+    let monitors = &mut *(data.0 as *mut Vec<Monitor>);
+
+    let mut info = MONITORINFO {
+        cbSize: std::mem::size_of::<MONITORINFO>() as u32,
+        ..Default::default()
+    };
+    if !GetMonitorInfoW(monitor, &mut info).as_bool() {
+        return BOOL(1);
+    }
+
+    let (mut dpi_x, mut dpi_y) = (0, 0);
+    let scale = match GetDpiForMonitor(monitor, MDT_EFFECTIVE_DPI, &mut dpi_x, &mut dpi_y) {
+        Ok(()) => dpi_x as f64 / BASE_DPI,
+        Err(_) => 1.0,
+    };
+
+    let rect = info.rcMonitor;
+    monitors.push(Monitor {
+        x: rect.left,
+        y: rect.top,
+        width: rect.right - rect.left,
+        height: rect.bottom - rect.top,
+        scale,
+        primary: info.dwFlags & MONITORINFOF_PRIMARY != 0,
+    });
+    BOOL(1)
+}
+
+/// List all monitors, primary first
+pub fn monitors() -> Vec<Monitor> {
+    let mut monitors: Vec<Monitor> = Vec::new();
+    unsafe {
+        let _ = EnumDisplayMonitors(None, None, Some(collect_monitor), LPARAM(&mut monitors as *mut _ as isize));
+    }
+    monitors.sort_by_key(|monitor| !monitor.primary);
+    monitors
+}
+
+/// Describe the host's screens for the hello message
+pub fn screen_info() -> serde_json::Value {
+    let monitors = monitors();
+    let primary = monitors.first();
+
+    // Bounding box of all monitors (the virtual desktop)
+    let left = monitors.iter().map(|m| m.x).min().unwrap_or(0);
+    let top = monitors.iter().map(|m| m.y).min().unwrap_or(0);
+    let right = monitors.iter().map(|m| m.x + m.width).max().unwrap_or(0);
+    let bottom = monitors.iter().map(|m| m.y + m.height).max().unwrap_or(0);
+
+    serde_json::json!({
+        "width": primary.map_or(0, |m| m.width),
+        "height": primary.map_or(0, |m| m.height),
+        "scale": primary.map_or(1.0, |m| m.scale),
+        "monitor_count": monitors.len(),
+        "virtual": { "x": left, "y": top, "width": right - left, "height": bottom - top },
+        "monitors": monitors,
+    })
+}
+
+/// Watch for monitors being added, removed or changed and send the new layout to all
+/// clients as `["screen", info]`, so their calibration follows the host
+pub async fn watch() {
+    let mut interval = tokio::time::interval(WATCH_INTERVAL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    let mut known = monitors();
+
+    loop {
+        interval.tick().await;
+        let current = monitors();
+        if current == known {
+            continue;
+        }
+
+        info!("Monitor layout changed ({} -> {} monitors)", known.len(), current.len());
+        known = current;
+        crate::clients::broadcast(&serde_json::json!(["screen", screen_info()]));
+    }
+}
@@ -0,0 +1,152 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use tracing::warn;
+#[cfg(feature = "gui")]
+use windows::core::{w, HSTRING, PCWSTR};
+use windows::Win32::Foundation::{CloseHandle, HANDLE};
+use windows::Win32::Security::{GetTokenInformation, TokenElevation, TOKEN_ELEVATION, TOKEN_QUERY};
+use windows::Win32::System::Threading::{
+    GetCurrentProcess, OpenProcess, OpenProcessToken, PROCESS_QUERY_LIMITED_INFORMATION,
+};
+#[cfg(feature = "gui")]
+use windows::Win32::System::Threading::{GetExitCodeProcess, WaitForSingleObject, INFINITE};
+#[cfg(feature = "gui")]
+use windows::Win32::UI::Shell::{ShellExecuteExW, SEE_MASK_NOCLOSEPROCESS, SHELLEXECUTEINFOW};
+use windows::Win32::UI::WindowsAndMessaging::{GetForegroundWindow, GetWindowThreadProcessId};
+#[cfg(feature = "gui")]
+use windows::Win32::UI::WindowsAndMessaging::{SW_HIDE, SW_SHOWNORMAL};
+
+static ELEVATED: OnceLock<bool> = OnceLock::new();
+/// Set once input was found to be blocked by an elevated window, so the user is told only once
+static BLOCKED: AtomicBool = AtomicBool::new(false);
+
+/// Whether TouchRelay runs with administrator rights
+pub fn is_elevated() -> bool {
+    *ELEVATED.get_or_init(|| unsafe { is_process_elevated(GetCurrentProcess()) })
+}
+
+/// Whether the window in front belongs to a program running as administrator
+fn is_foreground_elevated() -> bool {
+    unsafe {
+        let mut pid = 0;
+        GetWindowThreadProcessId(GetForegroundWindow(), Some(&mut pid));
+        if pid == 0 {
+            return false;
+        }
+        let Ok(process) = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid) else {
+            return false;
+        };
+        let elevated = is_process_elevated(process);
+        let _ = CloseHandle(process);
+        elevated
+    }
+}
+
+/// Check a process's token for elevation
+unsafe fn is_process_elevated(process: HANDLE) -> bool {
+    unsafe {
+        let mut token = HANDLE::default();
+        if OpenProcessToken(process, TOKEN_QUERY, &mut token).is_err() {
+            return false;
+        }
+        let mut elevation = TOKEN_ELEVATION::default();
+        let mut len = 0;
+        let result = GetTokenInformation(
+            token,
+            TokenElevation,
+            Some(&mut elevation as *mut _ as *mut _),
+            size_of::<TOKEN_ELEVATION>() as u32,
+            &mut len,
+        );
+        let _ = CloseHandle(token);
+        result.is_ok() && elevation.TokenIsElevated != 0
+    }
+}
+
+/// Call when injecting input failed. Windows silently drops input sent to windows of programs
+/// running as administrator (UIPI) unless TouchRelay runs that way too, so offer to restart it.
+pub fn note_failed_input() {
+    if BLOCKED.load(Ordering::Relaxed) || is_elevated() || !is_foreground_elevated() {
+        return;
+    }
+    BLOCKED.store(true, Ordering::Relaxed);
+    warn!("Input is blocked: the window in front runs as administrator and TouchRelay does not");
+
+    #[cfg(feature = "gui")]
+    if !crate::cli::get().no_tray {
+        crate::events::send(crate::events::AppEvent::StateChanged);
+        crate::notify::show(&crate::i18n::t("notify.input_blocked_title"), &crate::i18n::t("notify.input_blocked"));
+    }
+}
+
+/// Whether input was blocked by a window running as administrator since TouchRelay started
+#[cfg(feature = "gui")]
+pub fn input_was_blocked() -> bool {
+    BLOCKED.load(Ordering::Relaxed)
+}
+
+/// Start TouchRelay again as administrator with the same arguments; the new instance
+/// takes over once this one exits. Fails if the user declines.
+#[cfg(feature = "gui")]
+pub fn relaunch() -> Result<(), String> {
+    let mut args: Vec<String> = std::env::args().skip(1).filter(|arg| arg != "--autostart").collect();
+    // An administrator account signing in over the shoulder has its own %APPDATA%
+    if crate::cli::get().config.is_none() {
+        args.extend(["--config".to_string(), crate::config::config_path().to_string_lossy().to_string()]);
+    }
+    args.push("--elevated-restart".to_string());
+    let args = args.iter().map(|arg| quote(arg)).collect::<Vec<_>>().join(" ");
+
+    let exe = std::env::current_exe().map_err(|e| format!("Failed to locate TouchRelay: {}", e))?;
+    let file = HSTRING::from(exe.as_os_str());
+    let parameters = HSTRING::from(args);
+    let mut info = SHELLEXECUTEINFOW {
+        cbSize: size_of::<SHELLEXECUTEINFOW>() as u32,
+        lpVerb: w!("runas"),
+        lpFile: PCWSTR(file.as_ptr()),
+        lpParameters: PCWSTR(parameters.as_ptr()),
+        nShow: SW_SHOWNORMAL.0,
+        ..Default::default()
+    };
+    unsafe { ShellExecuteExW(&mut info) }.map_err(|e| format!("Failed to restart TouchRelay as administrator: {}", e))
+}
+
+/// Quote a command-line argument if it contains spaces
+#[cfg(feature = "gui")]
+fn quote(arg: &str) -> String {
+    if arg.is_empty() || arg.contains([' ', '\t']) {
+        format!("\"{}\"", arg)
+    } else {
+        arg.to_string()
+    }
+}
+
+/// Run this executable as administrator with `args` (Windows asks the user first),
+/// wait for it to finish and fail unless it exits with code 0
+#[cfg(feature = "gui")]
+pub fn run_elevated(args: &str) -> Result<(), String> {
+    let exe = std::env::current_exe().map_err(|e| format!("Failed to locate TouchRelay: {}", e))?;
+    let file = HSTRING::from(exe.as_os_str());
+    let parameters = HSTRING::from(args);
+    let mut info = SHELLEXECUTEINFOW {
+        cbSize: size_of::<SHELLEXECUTEINFOW>() as u32,
+        fMask: SEE_MASK_NOCLOSEPROCESS,
+        lpVerb: w!("runas"),
+        lpFile: PCWSTR(file.as_ptr()),
+        lpParameters: PCWSTR(parameters.as_ptr()),
+        nShow: SW_HIDE.0,
+        ..Default::default()
+    };
+    unsafe {
+        // Fails with "operation was canceled" when the user declines
+        ShellExecuteExW(&mut info).map_err(|e| format!("Failed to run TouchRelay as administrator: {}", e))?;
+        WaitForSingleObject(info.hProcess, INFINITE);
+        let mut code = 1;
+        let _ = GetExitCodeProcess(info.hProcess, &mut code);
+        let _ = CloseHandle(info.hProcess);
+        if code != 0 {
+            return Err(format!("TouchRelay {} failed (exit code {}), see its log", args, code));
+        }
+    }
+    Ok(())
+}
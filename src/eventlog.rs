@@ -0,0 +1,57 @@
+use tracing::warn;
+use windows::core::{HSTRING, PCWSTR};
+use windows::Win32::System::EventLog::{
+    DeregisterEventSource, RegisterEventSourceW, ReportEventW, EVENTLOG_ERROR_TYPE, EVENTLOG_INFORMATION_TYPE, EVENTLOG_WARNING_TYPE, REPORT_EVENT_TYPE,
+};
+
+const SOURCE_NAME: &str = "TouchRelay";
+
+/// Severity of an Event Log entry
+#[derive(Debug, Clone, Copy)]
+pub enum Level {
+    Info,
+    Warning,
+    Error,
+}
+
+/// Event IDs written to the Application log
+#[derive(Debug, Clone, Copy)]
+pub enum EventId {
+    Started = 1,
+    Stopped = 2,
+    ServerFailed = 3,
+    ServiceFailed = 4,
+    AuthFailed = 10,
+    ClientKicked = 20,
+}
+
+/// Write an entry to the Windows Application event log if enabled in the config
+pub fn report(level: Level, id: EventId, message: &str) {
+    if !crate::config::get().event_log {
+        return;
+    }
+
+    let event_type: REPORT_EVENT_TYPE = match level {
+        Level::Info => EVENTLOG_INFORMATION_TYPE,
+        Level::Warning => EVENTLOG_WARNING_TYPE,
+        Level::Error => EVENTLOG_ERROR_TYPE,
+    };
+
+    unsafe {
+        let source = match RegisterEventSourceW(PCWSTR::null(), &HSTRING::from(SOURCE_NAME)) {
+            Ok(source) => source,
+            Err(e) => {
+                warn!("Failed to open event log: {}", e);
+                return;
+            }
+        };
+
+        let message = HSTRING::from(message);
+        let strings = [PCWSTR(message.as_ptr())];
+        if let Err(e) = ReportEventW(source, event_type, 0, id as u32, None, 0, Some(&strings), None) {
+            warn!("Failed to write event log entry: {}", e);
+        }
+
+        let _ = DeregisterEventSource(source);
+    }
+}
@@ -0,0 +1,61 @@
+use std::sync::mpsc;
+use std::sync::OnceLock;
+#[cfg(feature = "gui")]
+use winit::event_loop::EventLoopProxy;
+
+static TARGET: OnceLock<Target> = OnceLock::new();
+
+/// Where events go: the tray event loop, or the main thread when running with `--no-tray`
+enum Target {
+    #[cfg(feature = "gui")]
+    Tray(EventLoopProxy<AppEvent>),
+    Channel(mpsc::Sender<AppEvent>),
+}
+
+/// Events sent to the tray event loop from other threads
+#[derive(Debug, Clone)]
+pub enum AppEvent {
+    /// Startup or pause state changed outside the tray menu
+    StateChanged,
+    /// The web server is listening (possibly on a fallback port)
+    ServerStarted,
+    /// The web server could not be started
+    ServerFailed(String),
+    /// The web server could not be started yet and will try again (shown in the tray's tooltip)
+    ServerRetrying(#[cfg_attr(not(feature = "gui"), allow(dead_code))] String),
+    /// Restart the web server with the configuration on disk
+    RestartServer,
+    /// Stop the server and exit (Ctrl+C)
+    Quit,
+    /// A WebSocket client connected or disconnected
+    ClientsChanged,
+    /// TouchRelay was launched again: remind the user where this instance is reachable
+    ShowAddress,
+}
+
+/// Install the proxy used to reach the tray event loop
+#[cfg(feature = "gui")]
+pub fn init(proxy: EventLoopProxy<AppEvent>) {
+    let _ = TARGET.set(Target::Tray(proxy));
+}
+
+/// Deliver events to a channel instead, for running without a tray
+pub fn init_channel() -> mpsc::Receiver<AppEvent> {
+    let (sender, receiver) = mpsc::channel();
+    let _ = TARGET.set(Target::Channel(sender));
+    receiver
+}
+
+/// Send an event to the tray event loop or headless main thread (ignored before either runs)
+pub fn send(event: AppEvent) {
+    match TARGET.get() {
+        #[cfg(feature = "gui")]
+        Some(Target::Tray(proxy)) => {
+            let _ = proxy.send_event(event);
+        }
+        Some(Target::Channel(sender)) => {
+            let _ = sender.send(event);
+        }
+        None => {}
+    }
+}
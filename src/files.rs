@@ -0,0 +1,67 @@
+use enigo::{Direction, Key};
+use tracing::info;
+
+use crate::backend::InputBackend;
+use crate::capability::Capability;
+
+// Refuse to type anything larger than this
+const MAX_FILE_SIZE: u64 = 64 * 1024;
+
+/// Read a text file from the allowlisted directory
+pub fn read_allowed_file(name: &str) -> Result<String, String> {
+    if !crate::policy::get().allows(Capability::File) {
+        return Err("File access disabled by policy".to_string());
+    }
+
+    // Only plain file names are accepted, never paths
+    if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\', ':']) {
+        return Err(format!("Invalid file name: {}", name));
+    }
+
+    let dir = crate::config::get().files_dir();
+    let dir = dir
+        .canonicalize()
+        .map_err(|e| format!("File directory {} unavailable: {}", dir.display(), e))?;
+
+    // Resolve links and make sure we are still inside the allowlisted directory
+    let path = dir
+        .join(name)
+        .canonicalize()
+        .map_err(|e| format!("File {} not found: {}", name, e))?;
+    if !path.starts_with(&dir) {
+        return Err(format!("File {} is outside the allowed directory", name));
+    }
+
+    let metadata = std::fs::metadata(&path).map_err(|e| format!("Failed to read {}: {}", name, e))?;
+    if metadata.len() > MAX_FILE_SIZE {
+        return Err(format!("File {} is too large ({} bytes)", name, metadata.len()));
+    }
+
+    std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", name, e))
+}
+
+/// Type the contents of an allowlisted file, or paste them via the clipboard
+pub fn type_file(backend: &mut dyn InputBackend, name: &str, paste: bool) -> Result<(), String> {
+    let contents = read_allowed_file(name)?;
+
+    if paste {
+        let mut clipboard = arboard::Clipboard::new()
+            .map_err(|e| format!("Clipboard unavailable: {}", e))?;
+        clipboard
+            .set_text(contents)
+            .map_err(|e| format!("Failed to set clipboard: {}", e))?;
+
+        backend.key(Key::Control, Direction::Press)
+            .map_err(|e| format!("Key press failed: {}", e))?;
+        let result = backend.key(Key::Unicode('v'), Direction::Click);
+        backend.key(Key::Control, Direction::Release)
+            .map_err(|e| format!("Key release failed: {}", e))?;
+        result.map_err(|e| format!("Paste failed: {}", e))?;
+    } else {
+        backend.text(&contents)
+            .map_err(|e| format!("Text input failed: {}", e))?;
+    }
+
+    info!("Injected file {} ({})", name, if paste { "pasted" } else { "typed" });
+    Ok(())
+}
@@ -0,0 +1,130 @@
+#[cfg(feature = "gui")]
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::info;
+use windows::core::BSTR;
+use windows::Win32::Foundation::VARIANT_TRUE;
+use windows::Win32::NetworkManagement::WindowsFirewall::{
+    INetFwPolicy2, INetFwRule, NetFwPolicy2, NetFwRule, NET_FW_ACTION_ALLOW, NET_FW_IP_PROTOCOL_TCP,
+    NET_FW_PROFILE2_DOMAIN, NET_FW_PROFILE2_PRIVATE, NET_FW_RULE_DIR_IN,
+};
+use windows::Win32::System::Com::{CoCreateInstance, CoInitializeEx, CLSCTX_INPROC_SERVER, COINIT_MULTITHREADED};
+#[cfg(feature = "gui")]
+use windows::Win32::{
+    NetworkManagement::WindowsFirewall::{INetFwMgr, NetFwMgr, NET_FW_IP_VERSION_ANY},
+    System::Variant::VARIANT,
+};
+
+/// Name of the inbound rule TouchRelay adds
+const RULE_NAME: &str = "TouchRelay";
+
+// Whether the rule existed when last checked, so the tray menu never waits for COM
+#[cfg(feature = "gui")]
+static RULE_EXISTS: AtomicBool = AtomicBool::new(false);
+
+/// Open the firewall settings. Joins the thread to the multithreaded COM apartment, so in
+/// the tray this only runs on worker threads: the UI thread must stay single-threaded for
+/// ShellExecuteEx.
+fn policy() -> Result<INetFwPolicy2, String> {
+    unsafe {
+        // Fails harmlessly if this thread already uses COM
+        let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
+        CoCreateInstance(&NetFwPolicy2, None, CLSCTX_INPROC_SERVER).map_err(|e| format!("Failed to open Windows Firewall settings: {}", e))
+    }
+}
+
+/// Whether TouchRelay's inbound rule existed when last checked (see `refresh_rule`)
+#[cfg(feature = "gui")]
+pub fn has_rule() -> bool {
+    RULE_EXISTS.load(Ordering::Relaxed)
+}
+
+/// Check for TouchRelay's inbound rule on a worker thread and update the menu if it changed
+#[cfg(feature = "gui")]
+pub fn refresh_rule() {
+    std::thread::spawn(check_rule);
+}
+
+/// Look up whether TouchRelay's inbound rule exists and remember it (blocks on COM)
+#[cfg(feature = "gui")]
+fn check_rule() {
+    let exists = policy().is_ok_and(|policy| unsafe { policy.Rules().and_then(|rules| rules.Item(&BSTR::from(RULE_NAME))).is_ok() });
+    if RULE_EXISTS.swap(exists, Ordering::Relaxed) != exists {
+        crate::events::send(crate::events::AppEvent::StateChanged);
+    }
+}
+
+/// Allow inbound TCP connections to `port` on private and domain networks, replacing an
+/// earlier rule for another port (`--add-firewall-rule`, needs administrator rights)
+pub fn add_rule(port: u16) -> Result<(), String> {
+    remove_rule()?;
+    let rules = unsafe { policy()?.Rules() }.map_err(|e| format!("Failed to read firewall rules: {}", e))?;
+    unsafe {
+        let rule: INetFwRule = CoCreateInstance(&NetFwRule, None, CLSCTX_INPROC_SERVER)
+            .map_err(|e| format!("Failed to create a firewall rule: {}", e))?;
+        rule.SetName(&BSTR::from(RULE_NAME))
+            .and_then(|()| rule.SetDescription(&BSTR::from("Lets phones on the local network reach TouchRelay")))
+            .and_then(|()| rule.SetGrouping(&BSTR::from(RULE_NAME)))
+            .and_then(|()| rule.SetProtocol(NET_FW_IP_PROTOCOL_TCP.0))
+            .and_then(|()| rule.SetLocalPorts(&BSTR::from(port.to_string())))
+            .and_then(|()| rule.SetDirection(NET_FW_RULE_DIR_IN))
+            .and_then(|()| rule.SetAction(NET_FW_ACTION_ALLOW))
+            .and_then(|()| rule.SetProfiles(NET_FW_PROFILE2_PRIVATE.0 | NET_FW_PROFILE2_DOMAIN.0))
+            .and_then(|()| rule.SetEnabled(VARIANT_TRUE))
+            .map_err(|e| format!("Failed to set up the firewall rule: {}", e))?;
+        rules.Add(&rule).map_err(|e| format!("Failed to add the firewall rule (run as administrator): {}", e))?;
+    }
+    info!("Added firewall rule \"{}\" for TCP port {}", RULE_NAME, port);
+    Ok(())
+}
+
+/// Delete TouchRelay's inbound rule if there is one (`--remove-firewall-rule`, needs administrator rights)
+pub fn remove_rule() -> Result<(), String> {
+    let rules = unsafe { policy()?.Rules() }.map_err(|e| format!("Failed to read firewall rules: {}", e))?;
+    let name = BSTR::from(RULE_NAME);
+    // Rule names need not be unique
+    while unsafe { rules.Item(&name) }.is_ok() {
+        unsafe { rules.Remove(&name) }.map_err(|e| format!("Failed to remove the firewall rule (run as administrator): {}", e))?;
+        info!("Removed firewall rule \"{}\"", RULE_NAME);
+    }
+    Ok(())
+}
+
+/// Add or remove the rule from the tray, asking for administrator rights unless TouchRelay has them.
+/// Blocks, so it is called from worker threads.
+#[cfg(feature = "gui")]
+pub fn set_rule_elevated(enabled: bool, port: u16) -> Result<(), String> {
+    let result = match (enabled, crate::elevation::is_elevated()) {
+        (true, true) => add_rule(port),
+        (false, true) => remove_rule(),
+        (true, false) => crate::elevation::run_elevated(&format!("--add-firewall-rule --port {}", port)),
+        (false, false) => crate::elevation::run_elevated("--remove-firewall-rule"),
+    };
+    check_rule();
+    result
+}
+
+/// Ask Windows Firewall whether other devices may reach this executable on a TCP port
+#[cfg(feature = "gui")]
+pub fn is_port_allowed(port: u16) -> Result<bool, String> {
+    let exe = std::env::current_exe().map_err(|e| format!("Failed to locate TouchRelay: {}", e))?;
+    unsafe {
+        let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
+        let manager: INetFwMgr = CoCreateInstance(&NetFwMgr, None, CLSCTX_INPROC_SERVER)
+            .map_err(|e| format!("Failed to query Windows Firewall: {}", e))?;
+
+        let mut allowed = VARIANT::default();
+        let mut restricted = VARIANT::default();
+        manager
+            .IsPortAllowed(
+                &BSTR::from(exe.to_string_lossy().as_ref()),
+                NET_FW_IP_VERSION_ANY,
+                port.into(),
+                &BSTR::new(),
+                NET_FW_IP_PROTOCOL_TCP,
+                &mut allowed,
+                &mut restricted,
+            )
+            .map_err(|e| format!("Failed to query Windows Firewall: {}", e))?;
+        Ok(bool::try_from(&allowed).unwrap_or(false))
+    }
+}
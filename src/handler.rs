@@ -1,153 +1,1033 @@
-use axum::extract::ws::{Message, WebSocket};
-use enigo::{Enigo, Mouse, Button, Keyboard, Direction, Settings, Key};
-use serde_json::Value;
-use std::sync::Arc;
+use enigo::{Axis, Button, Direction, Key};
+use futures::{SinkExt, StreamExt};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::net::SocketAddr;
 use tokio::sync::Mutex;
-use tracing::{error, info, warn};
+use tokio::time::{Duration, Instant};
+use tracing::{debug, error, info, warn};
+use yawc::close::CloseCode;
+use yawc::frame::{Frame, OpCode};
+use yawc::HttpWebSocket as WebSocket;
+
+use crate::backend::InputBackend;
+use crate::brightness::Change;
+use crate::capability::Capability;
+use crate::eventlog::{self, EventId, Level};
+
+/// Version of the WebSocket message protocol spoken by the embedded web client
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Interval at which joystick deflection is turned into cursor motion
+pub const JOYSTICK_TICK: Duration = Duration::from_millis(4);
+// Stop the cursor if the client stops refreshing its joystick vector (e.g. lost Wi-Fi)
+const JOYSTICK_TIMEOUT: Duration = Duration::from_millis(500);
+// Longest step integrated at once, so a stalled tick does not jump the cursor
+const JOYSTICK_MAX_STEP: Duration = Duration::from_millis(50);
+
+/// Query parameters sent by the web client when opening the WebSocket
+#[derive(Deserialize)]
+pub struct ConnectParams {
+    /// Persistent device ID, dropped if it is not a valid ID
+    #[serde(default, deserialize_with = "valid_device_id")]
+    device: Option<String>,
+    /// Protocol version of the client's app.js
+    v: Option<u32>,
+}
+
+/// Longest device ID accepted from a client
+const MAX_DEVICE_ID_LEN: usize = 64;
+
+/// Keep a device ID only if it is short and made of `[A-Za-z0-9_-]`, so it is
+/// safe to show in prompts, logs and config keys
+pub(crate) fn valid_device_id<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    let id = Option::<String>::deserialize(deserializer)?;
+    Ok(id.filter(|id| {
+        let valid = !id.is_empty()
+            && id.len() <= MAX_DEVICE_ID_LEN
+            && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if !valid {
+            warn!("Ignoring invalid device ID from client");
+        }
+        valid
+    }))
+}
+
+/// Which WebSocket endpoint a connection came in on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endpoint {
+    /// The LAN-facing `/ws` behind Basic auth and pairing approval
+    Lan,
+    /// The loopback-only endpoint for trusted native companion apps
+    Companion,
+}
+
+/// State of a single WebSocket session
+pub struct Session {
+    client_id: u64,
+    device_id: Option<String>,
+    backend: Mutex<Box<dyn InputBackend>>,
+    clock: Mutex<crate::latency::Clock>,
+    // Keys currently held down with `kd`
+    held_keys: Mutex<HashSet<Key>>,
+    // Select mode: the left button is held down until `su` or disconnect
+    selecting: Mutex<bool>,
+    joystick: std::sync::Mutex<Joystick>,
+    // Connection to the passthrough program, opened on first use
+    #[cfg(feature = "cap-passthrough")]
+    passthrough: Mutex<Option<crate::passthrough::Bridge>>,
+    // Fractional wheel steps (vertical, horizontal) left over after scaling
+    scroll_remainder: std::sync::Mutex<(f64, f64)>,
+    // Sub-pixel pointer motion left over after applying `pointer_speed`
+    move_remainder: std::sync::Mutex<(f64, f64)>,
+    // Whether input is dropped while another Windows user has the console or the
+    // physical mouse is in use; off for the selftest's mock backend
+    environment_gates: bool,
+}
+
+/// Deflection of the virtual joystick, integrated into cursor motion between messages
+struct Joystick {
+    x: f64,
+    y: f64,
+    // When the client last sent the vector
+    updated: Instant,
+    // When motion was last integrated
+    last_tick: Instant,
+    // Sub-pixel motion carried over to the next tick
+    remainder: (f64, f64),
+}
+
+impl Session {
+    pub fn new(client_id: u64, device_id: Option<String>, backend: Box<dyn InputBackend>) -> Self {
+        Self {
+            client_id,
+            device_id,
+            backend: Mutex::new(backend),
+            clock: Mutex::new(crate::latency::Clock::new()),
+            held_keys: Mutex::new(HashSet::new()),
+            selecting: Mutex::new(false),
+            joystick: std::sync::Mutex::new(Joystick {
+                x: 0.0,
+                y: 0.0,
+                updated: Instant::now(),
+                last_tick: Instant::now(),
+                remainder: (0.0, 0.0),
+            }),
+            #[cfg(feature = "cap-passthrough")]
+            passthrough: Mutex::new(None),
+            scroll_remainder: std::sync::Mutex::new((0.0, 0.0)),
+            move_remainder: std::sync::Mutex::new((0.0, 0.0)),
+            environment_gates: true,
+        }
+    }
+
+    /// Inject regardless of the Windows session and local input, which say nothing
+    /// about a mock backend
+    pub fn without_environment_gates(mut self) -> Self {
+        self.environment_gates = false;
+        self
+    }
+
+    /// Apply the device's scroll multipliers and direction to wheel steps,
+    /// carrying fractions over so slow multipliers still scroll eventually
+    fn scale_scroll(&self, dy: i64, dx: i64) -> (i32, i32) {
+        let scroll = crate::config::get().scroll_for(self.device_id.as_deref());
+        let sign = |invert: bool| if invert { -1.0 } else { 1.0 };
+
+        let mut remainder = self.scroll_remainder.lock().unwrap();
+        let y = dy as f64 * scroll.vertical * sign(scroll.invert_vertical) + remainder.0;
+        let x = dx as f64 * scroll.horizontal * sign(scroll.invert_horizontal) + remainder.1;
+        *remainder = (y.fract(), x.fract());
+        (y.trunc() as i32, x.trunc() as i32)
+    }
+
+    /// Apply the PC's `pointer_speed` to a pointer move, carrying fractions over
+    fn scale_move(&self, dx: i32, dy: i32) -> (i32, i32) {
+        let (min, max) = crate::config::POINTER_SPEED_RANGE;
+        let speed = crate::config::get().pointer_speed;
+        if speed == 1.0 || !speed.is_finite() {
+            return (dx, dy);
+        }
+        let speed = speed.clamp(min, max);
+
+        let mut remainder = self.move_remainder.lock().unwrap();
+        let x = dx as f64 * speed + remainder.0;
+        let y = dy as f64 * speed + remainder.1;
+        *remainder = (x.fract(), y.fract());
+        (x.trunc() as i32, y.trunc() as i32)
+    }
+
+    /// Check whether the joystick is deflected and the client is still refreshing it
+    pub fn joystick_active(&self) -> bool {
+        let joystick = self.joystick.lock().unwrap();
+        (joystick.x != 0.0 || joystick.y != 0.0) && joystick.updated.elapsed() < JOYSTICK_TIMEOUT
+    }
+
+    /// Let go of the joystick, as if the client had sent `["j", 0, 0]`
+    fn stop_joystick(&self) {
+        let mut joystick = self.joystick.lock().unwrap();
+        joystick.x = 0.0;
+        joystick.y = 0.0;
+        joystick.remainder = (0.0, 0.0);
+    }
+
+    /// Move the cursor by the motion accumulated since the previous tick
+    pub async fn tick_joystick(&self) {
+        // Stop for good rather than resume once input is paused, taken over or the console
+        // switched to another user; the client deflects the joystick again to continue
+        if crate::pause::is_paused() || !crate::clients::has_control(self.client_id) || !crate::winsession::is_active() {
+            self.stop_joystick();
+            return;
+        }
+        let (dx, dy) = {
+            let mut joystick = self.joystick.lock().unwrap();
+            let now = Instant::now();
+            let step = now.duration_since(joystick.last_tick).min(JOYSTICK_MAX_STEP).as_secs_f64();
+            joystick.last_tick = now;
+
+            let speed = crate::config::get().joystick_speed;
+            let x = joystick.x * speed * step + joystick.remainder.0;
+            let y = joystick.y * speed * step + joystick.remainder.1;
+            joystick.remainder = (x.fract(), y.fract());
+            (x.trunc() as i64, y.trunc() as i64)
+        };
+
+        // Goes through the regular path so pause, policy and control checks apply
+        if dx != 0 || dy != 0 {
+            if let Err(e) = handle_message(&json!(["m", dx, dy]).to_string(), self).await {
+                warn!("Joystick move failed: {}", e);
+            }
+        }
+    }
+
+    /// Leave select mode, letting go of the left button
+    pub async fn end_select_mode(&self) -> Result<(), String> {
+        let mut selecting = self.selecting.lock().await;
+        if *selecting {
+            self.backend.lock().await.button(Button::Left, Direction::Release)?;
+            *selecting = false;
+        }
+        Ok(())
+    }
+
+    /// Release every key this session still holds down
+    pub async fn release_held_keys(&self) {
+        let mut held_keys = self.held_keys.lock().await;
+        let mut backend = self.backend.lock().await;
+        for key in held_keys.drain() {
+            if let Err(e) = backend.key(key, Direction::Release) {
+                warn!("Failed to release held key {:?}: {}", key, e);
+            }
+        }
+    }
+}
+
+/// Map a key name used by the gamepad layout to a key that can be held down
+fn holdable_key(name: &str) -> Result<Key, String> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Ok(Key::Unicode(c.to_ascii_lowercase()));
+    }
+
+    Ok(match name {
+        "Space" => Key::Space,
+        "Shift" => Key::Shift,
+        "Control" => Key::Control,
+        "Alt" => Key::Alt,
+        "Tab" => Key::Tab,
+        "Return" => Key::Return,
+        "Escape" => Key::Escape,
+        "ArrowUp" => Key::UpArrow,
+        "ArrowDown" => Key::DownArrow,
+        "ArrowLeft" => Key::LeftArrow,
+        "ArrowRight" => Key::RightArrow,
+        _ => return Err(format!("Unknown key: {}", name)),
+    })
+}
+
+/// Send a JSON message to the client
+async fn send_json(socket: &mut WebSocket, msg: Value) -> Result<(), yawc::WebSocketError> {
+    socket.send(Frame::text(msg.to_string())).await
+}
+
+/// Close the connection with a code and human readable reason
+async fn close(socket: &mut WebSocket, code: CloseCode, reason: &str) {
+    let _ = socket.send(Frame::close(code, reason)).await;
+}
 
 /// Handle WebSocket connection
-pub async fn handle_socket(mut socket: WebSocket) {
-    info!("WebSocket connection established");
-
-    // Create Enigo instance for this connection
-    let enigo = match Enigo::new(&Settings::default()) {
-        Ok(e) => Arc::new(Mutex::new(e)),
-        Err(err) => {
-            error!("Failed to create Enigo instance: {}", err);
+pub async fn handle_socket(mut socket: WebSocket, params: ConnectParams, addr: SocketAddr, endpoint: Endpoint) {
+    let device_id = params.device;
+    info!("WebSocket connection established from {} (device: {}, {:?})", addr.ip(), device_id.as_deref().unwrap_or("unknown"), endpoint);
+
+    // Introduce the server so the client can detect updated assets
+    let hello = json!(["hello", {
+        "version": env!("CARGO_PKG_VERSION"),
+        "protocol": PROTOCOL_VERSION,
+        "assets": crate::assets::asset_version(),
+        "screen": crate::display::screen_info(),
+        "session": crate::winsession::status(crate::winsession::is_active()),
+    }]);
+    if let Err(e) = send_json(&mut socket, hello).await {
+        warn!("Failed to send hello: {}", e);
+        return;
+    }
+
+    // A cached app.js speaking another protocol version must reload before it can be used
+    if params.v != Some(PROTOCOL_VERSION) {
+        info!("Client protocol {:?} does not match {}, requesting reload", params.v, PROTOCOL_VERSION);
+        let _ = send_json(&mut socket, json!(["reload"])).await;
+        close(&mut socket, CloseCode::Normal, "Protocol mismatch").await;
+        return;
+    }
+
+    // First-time devices may need the host's approval (companion apps on this PC are trusted)
+    if endpoint == Endpoint::Lan && crate::pairing::needs_approval(device_id.as_deref()) {
+        if send_json(&mut socket, json!(["pairing", "pending"])).await.is_err() {
+            return;
+        }
+
+        if !crate::pairing::request_approval(device_id.as_deref(), addr).await {
+            let _ = send_json(&mut socket, json!(["pairing", "denied"])).await;
+            close(&mut socket, CloseCode::Policy, "Pairing denied").await;
+            return;
+        }
+
+        if send_json(&mut socket, json!(["pairing", "approved"])).await.is_err() {
+            return;
+        }
+    }
+
+    // Create the input backend for this connection
+    let backend = match crate::backend::desktop() {
+        Ok(backend) => backend,
+        Err(e) => {
+            error!("{}", e);
+            return;
+        }
+    };
+
+    let (client_id, mut outbound) = match crate::clients::register(endpoint == Endpoint::Lan, device_id.as_deref(), Some(addr.ip())) {
+        Ok(registered) => registered,
+        Err(e) => {
+            warn!("Rejecting client from {}: {}", addr.ip(), e);
+            eventlog::report(Level::Warning, EventId::ClientKicked, &format!("Rejected client from {}: {}", addr.ip(), e));
+            let _ = send_json(&mut socket, json!(["busy", "full"])).await;
+            close(&mut socket, CloseCode::Again, "Too many clients").await;
             return;
         }
     };
 
-    while let Some(msg) = socket.recv().await {
-        match msg {
-            Ok(Message::Text(text)) => {
-                if let Err(e) = handle_message(&text, Arc::clone(&enigo)).await {
-                    warn!("Failed to handle message: {} - Error: {}", text, e);
+    let session = Session::new(client_id, device_id, backend);
+    let release = crate::clients::release_signal(client_id).unwrap_or_default();
+    let client_details = json!({ "client": client_id, "device": session.device_id, "ip": addr.ip().to_string() });
+    crate::webhooks::notify("connect", client_details.clone());
+    if crate::config::get().notify_on_connect {
+        notify_connected(session.device_id.as_deref(), addr.ip());
+    }
+    let mut recorder = crate::recording::Recorder::start(client_id, session.device_id.as_deref());
+
+    // Apply the device's stored input profile
+    if let Some(profile) = crate::config::get().profile_for(session.device_id.as_deref()) {
+        if let Err(e) = send_json(&mut socket, json!(["profile", profile])).await {
+            warn!("Failed to send input profile: {}", e);
+        }
+    }
+
+    // Let the client know if input is currently paused
+    if crate::pause::is_paused() {
+        if let Err(e) = send_json(&mut socket, json!(["paused", true])).await {
+            warn!("Failed to send pause state: {}", e);
+        }
+    }
+
+    // Show what is playing in the media panel
+    if let Some(now_playing) = crate::media::current() {
+        if let Err(e) = send_json(&mut socket, json!(["now_playing", now_playing])).await {
+            warn!("Failed to send now playing: {}", e);
+        }
+    }
+
+    // In exclusive mode, later clients wait until the controller leaves
+    if !crate::clients::has_control(client_id) {
+        if let Err(e) = send_json(&mut socket, json!(["busy", "controlled"])).await {
+            warn!("Failed to send busy notice: {}", e);
+        }
+    }
+
+    // Drop clients that go silent (e.g. phones that wandered off Wi-Fi)
+    let idle_timeout = Duration::from_secs(crate::config::get().idle_timeout_secs);
+    let idle = tokio::time::sleep(idle_timeout);
+    tokio::pin!(idle);
+
+    // Ping the client regularly and drop it if a pong does not come back before the next ping
+    let ping_interval = Duration::from_secs(crate::config::get().ping_interval_secs);
+    let mut keepalive = tokio::time::interval_at(Instant::now() + ping_interval, ping_interval.max(Duration::from_secs(1)));
+    let mut awaiting_pong = false;
+
+    let mut joystick = tokio::time::interval(JOYSTICK_TICK);
+    joystick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+    loop {
+        tokio::select! {
+            frame = socket.next() => match frame {
+                Some(frame) if frame.opcode() == OpCode::Text => {
+                    idle.as_mut().reset(Instant::now() + idle_timeout);
+                    let text = frame.as_str();
+                    match handle_message(text, &session).await {
+                        Ok(()) => {
+                            if let Some(recorder) = recorder.as_mut() {
+                                recorder.record(text);
+                            }
+                        }
+                        Err(e) => {
+                            warn!("Failed to handle message: {} - Error: {}", text, e);
+                        }
+                    }
+                }
+                Some(frame) if frame.opcode() == OpCode::Pong => {
+                    awaiting_pong = false;
+                }
+                Some(frame) if frame.opcode() == OpCode::Close => {
+                    info!("WebSocket connection closed");
+                    break;
+                }
+                // Read errors end the stream
+                None => {
+                    info!("WebSocket connection lost");
+                    break;
+                }
+                // Pings are answered by yawc itself
+                _ => {}
+            },
+
+            // Messages pushed by the server (pause notices, shutdown, etc.)
+            Some(msg) = outbound.recv() => {
+                let closing = msg.opcode() == OpCode::Close;
+                if let Err(e) = socket.send(msg).await {
+                    error!("WebSocket send error: {}", e);
+                    break;
+                }
+                if closing {
+                    info!("Closed WebSocket connection from {}", addr.ip());
+                    break;
                 }
             }
-            Ok(Message::Close(_)) => {
-                info!("WebSocket connection closed");
-                break;
+
+            _ = keepalive.tick(), if !ping_interval.is_zero() => {
+                if awaiting_pong {
+                    info!("Closing dead WebSocket connection from {} (no pong within {:?})", addr.ip(), ping_interval);
+                    eventlog::report(Level::Info, EventId::ClientKicked, &format!("Disconnected unresponsive client {}", addr.ip()));
+                    break;
+                }
+                if let Err(e) = socket.send(Frame::ping("")).await {
+                    error!("WebSocket send error: {}", e);
+                    break;
+                }
+                awaiting_pong = true;
+            }
+
+            // Input paused or control handed to another client: let go of the joystick, keys and selection button
+            _ = release.notified() => {
+                session.stop_joystick();
+                session.release_held_keys().await;
+                match session.end_select_mode().await {
+                    Ok(()) => {
+                        if let Err(e) = send_json(&mut socket, json!(["select", false])).await {
+                            warn!("Failed to send select mode: {}", e);
+                        }
+                    }
+                    Err(e) => warn!("Failed to end select mode: {}", e),
+                }
+            }
+
+            // Continuous cursor motion while the joystick is deflected
+            _ = joystick.tick(), if session.joystick_active() => {
+                session.tick_joystick().await;
             }
-            Err(e) => {
-                error!("WebSocket error: {}", e);
+
+            _ = &mut idle, if !idle_timeout.is_zero() => {
+                info!("Closing idle WebSocket connection (no messages for {:?})", idle_timeout);
+                eventlog::report(Level::Info, EventId::ClientKicked, &format!("Disconnected idle client {}", addr.ip()));
+                close(&mut socket, CloseCode::Normal, "Idle timeout").await;
                 break;
             }
-            _ => {}
         }
     }
+
+    // Never leave keys or the selection button stuck down after the phone goes away
+    session.release_held_keys().await;
+    if let Err(e) = session.end_select_mode().await {
+        warn!("Failed to end select mode: {}", e);
+    }
+    crate::clients::unregister(client_id);
+    crate::webhooks::notify("disconnect", client_details);
+}
+
+/// Tell the PC's user which device just connected, e.g. "iPhone (192.168.1.23) connected to TouchRelay"
+fn notify_connected(device_id: Option<&str>, ip: std::net::IpAddr) {
+    let device = crate::config::get().device_name(device_id).unwrap_or_else(|| crate::i18n::t("notify.unknown_device"));
+    let message = crate::i18n::t_with("notify.device_connected", &[("device", &device), ("ip", &ip.to_string())]);
+    // Showing a toast blocks until Windows has taken it
+    tokio::task::spawn_blocking(move || crate::notify::show("TouchRelay", &message));
+}
+
+/// Store a device's sensitivity, scroll direction and layout in its `[devices]` profile
+fn save_preferences(device_id: Option<&str>, preferences: &serde_json::Map<String, Value>) -> Result<(), String> {
+    let device_id = device_id.ok_or("Preferences need a device ID")?;
+
+    let sensitivity = match preferences.get("sensitivity") {
+        Some(value) => {
+            let sensitivity = value.as_f64().ok_or("Invalid sensitivity")?;
+            if !(0.5..=3.0).contains(&sensitivity) {
+                return Err("Sensitivity must be between 0.5 and 3.0".to_string());
+            }
+            Some((sensitivity * 10.0).round() / 10.0)
+        }
+        None => None,
+    };
+    let natural_scroll = match preferences.get("natural_scroll") {
+        Some(value) => Some(value.as_bool().ok_or("Invalid natural_scroll")?),
+        None => None,
+    };
+    let layout = match preferences.get("layout") {
+        Some(value) => {
+            let layout = value.as_str().ok_or("Invalid layout")?;
+            if layout.is_empty() || layout.len() > 64 {
+                return Err("Invalid layout".to_string());
+            }
+            Some(layout.to_string())
+        }
+        None => None,
+    };
+
+    // Unknown devices may not add themselves while pairing approval is required
+    let config = crate::config::get();
+    if !config.devices.contains_key(device_id) && config.require_pairing_approval {
+        return Err("Device is not paired".to_string());
+    }
+    // Skip the file write when nothing changed (e.g. the slider was moved back)
+    let stored = config.devices.get(device_id).and_then(|device| device.profile.clone()).unwrap_or_default();
+    if sensitivity.is_none_or(|s| stored.sensitivity == Some(s))
+        && natural_scroll.is_none_or(|n| stored.natural_scroll == Some(n))
+        && layout.as_ref().is_none_or(|l| stored.layout.as_ref() == Some(l))
+    {
+        return Ok(());
+    }
+
+    crate::config::update(|config| {
+        let device = config.device_entry(device_id);
+        let profile = device.profile.get_or_insert_with(Default::default);
+        if sensitivity.is_some() {
+            profile.sensitivity = sensitivity;
+        }
+        if natural_scroll.is_some() {
+            profile.natural_scroll = natural_scroll;
+        }
+        if layout.is_some() {
+            profile.layout = layout;
+        }
+    })?;
+    info!("Saved preferences of device {}", device_id);
+    Ok(())
+}
+
+/// Run a single command outside a WebSocket connection (REST API) as a short-lived client,
+/// so pause, policy and exclusive control apply. Keys and buttons are released afterwards.
+/// Returns why the command was dropped, if it was.
+pub async fn run_once(text: &str, device_id: Option<String>) -> Result<Option<Dropped>, String> {
+    let backend = crate::backend::desktop()?;
+    let (client_id, _outbound) = crate::clients::register_one_shot(device_id.as_deref())?;
+    let session = Session::new(client_id, device_id, backend);
+
+    // Callers are told about dropped input, unlike WebSocket clients
+    let capability = serde_json::from_str::<Value>(text)
+        .ok()
+        .and_then(|msg| msg.get(0)?.as_str().and_then(Capability::for_command));
+    if let Some(reason) = capability.and_then(|capability| dropped(capability, &session)) {
+        crate::clients::unregister(client_id);
+        return Ok(Some(reason));
+    }
+
+    let result = handle_message(text, &session).await;
+    session.release_held_keys().await;
+    let released = session.end_select_mode().await;
+    crate::clients::unregister(client_id);
+    result.and(released).map(|()| None)
 }
 
 /// Handle incoming WebSocket message
-async fn handle_message(text: &str, enigo: Arc<Mutex<Enigo>>) -> Result<(), String> {
+pub async fn handle_message(text: &str, session: &Session) -> Result<(), String> {
     let msg: Value = serde_json::from_str(text)
         .map_err(|e| format!("JSON parse error: {}", e))?;
 
-    if let Value::Array(arr) = msg {
-        if arr.is_empty() {
-            return Err("Empty message array".to_string());
+    let Value::Array(arr) = msg else {
+        return Err("Message is not an array".to_string());
+    };
+    if arr.is_empty() {
+        return Err("Empty message array".to_string());
+    }
+
+    let cmd = arr[0].as_str().ok_or("Invalid command type")?;
+
+    let started = Instant::now();
+    let result = execute(cmd, &arr, session).await;
+    crate::metrics::record_command(cmd, result.is_ok(), started.elapsed());
+    result
+}
+
+/// Tell every client the output volume, so their volume sliders follow it
+fn send_volume() -> Result<(), String> {
+    let (level, muted) = crate::audio::volume()?;
+    crate::clients::broadcast(&json!(["vol", level, muted]));
+    Ok(())
+}
+
+/// Why input is dropped silently instead of being injected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dropped {
+    /// The kill-switch is engaged
+    Paused,
+    /// Another user has the console (fast user switching); nobody would see our input
+    SessionInactive,
+    /// The physical mouse is in use and wins over remote pointer input
+    LocalInput,
+    /// Another client has control in exclusive mode
+    NotController,
+}
+
+/// Check whether input needing `capability` is currently dropped for this client
+fn dropped(capability: Capability, session: &Session) -> Option<Dropped> {
+    if crate::pause::is_paused() {
+        Some(Dropped::Paused)
+    } else if session.environment_gates && !crate::winsession::is_active() {
+        Some(Dropped::SessionInactive)
+    } else if session.environment_gates && capability == Capability::Pointer && crate::activity::is_local_input_active() {
+        Some(Dropped::LocalInput)
+    } else if !crate::clients::has_control(session.client_id) {
+        Some(Dropped::NotController)
+    } else {
+        None
+    }
+}
+
+/// Check the sender's permissions and run one command
+async fn execute(cmd: &str, arr: &[Value], session: &Session) -> Result<(), String> {
+    // Check the device's permission policy before executing anything
+    if let Some(capability) = Capability::for_command(cmd) {
+        if dropped(capability, session).is_some() {
+            return Ok(());
         }
+        if !capability.is_compiled_in() {
+            return Err(format!("Command '{}' not available in this build ({:?} capability)", cmd, capability));
+        }
+        if !crate::policy::get().allows(capability) {
+            return Err(format!("Command '{}' disabled by policy ({:?} capability)", cmd, capability));
+        }
+        if !crate::config::get().is_allowed(session.device_id.as_deref(), capability) {
+            return Err(format!("Command '{}' not permitted ({:?} capability denied)", cmd, capability));
+        }
+        // Remote input turns displays back on that a client switched off
+        #[cfg(feature = "cap-power")]
+        if cmd != "display" {
+            crate::power::wake_displays();
+        }
+    }
 
-        let cmd = arr[0].as_str().ok_or("Invalid command type")?;
+    match cmd {
+        "m" => {
+            // Mouse move: ["m", dx, dy, timestamp?]
+            if arr.len() < 3 {
+                return Err("Invalid mouse move message".to_string());
+            }
+            let dx = arr[1].as_i64().ok_or("Invalid dx")? as i32;
+            let dy = arr[2].as_i64().ok_or("Invalid dy")? as i32;
 
-        match cmd {
-            "m" => {
-                // Mouse move: ["m", dx, dy]
-                if arr.len() < 3 {
-                    return Err("Invalid mouse move message".to_string());
+            // Replay moves queued up during a Wi-Fi stall at their original pace
+            if let Some(timestamp) = arr.get(3).and_then(|v| v.as_f64()) {
+                let delay = session.clock.lock().await.schedule(timestamp);
+                if !delay.is_zero() {
+                    tokio::time::sleep(delay).await;
                 }
-                let dx = arr[1].as_i64().ok_or("Invalid dx")? as i32;
-                let dy = arr[2].as_i64().ok_or("Invalid dy")? as i32;
+            }
 
-                let mut enigo = enigo.lock().await;
-                enigo.move_mouse(dx, dy, enigo::Coordinate::Rel)
-                    .map_err(|e| format!("Mouse move failed: {}", e))?;
+            let (dx, dy) = session.scale_move(dx, dy);
+            let mut backend = session.backend.lock().await;
+            backend.move_mouse(dx, dy)
+                .map_err(|e| format!("Mouse move failed: {}", e))?;
+        }
+
+        "j" => {
+            // Joystick deflection: ["j", x, y] with x and y in -1..1, resent
+            // periodically while held and ["j", 0, 0] on release
+            if arr.len() < 3 {
+                return Err("Invalid joystick message".to_string());
+            }
+            let x = arr[1].as_f64().ok_or("Invalid x")?;
+            let y = arr[2].as_f64().ok_or("Invalid y")?;
+            if !x.is_finite() || !y.is_finite() {
+                return Err("Invalid joystick vector".to_string());
             }
 
-            "b" => {
-                // Button click: ["b", "l"|"r", 1|2]
-                if arr.len() < 3 {
-                    return Err("Invalid button click message".to_string());
-                }
-                let button_type = arr[1].as_str().ok_or("Invalid button type")?;
-                let click_count = arr[2].as_u64().ok_or("Invalid click count")? as u32;
+            // Square the magnitude for fine control near the center
+            let magnitude = x.hypot(y);
+            let scale = if magnitude > 0.0 { magnitude.min(1.0).powi(2) / magnitude } else { 0.0 };
 
-                let button = match button_type {
-                    "l" => Button::Left,
-                    "r" => Button::Right,
-                    _ => return Err(format!("Unknown button type: {}", button_type)),
-                };
+            let was_active = session.joystick_active();
+            let mut joystick = session.joystick.lock().unwrap();
+            let now = Instant::now();
+            if !was_active {
+                joystick.last_tick = now;
+                joystick.remainder = (0.0, 0.0);
+            }
+            joystick.x = x * scale;
+            joystick.y = y * scale;
+            joystick.updated = now;
+        }
 
-                let mut enigo = enigo.lock().await;
-                for _ in 0..click_count {
-                    enigo.button(button, Direction::Click)
-                        .map_err(|e| format!("Button click failed: {}", e))?;
-                    // Add small delay between double clicks
-                    if click_count > 1 {
-                        tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
-                    }
-                }
+        "b" => {
+            // Button click: ["b", "l"|"r", 1|2]
+            if arr.len() < 3 {
+                return Err("Invalid button click message".to_string());
             }
+            let button_type = arr[1].as_str().ok_or("Invalid button type")?;
+            let click_count = arr[2].as_u64().ok_or("Invalid click count")? as u32;
 
-            "w" => {
-                // Mouse wheel: ["w", dy]
-                if arr.len() < 2 {
-                    return Err("Invalid wheel message".to_string());
+            let button = match button_type {
+                "l" => Button::Left,
+                "r" => Button::Right,
+                _ => return Err(format!("Unknown button type: {}", button_type)),
+            };
+
+            let mut backend = session.backend.lock().await;
+            for _ in 0..click_count {
+                backend.button(button, Direction::Click)
+                    .map_err(|e| format!("Button click failed: {}", e))?;
+                // Add small delay between double clicks
+                if click_count > 1 {
+                    tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
                 }
-                let dy = arr[1].as_i64().ok_or("Invalid dy")? as i32;
+            }
+        }
+
+        "sd" => {
+            // Enter select mode: ["sd"]. Cursor moves drag with the left button held
+            // until "su", so long selections don't need a finger kept down.
+            let mut selecting = session.selecting.lock().await;
+            if !*selecting {
+                session.backend.lock().await.button(Button::Left, Direction::Press)
+                    .map_err(|e| format!("Select mode failed: {}", e))?;
+                *selecting = true;
+            }
+        }
+
+        "su" => {
+            // Leave select mode: ["su"]. Always allowed, like "ku".
+            session.end_select_mode().await
+                .map_err(|e| format!("Ending select mode failed: {}", e))?;
+        }
 
-                let mut enigo = enigo.lock().await;
-                // Convert dy to scroll amount (positive = scroll up, negative = scroll down)
-                enigo.scroll(dy, enigo::Axis::Vertical)
+        "w" => {
+            // Mouse wheel: ["w", dy, dx?]
+            if arr.len() < 2 {
+                return Err("Invalid wheel message".to_string());
+            }
+            let dy = arr[1].as_i64().ok_or("Invalid dy")?;
+            let dx = match arr.get(2) {
+                Some(dx) => dx.as_i64().ok_or("Invalid dx")?,
+                None => 0,
+            };
+            let (dy, dx) = session.scale_scroll(dy, dx);
+
+            let mut backend = session.backend.lock().await;
+            // Convert dy to scroll amount (positive = scroll up, negative = scroll down)
+            if dy != 0 {
+                backend.scroll(dy, Axis::Vertical)
                     .map_err(|e| format!("Wheel scroll failed: {}", e))?;
             }
+            if dx != 0 {
+                backend.scroll(dx, Axis::Horizontal)
+                    .map_err(|e| format!("Horizontal scroll failed: {}", e))?;
+            }
+        }
+
+        "t" => {
+            // Text input: ["t", "text content"]
+            if arr.len() < 2 {
+                return Err("Invalid text message".to_string());
+            }
+            let text_content = arr[1].as_str().ok_or("Invalid text content")?;
+
+            let mut backend = session.backend.lock().await;
+            backend.text(text_content)
+                .map_err(|e| format!("Text input failed: {}", e))?;
+        }
+
+        "k" => {
+            // Key press: ["k", "KeyName"]
+            if arr.len() < 2 {
+                return Err("Invalid key press message".to_string());
+            }
+            let key_name = arr[1].as_str().ok_or("Invalid key name")?;
+
+            let key = match key_name {
+                "Escape" => Key::Escape,
+                "PageUp" => Key::PageUp,
+                "PageDown" => Key::PageDown,
+                "Delete" => Key::Backspace,  // Del button sends Backspace key
+                "Return" => Key::Return,
+                _ => return Err(format!("Unknown key: {}", key_name)),
+            };
+
+            let mut backend = session.backend.lock().await;
+            backend.key(key, Direction::Click)
+                .map_err(|e| format!("Key press failed: {}", e))?;
+            info!("Key pressed: {} (mapped to {:?})", key_name, key);
+        }
+
+        "kd" => {
+            // Hold a key down: ["kd", "KeyName"] (released with "ku" or on disconnect)
+            if arr.len() < 2 {
+                return Err("Invalid key down message".to_string());
+            }
+            let key = holdable_key(arr[1].as_str().ok_or("Invalid key name")?)?;
+
+            let mut held_keys = session.held_keys.lock().await;
+            if held_keys.contains(&key) {
+                return Ok(());
+            }
+            let max_held_keys = crate::config::get().max_held_keys;
+            if held_keys.len() >= max_held_keys {
+                return Err(format!("Too many keys held (limit {})", max_held_keys));
+            }
+
+            let mut backend = session.backend.lock().await;
+            backend.key(key, Direction::Press)
+                .map_err(|e| format!("Key down failed: {}", e))?;
+            held_keys.insert(key);
+        }
+
+        "ku" => {
+            // Release a held key: ["ku", "KeyName"]. Always allowed, so keys
+            // held before a pause or loss of control can still be let go.
+            if arr.len() < 2 {
+                return Err("Invalid key up message".to_string());
+            }
+            let key = holdable_key(arr[1].as_str().ok_or("Invalid key name")?)?;
+
+            if session.held_keys.lock().await.remove(&key) {
+                let mut backend = session.backend.lock().await;
+                backend.key(key, Direction::Release)
+                    .map_err(|e| format!("Key up failed: {}", e))?;
+            }
+        }
+
+        "media" => {
+            // Media transport key: ["media", "play_pause"|"next"|"prev"|"stop"]
+            if arr.len() < 2 {
+                return Err("Invalid media message".to_string());
+            }
+            let action = arr[1].as_str().ok_or("Invalid media action")?;
+
+            let key = match action {
+                "play_pause" => Key::MediaPlayPause,
+                "next" => Key::MediaNextTrack,
+                "prev" => Key::MediaPrevTrack,
+                "stop" => Key::MediaStop,
+                _ => return Err(format!("Unknown media action: {}", action)),
+            };
+
+            let mut backend = session.backend.lock().await;
+            backend.key(key, Direction::Click)
+                .map_err(|e| format!("Media key failed: {}", e))?;
+            info!("Media key pressed: {}", action);
+        }
+
+        "vol" => {
+            // System volume: ["vol", "up"|"down"|"mute"], ["vol", "set", 0..100] or ["vol", "get"].
+            // Setting or asking for the level answers every client with ["vol", level, muted].
+            if arr.len() < 2 {
+                return Err("Invalid volume message".to_string());
+            }
+            let action = arr[1].as_str().ok_or("Invalid volume action")?;
+
+            let key = match action {
+                "up" => Key::VolumeUp,
+                "down" => Key::VolumeDown,
+                "mute" => Key::VolumeMute,
+                "set" => {
+                    let level = arr.get(2).and_then(|v| v.as_f64()).ok_or("Invalid volume level")?;
+                    let percent = level.clamp(0.0, 100.0).round() as u8;
+                    crate::audio::set_volume(percent)?;
+                    info!("Volume set to {}%", percent);
+                    return send_volume();
+                }
+                "get" => return send_volume(),
+                _ => return Err(format!("Unknown volume action: {}", action)),
+            };
+
+            let mut backend = session.backend.lock().await;
+            backend.key(key, Direction::Click)
+                .map_err(|e| format!("Volume key failed: {}", e))?;
+            info!("Volume key pressed: {}", action);
+        }
+
+        "mixer" => {
+            // Per-application volume: ["mixer", "list"], ["mixer", "set", pid, 0..100] or
+            // ["mixer", "mute", pid, true|false]. The sender gets ["mixer", [applications]] back.
+            if arr.len() < 2 {
+                return Err("Invalid mixer message".to_string());
+            }
+            let action = arr[1].as_str().ok_or("Invalid mixer action")?;
 
-            "t" => {
-                // Text input: ["t", "text content"]
-                if arr.len() < 2 {
-                    return Err("Invalid text message".to_string());
+            if action != "list" {
+                let pid = arr.get(2).and_then(|v| v.as_u64()).and_then(|pid| u32::try_from(pid).ok()).ok_or("Invalid process ID")?;
+                match action {
+                    "set" => {
+                        let level = arr.get(3).and_then(|v| v.as_f64()).ok_or("Invalid volume level")?;
+                        crate::audio::set_app_volume(pid, level.clamp(0.0, 100.0).round() as u8)?;
+                    }
+                    "mute" => {
+                        let muted = arr.get(3).and_then(|v| v.as_bool()).ok_or("Invalid mute state")?;
+                        crate::audio::set_app_muted(pid, muted)?;
+                    }
+                    _ => return Err(format!("Unknown mixer action: {}", action)),
                 }
-                let text_content = arr[1].as_str().ok_or("Invalid text content")?;
+                info!("Mixer {} for process {}", action, pid);
+            }
+            let apps = crate::audio::app_volumes()?;
+            crate::clients::send_to(session.client_id, &json!(["mixer", apps]));
+        }
 
-                let mut enigo = enigo.lock().await;
-                enigo.text(text_content)
-                    .map_err(|e| format!("Text input failed: {}", e))?;
+        "audio_device" => {
+            // Output device: ["audio_device", "list"] or ["audio_device", "set", id].
+            // The sender gets ["audio_device", [devices]] back.
+            if arr.len() < 2 {
+                return Err("Invalid audio device message".to_string());
+            }
+            match arr[1].as_str().ok_or("Invalid audio device action")? {
+                "list" => {}
+                "set" => {
+                    let id = arr.get(2).and_then(|v| v.as_str()).ok_or("Invalid audio device ID")?;
+                    crate::audio::set_default_output(id)?;
+                }
+                action => return Err(format!("Unknown audio device action: {}", action)),
             }
+            let devices = crate::audio::output_devices()?;
+            crate::clients::send_to(session.client_id, &json!(["audio_device", devices]));
+        }
 
-            "k" => {
-                // Key press: ["k", "KeyName"]
-                if arr.len() < 2 {
-                    return Err("Invalid key press message".to_string());
+        "mic" => {
+            // Microphone mute: ["mic", "toggle"] or ["mic", "get"]; every client is told
+            // the state with ["mic", muted]
+            if arr.len() < 2 {
+                return Err("Invalid microphone message".to_string());
+            }
+            let muted = match arr[1].as_str().ok_or("Invalid microphone action")? {
+                "toggle" => crate::audio::toggle_mic()?,
+                "get" => crate::audio::is_mic_muted()?,
+                action => return Err(format!("Unknown microphone action: {}", action)),
+            };
+            crate::clients::broadcast(&json!(["mic", muted]));
+        }
+
+        "brightness" => {
+            // Screen brightness: ["brightness", 0..100] sets it, ["brightness", "+10"] or
+            // ["brightness", "-10"] changes it and ["brightness", "get"] asks for it. Every
+            // client is told the level with ["brightness", level].
+            let change = match arr.get(1) {
+                Some(Value::Number(level)) => {
+                    let level = level.as_f64().ok_or("Invalid brightness level")?;
+                    Change::To(level.clamp(0.0, 100.0).round() as u8)
+                }
+                Some(Value::String(text)) if text == "get" => Change::Get,
+                Some(Value::String(text)) if text.starts_with(['+', '-']) => {
+                    Change::By(text.parse().map_err(|_| format!("Invalid brightness change: {}", text))?)
                 }
-                let key_name = arr[1].as_str().ok_or("Invalid key name")?;
+                _ => return Err("Invalid brightness message".to_string()),
+            };
+            let level = tokio::task::spawn_blocking(move || crate::brightness::apply(change))
+                .await
+                .map_err(|e| format!("Brightness change failed: {}", e))??;
+            crate::clients::broadcast(&json!(["brightness", level]));
+        }
 
-                let key = match key_name {
-                    "Escape" => Key::Escape,
-                    "PageUp" => Key::PageUp,
-                    "PageDown" => Key::PageDown,
-                    "Delete" => Key::Backspace,  // Del button sends Backspace key
-                    "Return" => Key::Return,
-                    _ => return Err(format!("Unknown key: {}", key_name)),
-                };
+        #[cfg(feature = "cap-power")]
+        "display" => {
+            // Turn the displays off: ["display", "off"]. The next remote command wakes them.
+            match arr.get(1).and_then(|v| v.as_str()) {
+                Some("off") => crate::power::displays_off()?,
+                _ => return Err("Invalid display message".to_string()),
+            }
+        }
 
-                let mut enigo = enigo.lock().await;
-                enigo.key(key, Direction::Click)
-                    .map_err(|e| format!("Key press failed: {}", e))?;
-                info!("Key pressed: {} (mapped to {:?})", key_name, key);
+        #[cfg(feature = "cap-file")]
+        "f" => {
+            // Type file contents: ["f", "file name", "type"|"paste"]
+            if arr.len() < 2 {
+                return Err("Invalid file message".to_string());
             }
+            let name = arr[1].as_str().ok_or("Invalid file name")?;
+            let paste = arr.get(2).and_then(|v| v.as_str()) == Some("paste");
 
-            "ping" => {
-                // Heartbeat - do nothing
-                info!("Ping received");
+            let mut backend = session.backend.lock().await;
+            crate::files::type_file(backend.as_mut(), name, paste)?;
+        }
+
+        #[cfg(feature = "cap-passthrough")]
+        "x" => {
+            // Passthrough: ["x", payload] is written as a line to the configured
+            // local program; its output comes back as ["x", line]
+            let payload = match arr.get(1) {
+                Some(Value::String(text)) => text.clone(),
+                Some(value) => value.to_string(),
+                None => return Err("Invalid passthrough message".to_string()),
+            };
+
+            let mut bridge = session.passthrough.lock().await;
+            if let Some(open) = bridge.as_mut() {
+                if open.send(&payload).await.is_ok() {
+                    return Ok(());
+                }
             }
+            // Connect on first use, or again after the program went away
+            let mut fresh = crate::passthrough::Bridge::open(session.client_id).await?;
+            fresh.send(&payload).await?;
+            *bridge = Some(fresh);
+        }
+
+        "takeover" => {
+            // Request control from the active controller: ["takeover"]
+            crate::clients::request_takeover(session.client_id)?;
+        }
 
-            _ => {
-                return Err(format!("Unknown command: {}", cmd));
+        "takeover_reply" => {
+            // Controller's answer: ["takeover_reply", requester_id, true|false]
+            if arr.len() < 3 {
+                return Err("Invalid takeover reply message".to_string());
             }
+            let requester = arr[1].as_u64().ok_or("Invalid requester")?;
+            let approved = arr[2].as_bool().ok_or("Invalid approval")?;
+            crate::clients::reply_takeover(session.client_id, requester, approved)?;
         }
 
-        Ok(())
-    } else {
-        Err("Message is not an array".to_string())
+        "profile" => {
+            // Remember the device's own preferences for its next connection:
+            // ["profile", {"sensitivity": 2.0, "natural_scroll": true, "layout": "touchpad"}]
+            let preferences = arr.get(1).and_then(|v| v.as_object()).ok_or("Invalid profile message")?;
+            save_preferences(session.device_id.as_deref(), preferences)?;
+        }
+
+        "ping" => {
+            // Heartbeat - do nothing
+            debug!("Ping received");
+        }
+
+        _ => {
+            return Err(format!("Unknown command: {}", cmd));
+        }
     }
+
+    Ok(())
 }
@@ -0,0 +1,37 @@
+use axum::{response::IntoResponse, Json};
+use serde_json::json;
+use std::sync::OnceLock;
+use std::time::Instant;
+
+static STARTED: OnceLock<Instant> = OnceLock::new();
+
+/// Remember when the process started, for the reported uptime
+pub fn init() {
+    STARTED.get_or_init(Instant::now);
+}
+
+/// Seconds since the process started
+pub fn uptime_secs() -> u64 {
+    STARTED.get().map_or(0, |started| started.elapsed().as_secs())
+}
+
+/// GET /healthz (no authentication, for monitoring tools)
+pub async fn healthz() -> impl IntoResponse {
+    Json(json!({
+        "status": "ok",
+        "uptime_secs": uptime_secs(),
+        "clients": crate::clients::count(),
+        "paused": crate::pause::is_paused(),
+    }))
+}
+
+/// GET /api/version (no API token needed, so clients can check compatibility before pairing)
+pub async fn version() -> impl IntoResponse {
+    Json(json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "build_date": env!("TOUCHRELAY_BUILD_DATE"),
+        "platform": format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH),
+        "protocol": crate::handler::PROTOCOL_VERSION,
+        "uptime_secs": uptime_secs(),
+    }))
+}
@@ -0,0 +1,101 @@
+use rumqttc::{AsyncClient, QoS};
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+/// Buttons offered to Home Assistant: object ID, name, icon and the command they send
+const BUTTONS: [(&str, &str, &str, &str); 12] = [
+    ("escape", "Escape", "mdi:keyboard-esc", r#"["k","Escape"]"#),
+    ("enter", "Enter", "mdi:keyboard-return", r#"["k","Return"]"#),
+    ("page_up", "Page Up", "mdi:chevron-up", r#"["k","PageUp"]"#),
+    ("page_down", "Page Down", "mdi:chevron-down", r#"["k","PageDown"]"#),
+    ("play_pause", "Play/Pause", "mdi:play-pause", r#"["media","play_pause"]"#),
+    ("next_track", "Next Track", "mdi:skip-next", r#"["media","next"]"#),
+    ("previous_track", "Previous Track", "mdi:skip-previous", r#"["media","prev"]"#),
+    ("stop", "Stop", "mdi:stop", r#"["media","stop"]"#),
+    ("volume_up", "Volume Up", "mdi:volume-plus", r#"["vol","up"]"#),
+    ("volume_down", "Volume Down", "mdi:volume-minus", r#"["vol","down"]"#),
+    ("volume_mute", "Mute", "mdi:volume-mute", r#"["vol","mute"]"#),
+    ("mic_toggle", "Toggle Microphone", "mdi:microphone-off", r#"["mic","toggle"]"#),
+];
+
+/// Node ID used in discovery topics and unique IDs (the topic prefix reduced to `[a-z0-9_]`)
+fn node_id(prefix: &str) -> String {
+    prefix
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '_' })
+        .collect()
+}
+
+/// Publish MQTT discovery configs so Home Assistant lists this PC as a device with
+/// connection sensors, a pause switch, a keypress text field and key buttons
+pub fn announce(client: &AsyncClient, prefix: &str) {
+    let config = crate::config::get();
+    let discovery_prefix = config.mqtt.as_ref().and_then(|mqtt| mqtt.discovery_prefix.clone());
+    let Some(discovery_prefix) = discovery_prefix.filter(|prefix| !prefix.is_empty()) else {
+        return;
+    };
+
+    let node = node_id(prefix);
+    let computer = std::env::var("COMPUTERNAME").unwrap_or_else(|_| "PC".to_string());
+    let common = json!({
+        "availability_topic": format!("{}/status", prefix),
+        "device": {
+            "identifiers": [node],
+            "name": format!("TouchRelay on {}", computer),
+            "manufacturer": "DeltaFoundry",
+            "model": "TouchRelay",
+            "sw_version": env!("CARGO_PKG_VERSION"),
+        },
+    });
+
+    let mut entities = vec![
+        ("binary_sensor", "connected", json!({
+            "name": "Connected",
+            "device_class": "connectivity",
+            "state_topic": format!("{}/clients", prefix),
+            "value_template": "{{ 'ON' if value | int > 0 else 'OFF' }}",
+        })),
+        ("sensor", "clients", json!({
+            "name": "Connected devices",
+            "icon": "mdi:cellphone-link",
+            "state_topic": format!("{}/clients", prefix),
+            "state_class": "measurement",
+        })),
+        ("switch", "paused", json!({
+            "name": "Pause remote input",
+            "icon": "mdi:hand-back-left-off",
+            "state_topic": format!("{}/paused", prefix),
+            "command_topic": format!("{}/paused/set", prefix),
+            "payload_on": "true",
+            "payload_off": "false",
+        })),
+        ("text", "keypress", json!({
+            "name": "Send keypress",
+            "icon": "mdi:keyboard",
+            "command_topic": format!("{}/key", prefix),
+            "max": 32,
+        })),
+    ];
+    for (object_id, name, icon, command) in BUTTONS {
+        entities.push(("button", object_id, json!({
+            "name": name,
+            "icon": icon,
+            "command_topic": format!("{}/command", prefix),
+            "payload_press": command,
+        })));
+    }
+
+    let count = entities.len();
+    for (component, object_id, mut entity) in entities {
+        if let (Some(entity), Value::Object(common)) = (entity.as_object_mut(), common.clone()) {
+            entity.extend(common);
+        }
+        entity["unique_id"] = json!(format!("{}_{}", node, object_id));
+
+        let topic = format!("{}/{}/{}/{}/config", discovery_prefix, component, node, object_id);
+        if let Err(e) = client.try_publish(topic, QoS::AtLeastOnce, true, entity.to_string()) {
+            warn!("Failed to publish Home Assistant discovery: {}", e);
+        }
+    }
+    info!("Announced {} entities to Home Assistant", count);
+}
@@ -0,0 +1,175 @@
+use axum::{
+    extract::Path,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use tracing::{info, warn};
+use windows::core::PWSTR;
+use windows::Win32::Globalization::{GetUserPreferredUILanguages, MUI_LANGUAGE_NAME};
+
+/// Translations built into the executable, keyed by language tag
+const BUNDLED: &[(&str, &str)] = &[
+    ("en", include_str!("../locales/en.toml")),
+    ("de", include_str!("../locales/de.toml")),
+    ("es", include_str!("../locales/es.toml")),
+    ("fr", include_str!("../locales/fr.toml")),
+];
+// Complete set of strings that other languages fall back to
+const FALLBACK: &str = "en";
+const MAX_TAG_LEN: usize = 16;
+
+static STRINGS: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+/// Directory with extra or adjusted translations, one `<language>.toml` each
+pub fn locales_dir() -> PathBuf {
+    crate::config::config_dir().join("locales")
+}
+
+/// Translated string for `key` (e.g. `menu.quit`), falling back to English and then the key itself
+pub fn t(key: &str) -> String {
+    strings().get(key).cloned().unwrap_or_else(|| key.to_string())
+}
+
+/// Translated string with `{name}` placeholders filled in
+pub fn t_with(key: &str, args: &[(&str, &str)]) -> String {
+    fill(t(key), args)
+}
+
+/// Like `t_with`, but uses English unless the strings are already loaded. For the crash
+/// handler, which must not read the config while the process is failing.
+pub fn t_loaded_with(key: &str, args: &[(&str, &str)]) -> String {
+    let text = match STRINGS.get() {
+        Some(strings) => strings.get(key).cloned(),
+        None => catalog(FALLBACK).remove(key),
+    };
+    fill(text.unwrap_or_else(|| key.to_string()), args)
+}
+
+fn fill(text: String, args: &[(&str, &str)]) -> String {
+    args.iter()
+        .fold(text, |text, (name, value)| text.replace(&format!("{{{}}}", name), value))
+}
+
+/// GET /api/i18n/<language>.json: the web UI's strings (`[web]` keys) in the available language
+/// closest to the requested one, which is named in Content-Language
+pub async fn web_handler(Path(file): Path<String>) -> Response {
+    let Some(tag) = file.strip_suffix(".json").filter(|tag| is_valid_tag(tag)) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let language = resolve(tag).unwrap_or_else(|| FALLBACK.to_string());
+    let strings: BTreeMap<String, String> = catalog(&language)
+        .into_iter()
+        .filter_map(|(key, text)| Some((key.strip_prefix("web.")?.to_string(), text)))
+        .collect();
+
+    ([(header::CONTENT_LANGUAGE, language)], Json(strings)).into_response()
+}
+
+/// Strings of the UI language, chosen once per run
+fn strings() -> &'static HashMap<String, String> {
+    STRINGS.get_or_init(|| {
+        let language = language();
+        info!("Using language {}", language);
+        catalog(&language)
+    })
+}
+
+/// All strings for `language`: English overlaid with the bundled translation, then with
+/// `locales\<language>.toml` from the config directory
+fn catalog(language: &str) -> HashMap<String, String> {
+    let mut strings = HashMap::new();
+    let layers = if language == FALLBACK { vec![FALLBACK] } else { vec![FALLBACK, language] };
+
+    for tag in layers {
+        if let Some((_, text)) = BUNDLED.iter().find(|(bundled, _)| *bundled == tag) {
+            merge(&mut strings, text, "built-in");
+        }
+        let path = locales_dir().join(format!("{}.toml", tag));
+        if let Ok(text) = std::fs::read_to_string(&path) {
+            merge(&mut strings, &text, &path.to_string_lossy());
+        }
+    }
+    strings
+}
+
+/// Add the strings of a locale file, keyed by their dotted path such as `menu.quit`
+fn merge(strings: &mut HashMap<String, String>, text: &str, source: &str) {
+    fn flatten(strings: &mut HashMap<String, String>, prefix: &str, table: toml::Table) {
+        for (key, value) in table {
+            let key = if prefix.is_empty() { key } else { format!("{}.{}", prefix, key) };
+            match value {
+                toml::Value::String(text) => {
+                    strings.insert(key, text);
+                }
+                toml::Value::Table(table) => flatten(strings, &key, table),
+                _ => warn!("Ignoring non-text translation {}", key),
+            }
+        }
+    }
+
+    match text.parse::<toml::Table>() {
+        Ok(table) => flatten(strings, "", table),
+        Err(e) => warn!("Invalid locale file {}: {}", source, e),
+    }
+}
+
+/// Whether `tag` looks like a language tag, e.g. `de` or `pt-BR` (it becomes part of a file name)
+fn is_valid_tag(tag: &str) -> bool {
+    !tag.is_empty() && tag.len() <= MAX_TAG_LEN && tag.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+/// The available language best matching `tag`: the tag itself, then its primary language
+fn resolve(tag: &str) -> Option<String> {
+    if !is_valid_tag(tag) {
+        return None;
+    }
+    let primary = tag.split('-').next().unwrap_or(tag);
+    [tag, primary].into_iter().find_map(|candidate| {
+        if let Some((bundled, _)) = BUNDLED.iter().find(|(bundled, _)| bundled.eq_ignore_ascii_case(candidate)) {
+            return Some(bundled.to_string());
+        }
+        locales_dir()
+            .join(format!("{}.toml", candidate))
+            .is_file()
+            .then(|| candidate.to_string())
+    })
+}
+
+/// Language from `language` in the config, else the first Windows display language we have
+fn language() -> String {
+    if let Some(tag) = &crate::config::get().language {
+        match resolve(tag) {
+            Some(language) => return language,
+            None => warn!("No translation for language '{}'", tag),
+        }
+    }
+    windows_languages()
+        .iter()
+        .find_map(|tag| resolve(tag))
+        .unwrap_or_else(|| FALLBACK.to_string())
+}
+
+/// The user's Windows display languages in order of preference, e.g. `["de-DE", "en-US"]`
+fn windows_languages() -> Vec<String> {
+    let mut count = 0;
+    let mut len = 0;
+    unsafe {
+        if GetUserPreferredUILanguages(MUI_LANGUAGE_NAME, &mut count, None, &mut len).is_err() {
+            return Vec::new();
+        }
+        let mut buffer = vec![0u16; len as usize];
+        if GetUserPreferredUILanguages(MUI_LANGUAGE_NAME, &mut count, Some(PWSTR(buffer.as_mut_ptr())), &mut len).is_err() {
+            return Vec::new();
+        }
+        // Null-separated list ending in an empty string
+        String::from_utf16_lossy(&buffer)
+            .split('\0')
+            .filter(|tag| !tag.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+}
@@ -0,0 +1,67 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::time::Duration;
+use tracing::{error, warn};
+use windows::core::HSTRING;
+use windows::Win32::Foundation::{CloseHandle, GetLastError, ERROR_ALREADY_EXISTS, WAIT_ABANDONED, WAIT_OBJECT_0};
+use windows::Win32::System::Threading::{
+    CreateEventW, CreateMutexW, OpenEventW, SetEvent, WaitForSingleObject, EVENT_MODIFY_STATE, INFINITE,
+};
+
+use crate::events::{self, AppEvent};
+
+/// Object name shared by instances using the same config file in this Windows session
+fn object_name(kind: &str) -> HSTRING {
+    let mut hasher = DefaultHasher::new();
+    crate::config::config_path().to_string_lossy().to_lowercase().hash(&mut hasher);
+    HSTRING::from(format!("Local\\TouchRelay-{}-{:016x}", kind, hasher.finish()))
+}
+
+/// Claim this session's TouchRelay mutex, waiting up to `wait` for another instance to exit.
+/// Returns false if it still holds the mutex.
+pub fn acquire(wait: Duration) -> bool {
+    match unsafe { CreateMutexW(None, true, &object_name("Instance")) } {
+        // Kept open (and owned) until the process exits
+        Ok(mutex) => unsafe {
+            if GetLastError() != ERROR_ALREADY_EXISTS {
+                return true;
+            }
+            // The mutex passes to us (abandoned) when the other instance exits
+            let result = WaitForSingleObject(mutex, wait.as_millis().try_into().unwrap_or(INFINITE));
+            result == WAIT_OBJECT_0 || result == WAIT_ABANDONED
+        },
+        Err(e) => {
+            warn!("Failed to create the single-instance mutex: {}", e);
+            true
+        }
+    }
+}
+
+/// Ask the running instance to show where it can be reached
+pub fn signal_existing() {
+    unsafe {
+        match OpenEventW(EVENT_MODIFY_STATE, false, &object_name("Show")) {
+            Ok(event) => {
+                let _ = SetEvent(event);
+                let _ = CloseHandle(event);
+            }
+            Err(e) => warn!("Failed to reach the running instance: {}", e),
+        }
+    }
+}
+
+/// Turn later launches into `ShowAddress` events for this instance
+pub fn listen() {
+    let name = object_name("Show");
+    std::thread::spawn(move || {
+        let event = match unsafe { CreateEventW(None, false, false, &name) } {
+            Ok(event) => event,
+            Err(e) => {
+                error!("Failed to create the show event: {}", e);
+                return;
+            }
+        };
+        while unsafe { WaitForSingleObject(event, INFINITE) } == WAIT_OBJECT_0 {
+            events::send(AppEvent::ShowAddress);
+        }
+    });
+}
@@ -0,0 +1,59 @@
+use std::time::{Duration, Instant};
+
+// Events arriving this much later than the fastest observed transit are treated as a backlog
+const STALL_THRESHOLD_MS: f64 = 60.0;
+// Backlogged motion is replayed faster than real time so the cursor catches up
+const CATCH_UP_SPEED: f64 = 2.0;
+// Let the offset estimate creep upwards so it can follow clock drift (ms per event)
+const OFFSET_DRIFT_MS: f64 = 0.01;
+
+/// Estimates the offset between the client's event timestamps and the server clock,
+/// and paces bursts that arrive together after a network stall
+pub struct Clock {
+    started: Instant,
+    // Smallest observed (server time - client time), i.e. the fastest transit seen
+    offset: Option<f64>,
+    // Server instant and client timestamp at which the current backlog replay began
+    replay: Option<(Instant, f64)>,
+    // Latest client timestamp scheduled so far
+    last_timestamp: f64,
+}
+
+impl Clock {
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            offset: None,
+            replay: None,
+            last_timestamp: f64::MIN,
+        }
+    }
+
+    /// Return how long to hold an event stamped `timestamp` (client milliseconds) before injecting it
+    pub fn schedule(&mut self, timestamp: f64) -> Duration {
+        let now = Instant::now();
+        let transit = now.duration_since(self.started).as_secs_f64() * 1000.0 - timestamp;
+
+        let offset = match self.offset {
+            Some(offset) => (offset + OFFSET_DRIFT_MS).min(transit),
+            None => transit,
+        };
+        self.offset = Some(offset);
+
+        // Out-of-order events are merged into the current position right away
+        if timestamp < self.last_timestamp {
+            return Duration::ZERO;
+        }
+        self.last_timestamp = timestamp;
+
+        if transit - offset < STALL_THRESHOLD_MS {
+            self.replay = None;
+            return Duration::ZERO;
+        }
+
+        // Part of a backlog: spread it out following the original timing
+        let (anchor, anchor_timestamp) = *self.replay.get_or_insert((now, timestamp));
+        let due = anchor + Duration::from_secs_f64((timestamp - anchor_timestamp) / CATCH_UP_SPEED / 1000.0);
+        due.saturating_duration_since(now)
+    }
+}
@@ -0,0 +1,135 @@
+use axum::{extract::Path, http::StatusCode, response::IntoResponse, Json};
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use tracing::info;
+
+const MAX_NAME_LEN: usize = 64;
+const CONTROL_TYPES: [&str; 3] = ["button", "slider", "touchpad"];
+
+/// Directory holding one `<name>.json` file per custom layout
+fn layouts_dir() -> PathBuf {
+    crate::config::config_dir().join("layouts")
+}
+
+/// Map a layout name to its file, rejecting anything that is not a plain name
+fn layout_path(name: &str) -> Result<PathBuf, String> {
+    let valid = !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(format!("Invalid layout name: {}", name));
+    }
+    Ok(layouts_dir().join(format!("{}.json", name)))
+}
+
+/// Check that a layout is a `{"controls": [...]}` object the web client can render
+pub fn validate(layout: &Value) -> Result<(), String> {
+    let controls = layout
+        .get("controls")
+        .and_then(Value::as_array)
+        .ok_or("Layout needs a \"controls\" array")?;
+
+    for (index, control) in controls.iter().enumerate() {
+        let kind = control
+            .get("type")
+            .and_then(Value::as_str)
+            .ok_or_else(|| format!("Control {} has no type", index))?;
+        if !CONTROL_TYPES.contains(&kind) {
+            return Err(format!("Control {} has unknown type '{}'", index, kind));
+        }
+
+        // Buttons and sliders are bound to a command array such as ["k", "Escape"]
+        if kind != "touchpad" {
+            let command = control.get("command").and_then(Value::as_array);
+            if !command.is_some_and(|command| command.first().is_some_and(Value::is_string)) {
+                return Err(format!("Control {} needs a command array", index));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// List the names of all stored layouts, followed by those from enabled packs
+pub fn list() -> Vec<String> {
+    let entries = std::fs::read_dir(layouts_dir()).into_iter().flatten();
+
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let path = entry.path();
+            if path.extension()? != "json" {
+                return None;
+            }
+            Some(path.file_stem()?.to_str()?.to_string())
+        })
+        .collect();
+    names.sort();
+    names.extend(crate::packs::layout_names());
+    names
+}
+
+/// Load a stored layout, or `<pack>.<layout>` from a pack (Ok(None) if it does not exist)
+pub fn load(name: &str) -> Result<Option<Value>, String> {
+    if let Some((pack, layout)) = name.split_once('.') {
+        return crate::packs::layout(pack, layout);
+    }
+
+    let path = layout_path(name)?;
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+    };
+    serde_json::from_str(&text)
+        .map(Some)
+        .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))
+}
+
+/// Validate and store a layout, replacing any existing one with the same name
+pub fn save(name: &str, layout: &Value) -> Result<(), String> {
+    let path = layout_path(name)?;
+    validate(layout)?;
+
+    let text = serde_json::to_string_pretty(layout)
+        .map_err(|e| format!("Failed to serialize layout: {}", e))?;
+    std::fs::create_dir_all(layouts_dir())
+        .map_err(|e| format!("Failed to create {}: {}", layouts_dir().display(), e))?;
+    std::fs::write(&path, text)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+
+    info!("Saved layout {}", name);
+    Ok(())
+}
+
+/// Delete a stored layout, returning whether it existed
+pub fn delete(name: &str) -> Result<bool, String> {
+    let path = layout_path(name)?;
+    match std::fs::remove_file(&path) {
+        Ok(()) => {
+            info!("Deleted layout {}", name);
+            Ok(true)
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(format!("Failed to delete {}: {}", path.display(), e)),
+    }
+}
+
+/// Build the response for reading a single layout
+pub fn layout_response(name: &str) -> axum::response::Response {
+    match load(name) {
+        Ok(Some(layout)) => Json(layout).into_response(),
+        Ok(None) => (StatusCode::NOT_FOUND, "Layout not found").into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e).into_response(),
+    }
+}
+
+/// GET /layouts (read-only listing for web clients)
+pub async fn list_handler() -> impl IntoResponse {
+    Json(json!({ "layouts": list() }))
+}
+
+/// GET /layouts/{name}
+pub async fn get_handler(Path(name): Path<String>) -> impl IntoResponse {
+    layout_response(&name)
+}
@@ -0,0 +1,120 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use tracing::{info, warn};
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::{filter::filter_fn, prelude::*, reload, EnvFilter, Registry};
+
+const DEFAULT_FILTER: &str = "info";
+// One file per day, e.g. touchrelay.2024-05-01.log
+const LOG_FILE_PREFIX: &str = "touchrelay";
+const LOG_FILE_SUFFIX: &str = "log";
+const MAX_LOG_FILES: usize = 7;
+
+static FILTER: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+/// Level picked from the tray's Log Level menu, replacing the configured filter until restart
+static OVERRIDE: Mutex<Option<String>> = Mutex::new(None);
+/// Whether lines also go to the log files (`log_files`, on until the config says otherwise)
+static FILE_LOGGING: AtomicBool = AtomicBool::new(true);
+
+/// Directory holding the daily log files
+pub fn log_dir() -> PathBuf {
+    crate::config::config_dir().join("logs")
+}
+
+/// Log to stdout and the daily log files, filtered by `--log-level`, `RUST_LOG` or
+/// (once the config is loaded) `log_level`
+pub fn init() {
+    let filter = match &crate::cli::get().log_level {
+        Some(level) => EnvFilter::new(level),
+        None => EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER)),
+    };
+    let (filter, handle) = reload::Layer::new(filter);
+
+    let appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_FILE_PREFIX)
+        .filename_suffix(LOG_FILE_SUFFIX)
+        .max_log_files(MAX_LOG_FILES)
+        .build(log_dir());
+    let (file_layer, file_error) = match appender {
+        Ok(appender) => {
+            let layer = tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(appender)
+                .with_filter(filter_fn(|_| FILE_LOGGING.load(Ordering::Relaxed)));
+            (Some(layer), None)
+        }
+        Err(e) => (None, Some(e)),
+    };
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer())
+        .with(file_layer)
+        .init();
+    let _ = FILTER.set(handle);
+
+    if let Some(e) = file_error {
+        warn!("Failed to open log files in {}: {}", log_dir().display(), e);
+    }
+}
+
+/// Levels offered in the tray's Log Level menu
+#[cfg(feature = "gui")]
+pub const LEVELS: [&str; 5] = ["error", "warn", "info", "debug", "trace"];
+
+/// Apply the configured `log_files` and `log_level` (`--log-level` and `RUST_LOG` win when set,
+/// and a level picked from the tray wins over all of them)
+pub fn apply_config() {
+    FILE_LOGGING.store(crate::config::get().log_files, Ordering::Relaxed);
+
+    let level = match OVERRIDE.lock().unwrap().clone() {
+        Some(level) => level,
+        None => configured_filter(),
+    };
+    set_filter(&level);
+}
+
+/// Level picked from the tray, if any
+#[cfg(feature = "gui")]
+pub fn override_level() -> Option<String> {
+    OVERRIDE.lock().unwrap().clone()
+}
+
+/// Use `level` until TouchRelay restarts, or go back to the configured filter (None)
+#[cfg(feature = "gui")]
+pub fn set_override(level: Option<&str>) {
+    *OVERRIDE.lock().unwrap() = level.map(str::to_string);
+    apply_config();
+}
+
+/// Filter from `--log-level`, `RUST_LOG` or `log_level`, in that order
+fn configured_filter() -> String {
+    if let Some(level) = &crate::cli::get().log_level {
+        return level.clone();
+    }
+    if let Ok(level) = std::env::var("RUST_LOG") {
+        return level;
+    }
+    crate::config::get().log_level.clone().unwrap_or_else(|| DEFAULT_FILTER.to_string())
+}
+
+/// Swap the active filter, keeping the current one if `level` is invalid
+fn set_filter(level: &str) {
+    let Some(handle) = FILTER.get() else {
+        return;
+    };
+    if handle.with_current(|filter| filter.to_string() == level).unwrap_or(false) {
+        return;
+    }
+
+    match EnvFilter::try_new(level) {
+        Ok(filter) => {
+            if handle.reload(filter).is_ok() {
+                info!("Log level set to {}", level);
+            }
+        }
+        Err(e) => warn!("Invalid log level '{}': {}", level, e),
+    }
+}
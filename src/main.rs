@@ -1,35 +1,141 @@
-// Hide console window on Windows in release mode (debug mode keeps console for logs)
-#![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
+// Hide console window on Windows in release mode (debug mode and console-only builds keep it for logs)
+#![cfg_attr(all(not(debug_assertions), feature = "gui"), windows_subsystem = "windows")]
 
+#[cfg(feature = "gui")]
+mod about;
+#[cfg(feature = "gui")]
+mod console;
+#[cfg(feature = "gui")]
 mod menu;
+#[cfg(feature = "gui")]
+mod onboarding;
 mod startup;
 mod handler;
 mod assets;
+mod audio;
+mod activity;
+mod api;
+mod auth;
+mod backend;
+mod brightness;
+mod calibration;
+mod capability;
+mod cli;
+mod config;
+mod clients;
+mod crash;
+mod dialog;
+mod discovery;
+mod display;
+mod elevation;
+mod eventlog;
+mod events;
+mod firewall;
+#[cfg(feature = "cap-file")]
+mod files;
+mod health;
+mod homeassistant;
+mod http;
+mod i18n;
+mod instance;
+mod latency;
+mod logging;
+mod layouts;
+mod mdns;
+mod media;
+mod metrics;
+mod mqtt;
+mod pairing;
+#[cfg(feature = "cap-passthrough")]
+mod passthrough;
+mod network;
+mod notify;
+mod packs;
+mod pause;
+mod policy;
+#[cfg(feature = "cap-power")]
+mod power;
+mod proxy;
+mod qr;
+mod recording;
+mod reload;
+mod selftest;
+mod service;
+mod settings;
+mod shutdown;
+mod theme;
+mod updates;
+mod webhooks;
+mod winsession;
 
 use axum::{
-    extract::ws::WebSocketUpgrade,
+    extract::{ConnectInfo, Query},
+    http::HeaderMap,
+    middleware,
     response::IntoResponse,
-    routing::get,
+    routing::{get, post},
     Router,
 };
-use tracing::{info, warn};
+use std::net::SocketAddr;
+use tower_http::compression::CompressionLayer;
+use std::time::{Duration, Instant};
+#[cfg(feature = "gui")]
+use global_hotkey::{GlobalHotKeyEvent, GlobalHotKeyManager, HotKeyState, hotkey::HotKey};
+use tracing::{error, info, warn};
+#[cfg(feature = "gui")]
 use tray_icon::{
     menu::MenuEvent,
-    TrayIconBuilder, TrayIcon,
+    Icon, MouseButton, TrayIconBuilder, TrayIcon, TrayIconEvent,
 };
+#[cfg(feature = "gui")]
 use winit::event_loop::{EventLoop, ControlFlow, ActiveEventLoop};
+#[cfg(feature = "gui")]
 use winit::application::ApplicationHandler;
-use local_ip_address::local_ip;
 
+use events::AppEvent;
+#[cfg(feature = "gui")]
 use menu::{TrayMenu, MenuAction};
 
+// Number of icon swaps when the kill-switch is toggled
+#[cfg(feature = "gui")]
+const FLASH_COUNT: u8 = 6;
+#[cfg(feature = "gui")]
+const FLASH_INTERVAL: Duration = Duration::from_millis(250);
+
+// How long Quit waits for clients to be told and the server to stop
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(3);
+// How long the server waits for sessions to send their close frames
+const CLOSE_GRACE: Duration = Duration::from_secs(2);
+// Binding is retried with doubling delays, e.g. while the network comes up after boot
+const BIND_ATTEMPTS: u32 = 5;
+const BIND_RETRY_DELAY: Duration = Duration::from_secs(2);
+// How long an instance restarted as administrator waits for the previous one to quit
+const ELEVATED_RESTART_WAIT: Duration = Duration::from_secs(30);
+
 // Application handler for winit event loop
+#[cfg(feature = "gui")]
 struct TrayApp {
     tray_icon: TrayIcon,
     tray_menu: TrayMenu,
+    normal_icon: Icon,
+    paused_icon: Icon,
+    // Shown while at least one client is connected
+    connected_icon: Icon,
+    // Why the server is not running, shown in the tooltip until it starts again
+    server_error: Option<String>,
+    // Show the address once the server first starts (not when started with Windows)
+    startup_notice_pending: bool,
+    // Keeps the kill-switch hotkey registered while the app runs
+    _hotkey_manager: Option<GlobalHotKeyManager>,
+    kill_switch_id: Option<u32>,
+    flashes_left: u8,
+    next_flash: Option<Instant>,
+    // Thread running the web server
+    server: Option<std::thread::JoinHandle<()>>,
 }
 
-impl ApplicationHandler for TrayApp {
+#[cfg(feature = "gui")]
+impl ApplicationHandler<AppEvent> for TrayApp {
     fn resumed(&mut self, _event_loop: &ActiveEventLoop) {
         // Called when the application is resumed
     }
@@ -43,6 +149,42 @@ impl ApplicationHandler for TrayApp {
         // We don't have any windows, so this is not used
     }
 
+    fn user_event(&mut self, event_loop: &ActiveEventLoop, event: AppEvent) {
+        match event {
+            AppEvent::StateChanged => {
+                self.update_menu();
+                self.refresh_icon();
+            }
+            AppEvent::ClientsChanged => {
+                self.update_menu();
+                self.refresh_icon();
+                self.refresh_tooltip();
+            }
+            AppEvent::ServerStarted => {
+                self.server_error = None;
+                self.refresh_tooltip();
+                if std::mem::take(&mut self.startup_notice_pending) {
+                    show_startup_notice();
+                    if config::is_first_run() {
+                        onboarding::run();
+                    }
+                }
+            }
+            AppEvent::ShowAddress => {
+                notify::show(&i18n::t("notify.already_running"), &tooltip_text().replacen("TouchRelay\n", "", 1));
+            }
+            AppEvent::ServerFailed(reason) | AppEvent::ServerRetrying(reason) => {
+                self.server_error = Some(reason);
+                self.refresh_tooltip();
+            }
+            AppEvent::RestartServer => self.restart_server(),
+            AppEvent::Quit => {
+                self.stop_server();
+                event_loop.exit();
+            }
+        }
+    }
+
     fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
         // Check for menu events
         if let Ok(event) = MenuEvent::receiver().try_recv() {
@@ -52,111 +194,720 @@ impl ApplicationHandler for TrayApp {
             let should_update_menu = self.tray_menu.execute_action(action);
 
             if action == MenuAction::Quit {
+                self.stop_server();
                 event_loop.exit();
+            } else if action == MenuAction::RestartServer {
+                self.restart_server();
             } else if should_update_menu {
                 self.update_menu();
             }
+
+            if action == MenuAction::TogglePause {
+                self.refresh_icon();
+            }
+
+            if let MenuAction::SelectAddress(_) = action {
+                self.refresh_tooltip();
+            }
         }
+
+        // Double-clicking the icon opens the web interface like the first menu item
+        // (drained in full, since hovering the icon sends a stream of move events)
+        while let Ok(event) = TrayIconEvent::receiver().try_recv() {
+            if let TrayIconEvent::DoubleClick { button: MouseButton::Left, .. } = event {
+                self.tray_menu.execute_action(MenuAction::OpenWeb);
+            }
+        }
+
+        // Check for the kill-switch hotkey
+        if let Ok(event) = GlobalHotKeyEvent::receiver().try_recv() {
+            if Some(event.id()) == self.kill_switch_id && event.state() == HotKeyState::Pressed {
+                pause::toggle();
+                self.update_menu();
+                self.start_flash();
+            }
+        }
+
+        self.tick_flash(event_loop);
     }
 }
 
+#[cfg(feature = "gui")]
 impl TrayApp {
-    /// Update the tray menu to reflect current startup state
+    /// Stop the web server and start it again with the configuration on disk
+    /// (port, bind address and everything else the server reads at startup)
+    fn restart_server(&mut self) {
+        restart_server(&mut self.server);
+        self.update_menu();
+    }
+
+    fn stop_server(&mut self) {
+        stop_server(self.server.take());
+    }
+
+    /// Update the tray menu in place to reflect the current pause state, clients and settings
     fn update_menu(&mut self) {
-        let new_menu = TrayMenu::new();
-        self.tray_icon.set_menu(Some(Box::new(new_menu.menu().clone())));
-        self.tray_menu = new_menu;
-        info!("Menu updated with current startup state");
+        self.tray_menu.refresh();
+    }
+
+    /// Show the address and connected devices, or why the server is not running
+    fn refresh_tooltip(&self) {
+        let tooltip = match &self.server_error {
+            Some(reason) => format!("TouchRelay\n{}", i18n::t_with("tooltip.server_not_running", &[("reason", reason)])),
+            None => tooltip_text(),
+        };
+        if let Err(e) = self.tray_icon.set_tooltip(Some(tooltip)) {
+            warn!("Failed to update tray tooltip: {}", e);
+        }
+    }
+
+    /// Show the icon matching the current pause and connection state
+    fn refresh_icon(&self) {
+        if let Err(e) = self.tray_icon.set_icon(Some(self.state_icon())) {
+            warn!("Failed to update tray icon: {}", e);
+        }
+    }
+
+    /// Start flashing the tray icon to acknowledge a pause state change
+    fn start_flash(&mut self) {
+        self.flashes_left = FLASH_COUNT;
+        self.next_flash = Some(Instant::now());
+    }
+
+    /// Advance the icon flash animation, settling on the icon for the current pause state
+    fn tick_flash(&mut self, event_loop: &ActiveEventLoop) {
+        let Some(next_flash) = self.next_flash else {
+            return;
+        };
+
+        let now = Instant::now();
+        if now >= next_flash {
+            let icon = if self.flashes_left == 0 {
+                self.next_flash = None;
+                self.state_icon()
+            } else {
+                self.flashes_left -= 1;
+                self.next_flash = Some(now + FLASH_INTERVAL);
+                if self.flashes_left & 1 == 0 {
+                    self.normal_icon.clone()
+                } else {
+                    self.paused_icon.clone()
+                }
+            };
+
+            if let Err(e) = self.tray_icon.set_icon(Some(icon)) {
+                warn!("Failed to update tray icon: {}", e);
+            }
+        }
+
+        match self.next_flash {
+            Some(deadline) => event_loop.set_control_flow(ControlFlow::WaitUntil(deadline)),
+            None => event_loop.set_control_flow(ControlFlow::Wait),
+        }
+    }
+
+    /// Get the icon matching the current pause and connection state
+    fn state_icon(&self) -> Icon {
+        if pause::is_paused() {
+            self.paused_icon.clone()
+        } else if clients::count() > 0 {
+            self.connected_icon.clone()
+        } else {
+            self.normal_icon.clone()
+        }
     }
 }
 
+/// Type an allowlisted file into the focused window and exit
+#[cfg(feature = "cap-file")]
+fn type_file_once(name: &str, paste: bool) -> Result<(), String> {
+    let mut backend = backend::desktop()?;
+    files::type_file(backend.as_mut(), name, paste)
+}
+
+/// Tray tooltip showing the URL devices should open and how many are connected
+#[cfg(feature = "gui")]
+fn tooltip_text() -> String {
+    let mut tooltip = match network::access_url() {
+        Some(url) if network::is_loopback_only() => format!("TouchRelay\n{}", i18n::t_with("tooltip.this_pc_only", &[("url", &url)])),
+        Some(url) => format!("TouchRelay\n{}", url),
+        None => {
+            warn!("Failed to detect local IP address");
+            format!("TouchRelay\n{}", network::placeholder_url())
+        }
+    };
+    match clients::count() {
+        0 => {}
+        1 => tooltip.push_str(&format!("\n{}", i18n::t("tooltip.one_connected"))),
+        count => tooltip.push_str(&format!("\n{}", i18n::t_with("tooltip.many_connected", &[("count", &count.to_string())]))),
+    }
+    if winsession::is_remote() {
+        tooltip.push_str(&format!("\n{}", i18n::t("tooltip.remote_session")));
+    }
+    tooltip
+}
+
+/// Tell a user who just launched TouchRelay where to point their phone
+#[cfg(feature = "gui")]
+fn show_startup_notice() {
+    let url = network::access_url().unwrap_or_else(network::placeholder_url);
+    let message = i18n::t_with("notify.startup_message", &[("url", &url), ("show_qr", &i18n::t("menu.show_qr"))]);
+    notify::show(&i18n::t("notify.startup_title"), &message);
+}
+
+/// Register the configured kill-switch hotkey
+#[cfg(feature = "gui")]
+fn register_kill_switch() -> Option<(GlobalHotKeyManager, u32)> {
+    let hotkey_str = config::get().kill_switch_hotkey.clone();
+
+    let hotkey: HotKey = match hotkey_str.parse() {
+        Ok(hotkey) => hotkey,
+        Err(e) => {
+            error!("Invalid kill-switch hotkey '{}': {}", hotkey_str, e);
+            return None;
+        }
+    };
+
+    let manager = match GlobalHotKeyManager::new() {
+        Ok(manager) => manager,
+        Err(e) => {
+            error!("Failed to create hotkey manager: {}", e);
+            return None;
+        }
+    };
+
+    if let Err(e) = manager.register(hotkey) {
+        error!("Failed to register kill-switch hotkey '{}': {}", hotkey_str, e);
+        return None;
+    }
+
+    info!("Kill-switch hotkey registered: {}", hotkey_str);
+    Some((manager, hotkey.id()))
+}
+
 fn main() {
+    // Parse the command line first: --config and --log-level apply to everything below
+    let args = cli::get();
+
     // Initialize tracing
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info")),
-        )
-        .init();
+    logging::init();
+    logging::apply_config();
+    crash::install_hook();
+
+    health::init();
+
+    // Autostart setup for installers and deployment scripts
+    if args.enable_startup || args.disable_startup {
+        if let Err(e) = startup::set_startup_enabled(args.enable_startup) {
+            error!("Failed to update startup: {}", e);
+            std::process::exit(1);
+        }
+        info!("Start with Windows {}", if args.enable_startup { "enabled" } else { "disabled" });
+        return;
+    }
+
+    // On-demand update check for scripts and the console
+    if args.check_update {
+        match updates::check() {
+            Ok(Some(release)) => info!("TouchRelay {} is available: {}", release.version(), release.html_url),
+            Ok(None) => info!("TouchRelay {} is up to date", env!("CARGO_PKG_VERSION")),
+            Err(e) => {
+                error!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    // Firewall rule for installers, and for the tray, which runs this as administrator
+    if args.add_firewall_rule || args.remove_firewall_rule {
+        let result = if args.add_firewall_rule {
+            firewall::add_rule(network::configured_port())
+        } else {
+            firewall::remove_rule()
+        };
+        if let Err(e) = result {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // Windows service: set up, remove, or run when started by the service manager
+    if args.install_service || args.uninstall_service || args.service {
+        let result = if args.install_service {
+            service::install()
+        } else if args.uninstall_service {
+            service::uninstall()
+        } else {
+            service::run()
+        };
+        if let Err(e) = result {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // Hidden soak-test mode, runs against a mock backend
+    if args.selftest {
+        if let Err(e) = selftest::run(args) {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // Re-inject a recorded session
+    if let Some(path) = &args.replay {
+        if let Err(e) = recording::replay(path, args.speed) {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    #[cfg(feature = "cap-file")]
+    {
+        if let Some(name) = &args.type_file {
+            if let Err(e) = type_file_once(name, args.paste) {
+                error!("{}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+    }
+
+    // A second launch (e.g. double-clicking the exe again) points to the running instance,
+    // unless it replaces an instance that is restarting as administrator
+    let wait = if args.elevated_restart { ELEVATED_RESTART_WAIT } else { Duration::ZERO };
+    if !instance::acquire(wait) {
+        info!("TouchRelay is already running");
+        instance::signal_existing();
+        return;
+    }
+    updates::remove_old();
+    #[cfg(feature = "gui")]
+    if args.elevated_restart {
+        startup::use_elevated_task();
+    }
 
     info!("Starting TouchRelay server...");
+    eventlog::report(eventlog::Level::Info, eventlog::EventId::Started, "TouchRelay started");
 
+    #[cfg(feature = "gui")]
+    if args.no_tray {
+        run_headless();
+    } else {
+        run_tray();
+    }
+    // Console-only build
+    #[cfg(not(feature = "gui"))]
+    run_headless();
+
+    updates::install_pending();
+    info!("TouchRelay stopped");
+    eventlog::report(eventlog::Level::Info, eventlog::EventId::Stopped, "TouchRelay stopped");
+}
+
+/// Run the web server with a tray icon, menu and kill-switch hotkey until Quit
+#[cfg(feature = "gui")]
+fn run_tray() {
     // Create winit event loop
-    let event_loop = EventLoop::new().expect("Failed to create event loop");
+    let event_loop = EventLoop::<AppEvent>::with_user_event()
+        .build()
+        .expect("Failed to create event loop");
+    events::init(event_loop.create_proxy());
+    instance::listen();
 
-    // Load icon
+    // Load icons
     let icon = assets::load_icon();
+    let paused_icon = assets::load_paused_icon();
+    let connected_icon = assets::load_connected_icon();
 
-    // Get local IP address
-    let tooltip = match local_ip() {
-        Ok(ip) => {
-            let url = format!("http://{}:8000/", ip);
-            info!("Local access URL: {}", url);
-            format!("TouchRelay\n{}", url)
-        }
-        Err(_) => {
-            warn!("Failed to detect local IP address");
-            "TouchRelay\nhttp://<PC_IP>:8000/".to_string()
-        }
-    };
+    let tooltip = tooltip_text();
 
-    // Create tray menu
+    // Create tray menu; the startup and firewall check marks are filled in once
+    // schtasks and Windows Firewall have answered on worker threads
+    startup::refresh_cached();
+    firewall::refresh_rule();
     let tray_menu = TrayMenu::new();
 
     // Build tray icon
     let tray_icon = TrayIconBuilder::new()
         .with_menu(Box::new(tray_menu.menu().clone()))
         .with_tooltip(&tooltip)
-        .with_icon(icon)
+        .with_icon(icon.clone())
+        // Left clicks are for double-clicking, the menu opens on right click
+        .with_menu_on_left_click(false)
         .build()
         .expect("Failed to create tray icon");
 
     info!("System tray icon created");
 
-    // Start web server in a separate thread
-    std::thread::spawn(move || {
-        let runtime = tokio::runtime::Runtime::new().unwrap();
-        runtime.block_on(async {
-            run_server().await;
-        });
-    });
+    // Apply edits to config.toml while running
+    reload::watch();
+
+    // Look for new releases in the background
+    updates::spawn_checker();
+
+    // Tell clients what is playing
+    media::spawn_watcher();
+
+    // Track local physical input on this (event loop) thread
+    activity::install_hooks();
+
+    // Register kill-switch hotkey
+    let (hotkey_manager, kill_switch_id) = match register_kill_switch() {
+        Some((manager, id)) => (Some(manager), Some(id)),
+        None => (None, None),
+    };
+
+    let server = start_server_after(startup::launch_delay());
 
     // Create application handler
     let mut app = TrayApp {
         tray_icon,
         tray_menu,
+        normal_icon: icon,
+        paused_icon,
+        connected_icon,
+        server_error: None,
+        startup_notice_pending: !cli::get().autostart,
+        _hotkey_manager: hotkey_manager,
+        kill_switch_id,
+        flashes_left: 0,
+        next_flash: None,
+        server: Some(server),
     };
 
     // Run event loop in main thread
     event_loop.set_control_flow(ControlFlow::Wait);
     let _ = event_loop.run_app(&mut app);
+}
 
-    info!("TouchRelay stopped");
+/// Run the web server without a tray icon (`--no-tray` or a build without `gui`) until
+/// Ctrl+C, handling restart requests from the settings page, API and config reload
+fn run_headless() {
+    let events = events::init_channel();
+    instance::listen();
+    std::thread::spawn(|| {
+        let runtime = match tokio::runtime::Builder::new_current_thread().enable_all().build() {
+            Ok(runtime) => runtime,
+            Err(e) => {
+                error!("Failed to watch for Ctrl+C: {}", e);
+                return;
+            }
+        };
+        if runtime.block_on(tokio::signal::ctrl_c()).is_ok() {
+            events::send(AppEvent::Quit);
+        }
+    });
+
+    reload::watch();
+    updates::spawn_checker();
+    media::spawn_watcher();
+    activity::spawn_hook_thread();
+    let mut server = Some(start_server());
+    info!("Running without a tray icon, press Ctrl+C to stop");
+
+    let mut failure = None;
+    for event in events {
+        match event {
+            AppEvent::RestartServer => restart_server(&mut server),
+            AppEvent::Quit => break,
+            // Nobody would notice a console server sitting there without listening
+            AppEvent::ServerFailed(reason) => {
+                failure = Some(reason);
+                break;
+            }
+            AppEvent::ShowAddress => {
+                info!("Launched again; already running at {}", network::access_url().unwrap_or_else(network::placeholder_url));
+            }
+            // Retries are logged; only the final failure ends the process
+            AppEvent::StateChanged | AppEvent::ServerStarted | AppEvent::ServerRetrying(_) | AppEvent::ClientsChanged => {}
+        }
+    }
+    stop_server(server.take());
+
+    if let Some(reason) = failure {
+        eventlog::report(eventlog::Level::Info, eventlog::EventId::Stopped, "TouchRelay stopped");
+        error!("Exiting: {}", reason);
+        std::process::exit(1);
+    }
+}
+
+/// Stop the web server and start it again with the configuration on disk
+/// (port, bind address and everything else the server reads at startup)
+fn restart_server(server: &mut Option<std::thread::JoinHandle<()>>) {
+    info!("Restarting web server...");
+    stop_server(server.take());
+    if let Err(e) = config::reload() {
+        warn!("Keeping current settings: {}", e);
+    }
+    *server = Some(start_server());
+}
+
+/// Disconnect all clients and stop the web server, waiting up to `SHUTDOWN_TIMEOUT`
+fn stop_server(server: Option<std::thread::JoinHandle<()>>) {
+    let Some(server) = server else {
+        return;
+    };
+
+    shutdown::request();
+    let deadline = Instant::now() + SHUTDOWN_TIMEOUT;
+    while !server.is_finished() && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    if server.is_finished() {
+        let _ = server.join();
+    } else {
+        warn!("Web server did not stop within {:?}", SHUTDOWN_TIMEOUT);
+    }
+}
+
+/// Run the web server on its own thread and runtime
+fn start_server() -> std::thread::JoinHandle<()> {
+    start_server_after(Duration::ZERO)
+}
+
+/// Run the web server on its own thread after a delay (e.g. for the network after sign-in)
+fn start_server_after(delay: Duration) -> std::thread::JoinHandle<()> {
+    // Subscribe now, so Quit or a restart during the delay is not missed
+    let stopping = shutdown::requested();
+    let thread = std::thread::Builder::new().name(crash::SERVER_THREAD.to_string());
+    thread.spawn(move || {
+        let runtime = match tokio::runtime::Runtime::new() {
+            Ok(runtime) => runtime,
+            Err(e) => {
+                server_failed(&format!("Failed to start runtime: {}", e));
+                return;
+            }
+        };
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            runtime.block_on(async {
+                if !delay.is_zero() {
+                    info!("Starting the server in {:?}", delay);
+                    tokio::select! {
+                        _ = tokio::time::sleep(delay) => {}
+                        _ = stopping => return,
+                    }
+                }
+                run_server().await;
+            })
+        }));
+        // The panic hook already saved a crash report and told the user
+        if result.is_err() {
+            events::send(AppEvent::ServerFailed("stopped after an internal error".to_string()));
+        }
+    })
+    .expect("Failed to start the server thread")
+}
+
+/// Report that the web server is not running, in the log, a notification and the tray tooltip
+fn server_failed(reason: &str) {
+    error!("Server not running: {}", reason);
+    eventlog::report(eventlog::Level::Error, eventlog::EventId::ServerFailed, reason);
+    notify::show(&i18n::t("notify.could_not_start"), reason);
+    events::send(AppEvent::ServerFailed(reason.to_string()));
 }
 
 async fn run_server() {
+    // Resolves when Quit or a restart is requested
+    let mut stopping = Box::pin(shutdown::requested());
+    assets::init_etags();
+
     // Build router with embedded static files
     let app = Router::new()
         .route("/", get(assets::index_handler))
         .route("/ws", get(ws_handler))
         .route("/static/style.css", get(assets::css_handler))
         .route("/static/app.js", get(assets::js_handler))
-        .route("/static/icon.ico", get(assets::icon_handler));
+        .route("/static/icon.ico", get(assets::icon_handler))
+        .route("/static/icon-192.png", get(assets::icon_192_handler))
+        .route("/static/icon-512.png", get(assets::icon_512_handler))
+        .route("/manifest.json", get(assets::manifest_handler))
+        .route("/sw.js", get(assets::service_worker_handler))
+        .route("/qr.png", get(qr::qr_handler))
+        .route("/calibration", post(calibration::submit))
+        .route("/calibration/ping", get(calibration::ping))
+        .route("/layouts", get(layouts::list_handler))
+        .route("/layouts/{name}", get(layouts::get_handler))
+        .route("/packs/{pack}/icons/{file}", get(packs::icon_handler))
+        .route("/settings", get(settings::page).post(settings::save))
+        // Optional Basic auth for the web interface (the API uses its own token)
+        .layer(middleware::from_fn(auth::basic_auth))
+        .route("/healthz", get(health::healthz))
+        .route("/api/version", get(health::version))
+        .route("/api/i18n/{file}", get(i18n::web_handler))
+        .route("/api/theme", get(theme::theme_handler))
+        .route("/metrics", get(metrics::metrics_handler))
+        .route("/api/settings", get(api::get_settings).patch(api::patch_settings))
+        .route("/api/displays", get(api::list_displays))
+        .route("/api/audio/devices", get(api::list_audio_devices))
+        .route("/api/audio/sessions", get(api::list_audio_sessions))
+        .route("/api/command", post(api::run_command))
+        .route("/api/server/restart", post(api::restart_server))
+        .route("/api/layouts", get(api::list_layouts))
+        .route("/api/layouts/{name}", get(api::get_layout).put(api::put_layout).delete(api::delete_layout))
+        .route("/api/packs", get(api::list_packs).post(api::import_pack))
+        .route("/api/packs/{name}", axum::routing::patch(api::patch_pack).delete(api::delete_pack));
+
+    // Mount under the configured prefix for reverse proxies that forward the path unchanged
+    let base = proxy::base_path();
+    let app = if base.is_empty() {
+        app
+    } else {
+        info!("Serving under {}", base);
+        Router::new()
+            .route(&format!("{}/", base), get(assets::index_handler).layer(middleware::from_fn(auth::basic_auth)))
+            .nest(&base, app)
+    };
+    // Compress the web client for slow Wi-Fi (skipped for images and WebSocket upgrades)
+    let app = app.layer(CompressionLayer::new());
+
+    let mut delay = BIND_RETRY_DELAY;
+    let mut attempt = 1;
+    let listeners = loop {
+        match network::bind_listeners().await {
+            Ok(listeners) => break listeners,
+            Err(e) if attempt < BIND_ATTEMPTS => {
+                warn!("Failed to start server (attempt {} of {}): {}, retrying in {:?}", attempt, BIND_ATTEMPTS, e, delay);
+                events::send(AppEvent::ServerRetrying(format!("{} (retrying)", e)));
+                tokio::select! {
+                    _ = tokio::time::sleep(delay) => {}
+                    _ = &mut stopping => return,
+                }
+                delay *= 2;
+                attempt += 1;
+            }
+            Err(e) => {
+                server_failed(&e);
+                return;
+            }
+        }
+    };
+
+    for listener in &listeners {
+        if let Ok(addr) = listener.local_addr() {
+            info!("Server listening on http://{}", addr);
+        }
+    }
+    if network::port() != network::configured_port() {
+        warn!("Port {} is in use, listening on port {} instead", network::configured_port(), network::port());
+        let message = i18n::t_with(
+            "notify.port_fallback",
+            &[("configured", &network::configured_port().to_string()), ("port", &network::port().to_string())],
+        );
+        notify::show("TouchRelay", &message);
+    }
+    if network::is_loopback_only() {
+        info!("Loopback-only mode: not reachable from other devices");
+    }
+    info!("Access URL: {}", network::access_url().unwrap_or_else(network::placeholder_url));
+    events::send(AppEvent::ServerStarted);
+    mdns::advertise();
+    tokio::spawn(discovery::run());
+    tokio::spawn(display::watch());
+    let mqtt = tokio::spawn(mqtt::run());
 
-    let addr = "0.0.0.0:8000";
-    let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
+    // Tell connected clients why they are being dropped while the listeners stop
+    tokio::spawn(async move {
+        stopping.await;
+        info!("Shutting down, closing {} connections", clients::count());
+        clients::close_all("Server shutting down");
+    });
 
-    info!("Server listening on http://{}", addr);
-    info!("Access from mobile: http://<PC_IP>:8000/");
+    // Run one server per listener (IPv4 and IPv6)
+    let mut servers = tokio::task::JoinSet::new();
+    for listener in listeners {
+        let service = app.clone().into_make_service_with_connect_info::<SocketAddr>();
+        servers.spawn(async move { axum::serve(listener, service).with_graceful_shutdown(shutdown::requested()).await });
+    }
 
-    // Run server
-    axum::serve(listener, app)
-        .await
-        .unwrap();
+    // Loopback-only endpoint for native companion apps, without Basic auth or pairing
+    let companion_port = config::get().companion_port;
+    if companion_port != 0 {
+        let addr = SocketAddr::from(([127, 0, 0, 1], companion_port));
+        match tokio::net::TcpListener::bind(addr).await {
+            Ok(listener) => {
+                info!("Companion endpoint listening on ws://{}/ws", addr);
+                let companion = Router::new().route("/ws", get(companion_ws_handler));
+                let service = companion.into_make_service_with_connect_info::<SocketAddr>();
+                servers.spawn(async move { axum::serve(listener, service).with_graceful_shutdown(shutdown::requested()).await });
+            }
+            Err(e) => error!("Failed to start companion endpoint on {}: {}", addr, e),
+        }
+    }
+    while let Some(result) = servers.join_next().await {
+        match result {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => server_failed(&format!("Server error: {}", e)),
+            Err(e) => server_failed(&format!("Server task failed: {}", e)),
+        }
+    }
+
+    // Let sessions send their close frames and release held keys before the runtime goes away
+    let deadline = Instant::now() + CLOSE_GRACE;
+    while clients::count() > 0 && Instant::now() < deadline {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    // The MQTT bridge publishes "offline" on its way out
+    let _ = mqtt.await;
 
     info!("TouchRelay server stopped");
 }
 
-async fn ws_handler(ws: WebSocketUpgrade) -> impl IntoResponse {
-    ws.on_upgrade(handler::handle_socket)
+async fn ws_handler(
+    ws: yawc::IncomingUpgrade,
+    Query(params): Query<handler::ConnectParams>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let addr = proxy::client_addr(peer, &headers);
+    upgrade(ws, params, addr, handler::Endpoint::Lan)
+}
+
+async fn companion_ws_handler(
+    ws: yawc::IncomingUpgrade,
+    Query(params): Query<handler::ConnectParams>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    // Native apps send no Origin; a browser does, and any web page open on this PC could
+    // otherwise reach the endpoint, which skips authentication and pairing
+    if let Some(origin) = headers.get(axum::http::header::ORIGIN) {
+        warn!("Rejected companion connection from a web page ({:?})", origin);
+        return axum::http::StatusCode::FORBIDDEN.into_response();
+    }
+    upgrade(ws, params, addr, handler::Endpoint::Companion)
+}
+
+/// Accept a WebSocket upgrade, negotiating permessage-deflate with clients that offer it
+fn upgrade(
+    ws: yawc::IncomingUpgrade,
+    params: handler::ConnectParams,
+    addr: SocketAddr,
+    endpoint: handler::Endpoint,
+) -> axum::response::Response {
+    let mut options = yawc::Options::default().with_no_delay();
+    if config::get().ws_compression {
+        // Favor speed: most messages are short and latency matters more than ratio
+        options = options.with_low_latency_compression();
+    }
+
+    let (response, upgrade) = match ws.upgrade(options) {
+        Ok(upgrade) => upgrade,
+        Err(e) => {
+            warn!("WebSocket upgrade from {} failed: {}", addr.ip(), e);
+            return axum::http::StatusCode::BAD_REQUEST.into_response();
+        }
+    };
+    tokio::spawn(async move {
+        match upgrade.await {
+            Ok(socket) => handler::handle_socket(socket, params, addr, endpoint).await,
+            Err(e) => warn!("WebSocket upgrade from {} failed: {}", addr.ip(), e),
+        }
+    });
+    response.into_response()
 }
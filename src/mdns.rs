@@ -0,0 +1,52 @@
+use mdns_sd::{ServiceDaemon, ServiceInfo};
+use std::sync::Mutex;
+use tracing::{info, warn};
+
+const SERVICE_TYPES: [&str; 2] = ["_touchrelay._tcp.local.", "_http._tcp.local."];
+const HOST_NAME: &str = "touchrelay.local.";
+
+// Kept alive while the server runs so the records keep being answered
+static DAEMON: Mutex<Option<ServiceDaemon>> = Mutex::new(None);
+
+/// Build the service record, announcing the bound address or every LAN address
+fn service_info(service_type: &str, instance: &str, port: u16) -> Result<ServiceInfo, mdns_sd::Error> {
+    let properties = [("path", "/"), ("version", env!("CARGO_PKG_VERSION"))];
+    match crate::network::bound_ip().filter(|ip| !ip.is_unspecified()) {
+        Some(ip) => ServiceInfo::new(service_type, instance, HOST_NAME, ip, port, &properties[..]),
+        None => ServiceInfo::new(service_type, instance, HOST_NAME, (), port, &properties[..])
+            .map(ServiceInfo::enable_addr_auto),
+    }
+}
+
+/// Advertise the web interface via mDNS as `touchrelay.local`, replacing any
+/// earlier announcement (the port may have changed after a server restart)
+pub fn advertise() {
+    if let Some(previous) = DAEMON.lock().unwrap().take() {
+        let _ = previous.shutdown();
+    }
+    if !crate::config::get().mdns || crate::network::is_loopback_only() {
+        return;
+    }
+
+    let daemon = match ServiceDaemon::new() {
+        Ok(daemon) => daemon,
+        Err(e) => {
+            warn!("Failed to start mDNS responder: {}", e);
+            return;
+        }
+    };
+
+    let computer = std::env::var("COMPUTERNAME").unwrap_or_else(|_| "PC".to_string());
+    let instance = format!("TouchRelay on {}", computer);
+    let port = crate::network::port();
+
+    for service_type in SERVICE_TYPES {
+        let result = service_info(service_type, &instance, port).and_then(|info| daemon.register(info));
+        if let Err(e) = result {
+            warn!("Failed to advertise {} via mDNS: {}", service_type, e);
+        }
+    }
+
+    info!("Advertising via mDNS as http://{}:{}/", HOST_NAME.trim_end_matches('.'), port);
+    *DAEMON.lock().unwrap() = Some(daemon);
+}
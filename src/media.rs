@@ -0,0 +1,86 @@
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::{debug, warn};
+use windows::Media::Control::{
+    GlobalSystemMediaTransportControlsSessionManager as SessionManager,
+    GlobalSystemMediaTransportControlsSessionPlaybackStatus as PlaybackStatus,
+};
+use windows::Win32::System::Com::{CoInitializeEx, COINIT_MULTITHREADED};
+
+// Media apps do not announce track changes to us, so the current session is polled
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// What is playing, as pushed to clients with `["now_playing", {...}]`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NowPlaying {
+    pub title: String,
+    pub artist: String,
+    /// `playing`, `paused`, `stopped` or `changing`
+    pub status: &'static str,
+    /// App playing it, e.g. `Spotify.exe` or a browser's app ID
+    pub app: String,
+}
+
+/// Last state sent to clients, for those that connect later
+static CURRENT: Mutex<Option<NowPlaying>> = Mutex::new(None);
+
+/// What is playing right now, if any app reports it
+pub fn current() -> Option<NowPlaying> {
+    CURRENT.lock().unwrap().clone()
+}
+
+/// Follow the media session Windows shows in its volume flyout and tell clients when the
+/// track or playback state changes (`["now_playing", null]` when nothing plays any more)
+pub fn spawn_watcher() {
+    std::thread::spawn(|| {
+        // Fails harmlessly if this thread already uses COM
+        let _ = unsafe { CoInitializeEx(None, COINIT_MULTITHREADED) };
+        let manager = match SessionManager::RequestAsync().and_then(|request| request.get()) {
+            Ok(manager) => manager,
+            Err(e) => {
+                warn!("Now playing information is unavailable: {}", e);
+                return;
+            }
+        };
+        loop {
+            let now_playing = read(&manager).unwrap_or_else(|e| {
+                debug!("Failed to read the media session: {}", e);
+                None
+            });
+            let changed = {
+                let mut current = CURRENT.lock().unwrap();
+                let changed = *current != now_playing;
+                *current = now_playing.clone();
+                changed
+            };
+            if changed {
+                debug!("Now playing: {:?}", now_playing);
+                crate::clients::broadcast(&serde_json::json!(["now_playing", now_playing]));
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    });
+}
+
+fn read(manager: &SessionManager) -> windows::core::Result<Option<NowPlaying>> {
+    // Fails when no app has a media session
+    let Ok(session) = manager.GetCurrentSession() else {
+        return Ok(None);
+    };
+    let properties = session.TryGetMediaPropertiesAsync()?.get()?;
+    let status = match session.GetPlaybackInfo()?.PlaybackStatus()? {
+        PlaybackStatus::Playing => "playing",
+        PlaybackStatus::Paused => "paused",
+        PlaybackStatus::Changing => "changing",
+        PlaybackStatus::Stopped => "stopped",
+        // Closed or just opened, nothing to show yet
+        _ => return Ok(None),
+    };
+    Ok(Some(NowPlaying {
+        title: properties.Title()?.to_string(),
+        artist: properties.Artist()?.to_string(),
+        status,
+        app: session.SourceAppUserModelId()?.to_string(),
+    }))
+}
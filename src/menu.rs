@@ -1,22 +1,80 @@
-use tray_icon::menu::{Menu, MenuItem, MenuId};
+use std::net::IpAddr;
+use tray_icon::menu::{CheckMenuItem, Menu, MenuItem, MenuId, Submenu};
 use tracing::{info, error};
-use local_ip_address::local_ip;
+
+use crate::i18n::{t, t_with};
+
+/// Ports offered in the Port submenu
+const COMMON_PORTS: [u16; 4] = [8000, 8080, 8888, 9000];
+
+/// Pointer speed presets: label key and multiplier
+const POINTER_SPEEDS: [(&str, f64); 3] = [("menu.speed_slow", 0.5), ("menu.speed_normal", 1.0), ("menu.speed_fast", 1.5)];
 
 // Menu action enum for handling menu events
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MenuAction {
     OpenWeb,
+    CopyUrl,
+    ShowQr,
+    SelectAddress(Option<IpAddr>),
+    // Index into the pack names captured when the menu was built
+    TogglePack(usize),
+    RemovePack(usize),
+    DisconnectClient(u64),
+    // Index into POINTER_SPEEDS
+    SetPointerSpeed(usize),
+    CustomPointerSpeed,
+    SetPort(u16),
+    CustomPort,
+    OpenPacksFolder,
+    TogglePause,
     ToggleStartup,
+    ToggleFirewallRule,
+    RestartServer,
+    RestartElevated,
+    OpenLogFolder,
+    // None = back to the configured log_level
+    SetLogLevel(Option<&'static str>),
+    ToggleConsole,
+    CheckForUpdates,
     About,
     Quit,
     None,
 }
 
-// Tray menu structure with all menu items
+// Tray menu structure with all menu items, kept to update them in place
 pub struct TrayMenu {
     menu: Menu,
     open_web_id: MenuId,
-    startup_id: MenuId,
+    copy_url_id: MenuId,
+    qr_id: MenuId,
+    address_menu: Submenu,
+    // Entries of the advertised address submenu (None = automatic)
+    address_items: Vec<(CheckMenuItem, Option<IpAddr>)>,
+    // Addresses the submenu was built for, so it is only rebuilt when they change
+    addresses: Vec<(String, IpAddr)>,
+    packs_menu: Submenu,
+    packs: PackEntries,
+    clients_menu: Submenu,
+    // Disconnect items keyed by client ID
+    client_ids: Vec<(MenuId, u64)>,
+    // Clients and labels the submenu was built for
+    clients: Vec<(u64, String)>,
+    speed_items: Vec<CheckMenuItem>,
+    custom_speed_item: CheckMenuItem,
+    port_menu: Submenu,
+    port_items: Vec<(CheckMenuItem, u16)>,
+    custom_port_item: CheckMenuItem,
+    pause_item: MenuItem,
+    startup_item: CheckMenuItem,
+    firewall_item: CheckMenuItem,
+    restart_id: MenuId,
+    // Only offered while TouchRelay runs without administrator rights
+    elevate_item: Option<MenuItem>,
+    log_folder_id: MenuId,
+    log_level_items: Vec<(CheckMenuItem, Option<&'static str>)>,
+    console_item: CheckMenuItem,
+    updates_id: MenuId,
     about_id: MenuId,
     quit_id: MenuId,
 }
@@ -26,49 +84,242 @@ impl TrayMenu {
     pub fn new() -> Self {
         let menu = Menu::new();
 
-        // Create menu items
-        let open_web_item = MenuItem::new("Open Web Interface", true, None);
+        // Create menu items; check marks and labels are filled in by refresh()
+        let open_web_item = MenuItem::new(t("menu.open_web"), true, None);
+        let copy_url_item = MenuItem::new(t("menu.copy_url"), true, None);
+        let qr_item = MenuItem::new(t("menu.show_qr"), true, None);
 
-        let is_startup_enabled = crate::startup::is_startup_enabled();
-        let startup_text = if is_startup_enabled {
-            "✓ Start with Windows"
-        } else {
-            "Start with Windows"
-        };
-        let startup_item = MenuItem::new(startup_text, true, None);
+        let address_menu = Submenu::new(t("menu.advertised_address"), true);
+        let addresses = crate::network::lan_addresses();
+        let address_items = fill_address_submenu(&address_menu, &addresses);
+
+        let packs_menu = Submenu::new(t("menu.packs"), true);
+        let packs = fill_packs_submenu(&packs_menu);
+
+        let clients_menu = Submenu::new(t("menu.connected_devices"), true);
+        let clients = client_labels();
+        let client_ids = fill_clients_submenu(&clients_menu, &clients);
+
+        let speed_menu = Submenu::new(t("menu.pointer_speed"), true);
+        let speed_items: Vec<CheckMenuItem> = POINTER_SPEEDS
+            .iter()
+            .map(|(key, _)| CheckMenuItem::new(t(key), true, false, None))
+            .collect();
+        let custom_speed_item = CheckMenuItem::new(t("menu.speed_custom"), true, false, None);
+        for item in &speed_items {
+            speed_menu.append(item).unwrap();
+        }
+        speed_menu.append(&custom_speed_item).unwrap();
+
+        let port_menu = Submenu::new(t("menu.port"), true);
+        let port_items: Vec<(CheckMenuItem, u16)> = COMMON_PORTS
+            .iter()
+            .map(|port| (CheckMenuItem::new(port.to_string(), true, false, None), *port))
+            .collect();
+        let custom_port_item = CheckMenuItem::new(t("menu.port_custom"), true, false, None);
+        for (item, _) in &port_items {
+            port_menu.append(item).unwrap();
+        }
+        port_menu.append(&custom_port_item).unwrap();
+
+        let pause_item = MenuItem::new(t("menu.pause"), true, None);
+        let startup_item = CheckMenuItem::new(t("menu.start_with_windows"), true, false, None);
+        let firewall_item = CheckMenuItem::new(t("menu.allow_through_firewall"), true, false, None);
+        let restart_item = MenuItem::new(t("menu.restart_server"), true, None);
+        let elevate_item = (!crate::elevation::is_elevated()).then(|| MenuItem::new(t("menu.restart_elevated"), true, None));
+        let log_folder_item = MenuItem::new(t("menu.open_log_folder"), true, None);
 
-        let about_item = MenuItem::new("About", true, None);
-        let quit_item = MenuItem::new("Quit", true, None);
+        let log_level_menu = Submenu::new(t("menu.log_level"), true);
+        let log_level_items: Vec<(CheckMenuItem, Option<&'static str>)> = std::iter::once((t("menu.log_level_configured"), None))
+            .chain(crate::logging::LEVELS.into_iter().map(|level| (level.to_string(), Some(level))))
+            .map(|(text, level)| (CheckMenuItem::new(text, true, false, None), level))
+            .collect();
+        for (item, _) in &log_level_items {
+            log_level_menu.append(item).unwrap();
+        }
 
-        // Get menu IDs
-        let open_web_id = open_web_item.id().clone();
-        let startup_id = startup_item.id().clone();
-        let about_id = about_item.id().clone();
-        let quit_id = quit_item.id().clone();
+        let console_item = CheckMenuItem::new(t("menu.debug_console"), true, false, None);
+        let updates_item = MenuItem::new(t("menu.check_for_updates"), true, None);
+        let about_item = MenuItem::new(t("menu.about"), true, None);
+        let quit_item = MenuItem::new(t("menu.quit"), true, None);
 
         // Append items to menu
         menu.append(&open_web_item).unwrap();
+        menu.append(&copy_url_item).unwrap();
+        menu.append(&qr_item).unwrap();
+        menu.append(&address_menu).unwrap();
+        menu.append(&port_menu).unwrap();
+        menu.append(&packs_menu).unwrap();
+        menu.append(&clients_menu).unwrap();
+        menu.append(&speed_menu).unwrap();
+        menu.append(&pause_item).unwrap();
         menu.append(&startup_item).unwrap();
+        menu.append(&firewall_item).unwrap();
+        menu.append(&restart_item).unwrap();
+        if let Some(item) = &elevate_item {
+            menu.append(item).unwrap();
+        }
+        menu.append(&log_folder_item).unwrap();
+        menu.append(&log_level_menu).unwrap();
+        menu.append(&console_item).unwrap();
+        menu.append(&updates_item).unwrap();
         menu.append(&about_item).unwrap();
         menu.append(&quit_item).unwrap();
 
+        let mut tray_menu = Self {
+            menu,
+            open_web_id: open_web_item.id().clone(),
+            copy_url_id: copy_url_item.id().clone(),
+            qr_id: qr_item.id().clone(),
+            address_menu,
+            address_items,
+            addresses,
+            packs_menu,
+            packs,
+            clients_menu,
+            client_ids,
+            clients,
+            speed_items,
+            custom_speed_item,
+            port_menu,
+            port_items,
+            custom_port_item,
+            pause_item,
+            startup_item,
+            firewall_item,
+            restart_id: restart_item.id().clone(),
+            elevate_item,
+            log_folder_id: log_folder_item.id().clone(),
+            log_level_items,
+            console_item,
+            updates_id: updates_item.id().clone(),
+            about_id: about_item.id().clone(),
+            quit_id: quit_item.id().clone(),
+        };
+        tray_menu.refresh();
         info!("Tray menu created");
+        tray_menu
+    }
 
-        Self {
-            menu,
-            open_web_id,
-            startup_id,
-            about_id,
-            quit_id,
+    /// Bring the menu up to date with the pause state, clients and settings. Check marks
+    /// and labels change in place; a submenu is only rebuilt when its entries changed.
+    pub fn refresh(&mut self) {
+        let config = crate::config::get();
+
+        // Not applicable when listening on loopback or a single configured address
+        let addresses = crate::network::lan_addresses();
+        if addresses != self.addresses {
+            self.address_items = fill_address_submenu(&self.address_menu, &addresses);
+            self.addresses = addresses;
+        }
+        let fixed = crate::network::is_loopback_only()
+            || crate::network::bound_ip().is_some_and(|ip| !ip.is_unspecified());
+        self.address_menu.set_enabled(!fixed);
+        for (item, ip) in &self.address_items {
+            item.set_enabled(!fixed);
+            item.set_checked(config.advertise_ip == *ip);
+        }
+
+        // --port and TOUCHRELAY_PORT win over the config file
+        let fixed = crate::network::configured_port() != config.port;
+        self.port_menu.set_enabled(!fixed);
+        for (item, port) in &self.port_items {
+            item.set_enabled(!fixed);
+            item.set_checked(config.port == *port);
+        }
+        let is_custom = !COMMON_PORTS.contains(&config.port);
+        self.custom_port_item.set_enabled(!fixed);
+        self.custom_port_item.set_checked(is_custom);
+        self.custom_port_item.set_text(if is_custom {
+            t_with("menu.port_custom_value", &[("port", &config.port.to_string())])
+        } else {
+            t("menu.port_custom")
+        });
+
+        if pack_states() != self.packs.states() {
+            self.packs = fill_packs_submenu(&self.packs_menu);
+        }
+
+        let clients = client_labels();
+        if clients != self.clients {
+            self.client_ids = fill_clients_submenu(&self.clients_menu, &clients);
+            self.clients = clients;
+        }
+
+        let speed = config.pointer_speed;
+        for (item, (_, preset)) in self.speed_items.iter().zip(POINTER_SPEEDS) {
+            item.set_checked(speed == preset);
+        }
+        let is_custom = !POINTER_SPEEDS.iter().any(|(_, preset)| *preset == speed);
+        self.custom_speed_item.set_checked(is_custom);
+        self.custom_speed_item.set_text(if is_custom {
+            t_with("menu.speed_custom_value", &[("speed", &speed.to_string())])
+        } else {
+            t("menu.speed_custom")
+        });
+
+        self.pause_item.set_text(if crate::pause::is_paused() { t("menu.resume") } else { t("menu.pause") });
+        self.startup_item.set_checked(crate::startup::is_startup_enabled_cached());
+        self.firewall_item.set_checked(crate::firewall::has_rule());
+        if let Some(item) = &self.elevate_item {
+            item.set_text(if crate::elevation::input_was_blocked() {
+                t("menu.restart_elevated_blocked")
+            } else {
+                t("menu.restart_elevated")
+            });
+        }
+
+        let selected = crate::logging::override_level();
+        for (item, level) in &self.log_level_items {
+            item.set_checked(selected.as_deref() == *level);
         }
+        self.console_item.set_checked(crate::console::is_open());
     }
 
     /// Handle menu event and return the corresponding action
     pub fn handle_event(&self, event_id: &MenuId) -> MenuAction {
         if event_id == &self.open_web_id {
             MenuAction::OpenWeb
-        } else if event_id == &self.startup_id {
+        } else if event_id == &self.copy_url_id {
+            MenuAction::CopyUrl
+        } else if event_id == &self.qr_id {
+            MenuAction::ShowQr
+        } else if let Some((_, ip)) = self.address_items.iter().find(|(item, _)| item.id() == event_id) {
+            MenuAction::SelectAddress(*ip)
+        } else if let Some(index) = self.packs.toggle_ids.iter().position(|id| id == event_id) {
+            MenuAction::TogglePack(index)
+        } else if let Some(index) = self.packs.remove_ids.iter().position(|id| id == event_id) {
+            MenuAction::RemovePack(index)
+        } else if event_id == &self.packs.folder_id {
+            MenuAction::OpenPacksFolder
+        } else if let Some((_, client)) = self.client_ids.iter().find(|(id, _)| id == event_id) {
+            MenuAction::DisconnectClient(*client)
+        } else if let Some(index) = self.speed_items.iter().position(|item| item.id() == event_id) {
+            MenuAction::SetPointerSpeed(index)
+        } else if event_id == self.custom_speed_item.id() {
+            MenuAction::CustomPointerSpeed
+        } else if let Some((_, port)) = self.port_items.iter().find(|(item, _)| item.id() == event_id) {
+            MenuAction::SetPort(*port)
+        } else if event_id == self.custom_port_item.id() {
+            MenuAction::CustomPort
+        } else if event_id == self.pause_item.id() {
+            MenuAction::TogglePause
+        } else if event_id == self.startup_item.id() {
             MenuAction::ToggleStartup
+        } else if event_id == self.firewall_item.id() {
+            MenuAction::ToggleFirewallRule
+        } else if event_id == &self.restart_id {
+            MenuAction::RestartServer
+        } else if self.elevate_item.as_ref().is_some_and(|item| item.id() == event_id) {
+            MenuAction::RestartElevated
+        } else if event_id == &self.log_folder_id {
+            MenuAction::OpenLogFolder
+        } else if let Some((_, level)) = self.log_level_items.iter().find(|(item, _)| item.id() == event_id) {
+            MenuAction::SetLogLevel(*level)
+        } else if event_id == self.console_item.id() {
+            MenuAction::ToggleConsole
+        } else if event_id == &self.updates_id {
+            MenuAction::CheckForUpdates
         } else if event_id == &self.about_id {
             MenuAction::About
         } else if event_id == &self.quit_id {
@@ -91,18 +342,121 @@ impl TrayMenu {
                 open_web_interface();
                 false
             }
+            MenuAction::CopyUrl => {
+                copy_url();
+                false
+            }
+            MenuAction::ShowQr => {
+                crate::qr::show();
+                false
+            }
+            MenuAction::SelectAddress(ip) => {
+                match crate::config::update(|config| config.advertise_ip = ip) {
+                    Ok(()) => info!("Advertised address set to {}", ip.map_or("automatic".to_string(), |ip| ip.to_string())),
+                    Err(e) => error!("Failed to save advertised address: {}", e),
+                }
+                true // Check mark follows the selection
+            }
+            MenuAction::TogglePack(index) => {
+                let name = &self.packs.names[index];
+                let enabled = !crate::config::get().disabled_packs.contains(name);
+                if let Err(e) = crate::packs::set_enabled(name, !enabled) {
+                    error!("Failed to toggle pack {}: {}", name, e);
+                }
+                true // Check mark follows the pack state
+            }
+            MenuAction::RemovePack(index) => {
+                let name = &self.packs.names[index];
+                let prompt = t_with("menu.remove_pack_prompt", &[("name", name)]);
+                if !crate::dialog::confirm("TouchRelay", &prompt) {
+                    return false;
+                }
+                if let Err(e) = crate::packs::remove(name) {
+                    error!("Failed to remove pack {}: {}", name, e);
+                }
+                true // Removed packs disappear from the submenu
+            }
+            MenuAction::OpenPacksFolder => {
+                let dir = crate::packs::packs_dir();
+                if let Err(e) = std::fs::create_dir_all(&dir).and_then(|()| open::that(&dir)) {
+                    error!("Failed to open {}: {}", dir.display(), e);
+                }
+                false
+            }
+            MenuAction::DisconnectClient(id) => {
+                crate::clients::disconnect(id);
+                false // The menu is rebuilt once the client is gone
+            }
+            MenuAction::SetPointerSpeed(index) => {
+                set_pointer_speed(POINTER_SPEEDS[index].1);
+                true // Check mark follows the selection
+            }
+            MenuAction::CustomPointerSpeed => {
+                ask_pointer_speed();
+                true // Check mark follows the selection
+            }
+            MenuAction::SetPort(port) => {
+                set_port(port);
+                true // Check mark follows the selection
+            }
+            MenuAction::CustomPort => {
+                ask_port();
+                true // Check mark follows the selection
+            }
+            MenuAction::TogglePause => {
+                crate::pause::toggle();
+                true // Menu label reflects the pause state
+            }
             MenuAction::ToggleStartup => {
                 info!("Toggling startup...");
-                crate::startup::toggle_startup();
-                true // Return true to indicate menu should be updated
+                // schtasks can take a while, so keep it off the UI thread; the menu
+                // shows the outcome once it is done, in case it failed
+                std::thread::spawn(|| {
+                    crate::startup::toggle_startup();
+                    crate::events::send(crate::events::AppEvent::StateChanged);
+                });
+                false
             }
-            MenuAction::About => {
-                info!("Opening GitHub page...");
-                if let Err(e) = open::that("https://github.com/DeltaFoundry/TouchRelay") {
-                    error!("Failed to open GitHub page: {}", e);
+            MenuAction::ToggleFirewallRule => {
+                toggle_firewall_rule();
+                false // The menu is rebuilt once the change is done
+            }
+            MenuAction::RestartServer => {
+                info!("Restart server action triggered");
+                false // Restart is handled by caller
+            }
+            MenuAction::RestartElevated => {
+                info!("Restarting as administrator...");
+                match crate::elevation::relaunch() {
+                    Ok(()) => crate::events::send(crate::events::AppEvent::Quit),
+                    Err(e) => error!("{}", e),
                 }
                 false
             }
+            MenuAction::OpenLogFolder => {
+                let dir = crate::logging::log_dir();
+                if let Err(e) = std::fs::create_dir_all(&dir).and_then(|()| open::that(&dir)) {
+                    error!("Failed to open {}: {}", dir.display(), e);
+                }
+                false
+            }
+            MenuAction::SetLogLevel(level) => {
+                crate::logging::set_override(level);
+                true // Check mark follows the selection
+            }
+            MenuAction::ToggleConsole => {
+                crate::console::toggle();
+                true // Check mark follows the console
+            }
+            MenuAction::CheckForUpdates => {
+                info!("Checking for updates...");
+                crate::updates::check_now();
+                false
+            }
+            MenuAction::About => {
+                crate::about::show();
+                false
+            }
             MenuAction::Quit => {
                 info!("Quit action triggered");
                 false // Quit is handled by caller
@@ -114,24 +468,205 @@ impl TrayMenu {
     }
 }
 
-/// Open the web interface in the default browser
-fn open_web_interface() {
-    match local_ip() {
-        Ok(ip) => {
-            let url = format!("http://{}:8000/", ip);
-            info!("Opening web interface: {}", url);
-            if let Err(e) = open::that(&url) {
-                error!("Failed to open web interface: {}", e);
-            }
+/// Remove every entry of a submenu before it is filled again
+fn clear(submenu: &Submenu) {
+    while submenu.remove_at(0).is_some() {}
+}
+
+/// Fill the submenu for choosing which LAN address is advertised (check marks come from refresh())
+fn fill_address_submenu(submenu: &Submenu, addresses: &[(String, IpAddr)]) -> Vec<(CheckMenuItem, Option<IpAddr>)> {
+    clear(submenu);
+    let mut items = vec![(CheckMenuItem::new(t("menu.automatic"), true, false, None), None)];
+    for (name, ip) in addresses {
+        items.push((CheckMenuItem::new(format!("{} ({})", ip, name), true, false, None), Some(*ip)));
+    }
+    for (item, _) in &items {
+        submenu.append(item).unwrap();
+    }
+    items
+}
+
+/// Add or remove the firewall rule in the background, since Windows asks for administrator rights first
+fn toggle_firewall_rule() {
+    let enable = !crate::firewall::has_rule();
+    let port = crate::network::configured_port();
+    std::thread::spawn(move || {
+        match crate::firewall::set_rule_elevated(enable, port) {
+            Ok(()) => info!("Firewall rule {}", if enable { "added" } else { "removed" }),
+            Err(e) => error!("{}", e),
         }
-        Err(e) => {
-            error!("Failed to get local IP address: {}", e);
-            // Fallback to localhost
-            let url = "http://127.0.0.1:8000/";
-            info!("Opening web interface (localhost): {}", url);
-            if let Err(e) = open::that(url) {
-                error!("Failed to open web interface: {}", e);
+        crate::events::send(crate::events::AppEvent::StateChanged);
+    });
+}
+
+/// Save a new port and restart the server on it
+fn set_port(port: u16) {
+    if port == crate::config::get().port {
+        return;
+    }
+    match crate::config::update(|config| config.port = port) {
+        Ok(()) => {
+            info!("Port set to {}, restarting the server", port);
+            crate::events::send(crate::events::AppEvent::RestartServer);
+            // Move the firewall rule along to the new port
+            if crate::firewall::has_rule() {
+                std::thread::spawn(move || {
+                    if let Err(e) = crate::firewall::set_rule_elevated(true, port) {
+                        error!("{}", e);
+                    }
+                });
             }
         }
+        Err(e) => error!("Failed to save port: {}", e),
+    }
+}
+
+/// Ask for a port number and switch to it if it is valid
+fn ask_port() {
+    let current = crate::config::get().port.to_string();
+    let Some(text) = crate::dialog::prompt("TouchRelay", &t("menu.port_prompt"), &current) else {
+        return;
+    };
+    match text.trim().parse::<u16>() {
+        Ok(port) if port != 0 => set_port(port),
+        _ => crate::dialog::error("TouchRelay", &t("menu.port_invalid")),
+    }
+}
+
+/// Save a new `pointer_speed`, which applies to the next pointer move
+fn set_pointer_speed(speed: f64) {
+    match crate::config::update(|config| config.pointer_speed = speed) {
+        Ok(()) => info!("Pointer speed set to {}", speed),
+        Err(e) => error!("Failed to save pointer speed: {}", e),
+    }
+}
+
+/// Ask for a pointer speed multiplier and save it if it is valid
+fn ask_pointer_speed() {
+    let (min, max) = crate::config::POINTER_SPEED_RANGE;
+    let range = [("min", min.to_string()), ("max", max.to_string())];
+    let args: Vec<(&str, &str)> = range.iter().map(|(name, value)| (*name, value.as_str())).collect();
+    let current = crate::config::get().pointer_speed.to_string();
+
+    let Some(text) = crate::dialog::prompt("TouchRelay", &t_with("menu.speed_prompt", &args), &current) else {
+        return;
+    };
+    match text.trim().replace(',', ".").parse::<f64>() {
+        Ok(speed) if (min..=max).contains(&speed) => set_pointer_speed(speed),
+        _ => crate::dialog::error("TouchRelay", &t_with("menu.speed_invalid", &args)),
+    }
+}
+
+/// Connected devices with the label shown for them
+fn client_labels() -> Vec<(u64, String)> {
+    crate::clients::list()
+        .into_iter()
+        .map(|client| {
+            let label = match &client.name {
+                Some(name) => format!("{} ({})", name, client.ip),
+                None => client.ip.to_string(),
+            };
+            (client.id, label)
+        })
+        .collect()
+}
+
+/// Fill the submenu listing connected devices, each with a Disconnect item
+fn fill_clients_submenu(submenu: &Submenu, clients: &[(u64, String)]) -> Vec<(MenuId, u64)> {
+    clear(submenu);
+    let mut ids = Vec::new();
+
+    for (id, label) in clients {
+        let client_menu = Submenu::new(label, true);
+        let disconnect = MenuItem::new(t("menu.disconnect"), true, None);
+        client_menu.append(&disconnect).unwrap();
+        submenu.append(&client_menu).unwrap();
+        ids.push((disconnect.id().clone(), *id));
+    }
+
+    if ids.is_empty() {
+        submenu.append(&MenuItem::new(t("menu.no_devices"), false, None)).unwrap();
+    }
+
+    ids
+}
+
+/// Menu ids of the packs submenu
+struct PackEntries {
+    names: Vec<String>,
+    // Whether each pack was enabled when the submenu was built
+    enabled: Vec<bool>,
+    toggle_ids: Vec<MenuId>,
+    remove_ids: Vec<MenuId>,
+    folder_id: MenuId,
+}
+
+impl PackEntries {
+    /// Packs and their enabled state as shown in the submenu
+    fn states(&self) -> Vec<(String, bool)> {
+        self.names.iter().cloned().zip(self.enabled.iter().copied()).collect()
+    }
+}
+
+/// Installed packs and whether they are enabled
+fn pack_states() -> Vec<(String, bool)> {
+    crate::packs::list().into_iter().map(|pack| (pack.name, pack.enabled)).collect()
+}
+
+/// Fill the submenu for enabling and removing installed packs
+fn fill_packs_submenu(submenu: &Submenu) -> PackEntries {
+    clear(submenu);
+    let mut entries = PackEntries {
+        names: Vec::new(),
+        enabled: Vec::new(),
+        toggle_ids: Vec::new(),
+        remove_ids: Vec::new(),
+        folder_id: MenuId::new(""),
+    };
+
+    for pack in crate::packs::list() {
+        let pack_menu = Submenu::new(&pack.name, true);
+        let toggle = CheckMenuItem::new(t("menu.pack_enabled"), true, pack.enabled, None);
+        let remove = MenuItem::new(t("menu.pack_remove"), true, None);
+        pack_menu.append(&toggle).unwrap();
+        pack_menu.append(&remove).unwrap();
+        submenu.append(&pack_menu).unwrap();
+
+        entries.names.push(pack.name);
+        entries.enabled.push(pack.enabled);
+        entries.toggle_ids.push(toggle.id().clone());
+        entries.remove_ids.push(remove.id().clone());
+    }
+
+    if entries.names.is_empty() {
+        submenu.append(&MenuItem::new(t("menu.no_packs"), false, None)).unwrap();
+    }
+    let folder = MenuItem::new(t("menu.open_packs_folder"), true, None);
+    entries.folder_id = folder.id().clone();
+    submenu.append(&folder).unwrap();
+
+    entries
+}
+
+/// Open the web interface in the default browser
+fn open_web_interface() {
+    let url = crate::network::local_url();
+    info!("Opening web interface: {}", url);
+    if let Err(e) = open::that(&url) {
+        error!("Failed to open web interface: {}", e);
+    }
+}
+
+/// Copy the access URL to the clipboard so it can be pasted into a chat
+fn copy_url() {
+    let Some(url) = crate::network::access_url() else {
+        error!("Cannot copy URL: LAN address unknown");
+        return;
+    };
+
+    let result = arboard::Clipboard::new().and_then(|mut clipboard| clipboard.set_text(url.clone()));
+    match result {
+        Ok(()) => info!("Copied {} to the clipboard", url),
+        Err(e) => error!("Failed to copy URL: {}", e),
     }
 }
@@ -0,0 +1,123 @@
+use axum::{
+    http::{header, HeaderMap},
+    response::IntoResponse,
+};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+// Upper bounds (seconds) of the command duration histogram buckets
+const DURATION_BUCKETS: [f64; 9] = [0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 1.0];
+// Distinct command labels kept before the rest are counted as "other"
+const MAX_COMMAND_LABELS: usize = 64;
+
+static METRICS: OnceLock<Mutex<Metrics>> = OnceLock::new();
+
+#[derive(Default)]
+struct CommandStats {
+    handled: u64,
+    failed: u64,
+}
+
+/// Counters since the process started
+#[derive(Default)]
+struct Metrics {
+    connections: u64,
+    rejected_connections: u64,
+    auth_failures: u64,
+    commands: BTreeMap<String, CommandStats>,
+    // Cumulative count per bucket, then the count and sum of all observations
+    duration_buckets: [u64; DURATION_BUCKETS.len()],
+    duration_count: u64,
+    duration_sum: f64,
+}
+
+fn metrics() -> &'static Mutex<Metrics> {
+    METRICS.get_or_init(|| Mutex::new(Metrics::default()))
+}
+
+/// Count an accepted WebSocket connection
+pub fn connection_opened() {
+    metrics().lock().unwrap().connections += 1;
+}
+
+/// Count a connection turned away (e.g. connection limit reached)
+pub fn connection_rejected() {
+    metrics().lock().unwrap().rejected_connections += 1;
+}
+
+/// Count a request with invalid credentials or API token
+pub fn auth_failed() {
+    metrics().lock().unwrap().auth_failures += 1;
+}
+
+/// Count a handled command and how long it took
+pub fn record_command(cmd: &str, ok: bool, duration: Duration) {
+    let mut metrics = metrics().lock().unwrap();
+
+    // Keep unknown commands from growing the label set without bounds
+    let known = metrics.commands.contains_key(cmd) || metrics.commands.len() < MAX_COMMAND_LABELS;
+    let label = if known && is_plain_label(cmd) { cmd } else { "other" };
+    let stats = metrics.commands.entry(label.to_string()).or_default();
+    stats.handled += 1;
+    if !ok {
+        stats.failed += 1;
+    }
+
+    let seconds = duration.as_secs_f64();
+    for (bucket, bound) in metrics.duration_buckets.iter_mut().zip(DURATION_BUCKETS) {
+        if seconds <= bound {
+            *bucket += 1;
+        }
+    }
+    metrics.duration_count += 1;
+    metrics.duration_sum += seconds;
+}
+
+fn is_plain_label(cmd: &str) -> bool {
+    !cmd.is_empty() && cmd.len() <= 32 && cmd.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Render all metrics in the Prometheus text format
+fn render() -> String {
+    let metrics = metrics().lock().unwrap();
+    let mut out = String::new();
+
+    let mut single = |name: &str, kind: &str, help: &str, value: String| {
+        let _ = writeln!(out, "# HELP {} {}\n# TYPE {} {}\n{} {}", name, help, name, kind, name, value);
+    };
+    single("touchrelay_uptime_seconds", "gauge", "Seconds since TouchRelay started.", crate::health::uptime_secs().to_string());
+    single("touchrelay_paused", "gauge", "1 while remote input is paused.", u8::from(crate::pause::is_paused()).to_string());
+    single("touchrelay_clients", "gauge", "Connected WebSocket clients.", crate::clients::count().to_string());
+    single("touchrelay_connections_total", "counter", "Accepted WebSocket connections.", metrics.connections.to_string());
+    single("touchrelay_connections_rejected_total", "counter", "WebSocket connections turned away.", metrics.rejected_connections.to_string());
+    single("touchrelay_auth_failures_total", "counter", "Requests with invalid credentials or API token.", metrics.auth_failures.to_string());
+
+    let _ = writeln!(out, "# HELP touchrelay_commands_total Commands handled, by type.\n# TYPE touchrelay_commands_total counter");
+    for (cmd, stats) in &metrics.commands {
+        let _ = writeln!(out, "touchrelay_commands_total{{command=\"{}\"}} {}", cmd, stats.handled);
+    }
+    let _ = writeln!(out, "# HELP touchrelay_command_errors_total Commands that failed (injection errors, denied or invalid), by type.\n# TYPE touchrelay_command_errors_total counter");
+    for (cmd, stats) in &metrics.commands {
+        let _ = writeln!(out, "touchrelay_command_errors_total{{command=\"{}\"}} {}", cmd, stats.failed);
+    }
+
+    let _ = writeln!(out, "# HELP touchrelay_command_duration_seconds Time to handle a command, including pacing of backlogged moves.\n# TYPE touchrelay_command_duration_seconds histogram");
+    for (count, bound) in metrics.duration_buckets.iter().zip(DURATION_BUCKETS) {
+        let _ = writeln!(out, "touchrelay_command_duration_seconds_bucket{{le=\"{}\"}} {}", bound, count);
+    }
+    let _ = writeln!(out, "touchrelay_command_duration_seconds_bucket{{le=\"+Inf\"}} {}", metrics.duration_count);
+    let _ = writeln!(out, "touchrelay_command_duration_seconds_sum {}", metrics.duration_sum);
+    let _ = writeln!(out, "touchrelay_command_duration_seconds_count {}", metrics.duration_count);
+    out
+}
+
+/// GET /metrics (requires the API token, like `/api`)
+pub async fn metrics_handler(headers: HeaderMap) -> impl IntoResponse {
+    if let Err(rejection) = crate::api::authorize(&headers) {
+        return rejection.into_response();
+    }
+
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], render()).into_response()
+}
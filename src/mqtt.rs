@@ -0,0 +1,162 @@
+use rumqttc::{AsyncClient, Event, LastWill, MqttOptions, Outgoing, Packet, QoS};
+use serde_json::json;
+use std::time::Duration;
+use tracing::{info, warn};
+
+// How often the published state is compared with the current one
+const STATE_INTERVAL: Duration = Duration::from_secs(2);
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+const KEEP_ALIVE: Duration = Duration::from_secs(30);
+// Time allowed to publish "offline" when the server stops
+const DISCONNECT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// State published as retained messages below the topic prefix
+#[derive(PartialEq)]
+struct State {
+    clients: usize,
+    paused: bool,
+}
+
+impl State {
+    fn current() -> Self {
+        Self {
+            clients: crate::clients::count(),
+            paused: crate::pause::is_paused(),
+        }
+    }
+}
+
+/// Topic prefix, `touchrelay/<computer name>` unless configured
+pub fn topic_prefix() -> String {
+    let config = crate::config::get();
+    match config.mqtt.as_ref().and_then(|mqtt| mqtt.topic_prefix.clone()) {
+        Some(prefix) => prefix.trim_end_matches('/').to_string(),
+        None => format!("touchrelay/{}", computer_name().to_lowercase()),
+    }
+}
+
+fn computer_name() -> String {
+    std::env::var("COMPUTERNAME").unwrap_or_else(|_| "pc".to_string())
+}
+
+fn publish_state(client: &AsyncClient, prefix: &str, state: &State) {
+    let messages = [
+        (format!("{}/clients", prefix), state.clients.to_string()),
+        (format!("{}/paused", prefix), state.paused.to_string()),
+    ];
+    for (topic, payload) in messages {
+        if let Err(e) = client.try_publish(topic, QoS::AtLeastOnce, true, payload) {
+            warn!("Failed to publish MQTT state: {}", e);
+        }
+    }
+}
+
+/// Run a command in the WebSocket format without holding up the MQTT event loop
+fn spawn_command(text: String) {
+    info!("Running MQTT command {}", text);
+    tokio::spawn(async move {
+        match crate::handler::run_once(&text, None).await {
+            Ok(None) => {}
+            Ok(Some(reason)) => info!("MQTT command {} dropped: {:?}", text, reason),
+            Err(e) => warn!("MQTT command {} failed: {}", text, e),
+        }
+    });
+}
+
+/// Run the MQTT bridge configured in `[mqtt]` until the server stops: publish
+/// `<prefix>/status` (online/offline), `<prefix>/clients` and `<prefix>/paused`, run
+/// commands in the WebSocket format received on `<prefix>/command`, press the key named
+/// on `<prefix>/key` and pause or resume input from `<prefix>/paused/set`
+pub async fn run() {
+    let config = crate::config::get();
+    let Some(settings) = config.mqtt.clone() else {
+        return;
+    };
+
+    let prefix = topic_prefix();
+    let status_topic = format!("{}/status", prefix);
+    let command_topic = format!("{}/command", prefix);
+    let key_topic = format!("{}/key", prefix);
+    let pause_topic = format!("{}/paused/set", prefix);
+
+    let client_id = settings.client_id.clone().unwrap_or_else(|| format!("touchrelay-{}", computer_name().to_lowercase()));
+    let mut options = MqttOptions::new(client_id, &settings.host, settings.port);
+    options.set_keep_alive(KEEP_ALIVE);
+    options.set_last_will(LastWill::new(&status_topic, "offline", QoS::AtLeastOnce, true));
+    if let Some(username) = &settings.username {
+        options.set_credentials(username, settings.password.as_deref().unwrap_or_default());
+    }
+
+    // Room for the discovery configs and state published right after connecting
+    let (client, mut eventloop) = AsyncClient::new(options, 64);
+    let mut stopping = Box::pin(crate::shutdown::requested());
+    let mut state_check = tokio::time::interval(STATE_INTERVAL);
+    let mut published: Option<State> = None;
+    info!("Connecting to MQTT broker {}:{} (topics {}/...)", settings.host, settings.port, prefix);
+
+    loop {
+        tokio::select! {
+            event = eventloop.poll() => match event {
+                Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                    info!("Connected to MQTT broker {}:{}", settings.host, settings.port);
+                    // Sessions are not persistent: subscribe and publish everything again
+                    for topic in [&command_topic, &key_topic, &pause_topic] {
+                        if let Err(e) = client.try_subscribe(topic, QoS::AtLeastOnce) {
+                            warn!("Failed to subscribe to {}: {}", topic, e);
+                        }
+                    }
+                    if let Err(e) = client.try_publish(&status_topic, QoS::AtLeastOnce, true, "online") {
+                        warn!("Failed to publish MQTT status: {}", e);
+                    }
+                    let state = State::current();
+                    publish_state(&client, &prefix, &state);
+                    published = Some(state);
+                    crate::homeassistant::announce(&client, &prefix);
+                }
+                Ok(Event::Incoming(Packet::Publish(message))) => {
+                    let payload = String::from_utf8_lossy(&message.payload).trim().to_string();
+                    if message.topic == command_topic {
+                        spawn_command(payload);
+                    } else if message.topic == key_topic {
+                        spawn_command(json!(["k", payload]).to_string());
+                    } else if message.topic == pause_topic {
+                        crate::pause::set_paused(payload.eq_ignore_ascii_case("true") || payload.eq_ignore_ascii_case("on"));
+                        crate::events::send(crate::events::AppEvent::StateChanged);
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    warn!("MQTT connection to {}:{} failed: {}", settings.host, settings.port, e);
+                    published = None;
+                    tokio::select! {
+                        _ = tokio::time::sleep(RECONNECT_DELAY) => {}
+                        _ = &mut stopping => return,
+                    }
+                }
+            },
+
+            _ = state_check.tick(), if published.is_some() => {
+                let state = State::current();
+                if published.as_ref() != Some(&state) {
+                    publish_state(&client, &prefix, &state);
+                    published = Some(state);
+                }
+            }
+
+            _ = &mut stopping => break,
+        }
+    }
+
+    // Say goodbye properly instead of leaving it to the last will
+    let _ = client.try_publish(&status_topic, QoS::AtLeastOnce, true, "offline");
+    let _ = client.try_disconnect();
+    let _ = tokio::time::timeout(DISCONNECT_TIMEOUT, async {
+        loop {
+            match eventloop.poll().await {
+                Ok(Event::Outgoing(Outgoing::Disconnect)) | Err(_) => break,
+                Ok(_) => {}
+            }
+        }
+    })
+    .await;
+}
@@ -0,0 +1,189 @@
+use local_ip_address::{list_afinet_netifas, local_ip, local_ipv6};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::atomic::{AtomicU16, Ordering};
+use tokio::net::TcpListener;
+use tracing::{error, warn};
+
+// Further ports tried when the configured one is taken
+const PORT_FALLBACK_ATTEMPTS: u16 = 10;
+
+// Port the server actually bound to (0 until it is listening)
+static BOUND_PORT: AtomicU16 = AtomicU16::new(0);
+
+/// Get the configured port (`--port`, then `TOUCHRELAY_PORT` override the config file)
+pub fn configured_port() -> u16 {
+    if let Some(port) = crate::cli::get().port {
+        return port;
+    }
+    if let Ok(value) = std::env::var("TOUCHRELAY_PORT") {
+        match value.parse() {
+            Ok(port) => return port,
+            Err(_) => warn!("Ignoring invalid TOUCHRELAY_PORT: {}", value),
+        }
+    }
+    crate::config::get().port
+}
+
+/// Get the port the web server listens on (the configured one until it is bound)
+pub fn port() -> u16 {
+    match BOUND_PORT.load(Ordering::Relaxed) {
+        0 => configured_port(),
+        port => port,
+    }
+}
+
+/// Check whether the server must only listen on the loopback interface
+pub fn is_loopback_only() -> bool {
+    crate::config::get().loopback_only || crate::policy::get().disable_external_binding
+}
+
+/// Resolve `--bind` or the configured `bind` setting to an address (None = all interfaces)
+pub fn bound_ip() -> Option<IpAddr> {
+    let bind = crate::cli::get().bind.clone().or_else(|| crate::config::get().bind.clone())?;
+    if let Ok(ip) = bind.parse() {
+        return Some(ip);
+    }
+
+    // Otherwise treat it as an adapter name and use its IPv4 address (or a routable IPv6 one)
+    let interfaces = match list_afinet_netifas() {
+        Ok(interfaces) => interfaces,
+        Err(e) => {
+            error!("Failed to list network interfaces: {}", e);
+            Vec::new()
+        }
+    };
+    let addresses: Vec<IpAddr> = interfaces
+        .into_iter()
+        .filter(|(name, _)| name.eq_ignore_ascii_case(&bind))
+        .map(|(_, ip)| ip)
+        .collect();
+    let found = addresses
+        .iter()
+        .find(|ip| ip.is_ipv4())
+        .or_else(|| addresses.iter().find(|ip| !is_link_local(ip)))
+        .copied();
+
+    // Never silently fall back to all interfaces if a specific one was requested
+    if found.is_none() {
+        error!("Network interface '{}' not found, listening on loopback only", bind);
+    }
+    Some(found.unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST)))
+}
+
+/// Check for an IPv6 link-local address (fe80::/10), which needs a zone ID to be usable in URLs
+fn is_link_local(ip: &IpAddr) -> bool {
+    matches!(ip, IpAddr::V6(v6) if v6.segments()[0] & 0xffc0 == 0xfe80)
+}
+
+/// Get the addresses the web server binds to for a given port
+/// (both IPv4 and IPv6 unless a specific address is configured)
+fn bind_addrs(port: u16) -> Vec<SocketAddr> {
+    let ips = if is_loopback_only() {
+        vec![IpAddr::V4(Ipv4Addr::LOCALHOST), IpAddr::V6(Ipv6Addr::LOCALHOST)]
+    } else {
+        match bound_ip() {
+            Some(ip) => vec![ip],
+            None => vec![IpAddr::V4(Ipv4Addr::UNSPECIFIED), IpAddr::V6(Ipv6Addr::UNSPECIFIED)],
+        }
+    };
+    ips.into_iter().map(|ip| SocketAddr::new(ip, port)).collect()
+}
+
+/// Bind the web server, moving on to the next ports if the configured one is taken
+pub async fn bind_listeners() -> Result<Vec<TcpListener>, String> {
+    let first = configured_port();
+    let last = first.saturating_add(PORT_FALLBACK_ATTEMPTS);
+    let mut last_error = None;
+
+    for port in first..=last {
+        let mut addrs = bind_addrs(port).into_iter();
+        let Some(primary) = addrs.next() else {
+            break;
+        };
+
+        match TcpListener::bind(primary).await {
+            Ok(listener) => {
+                let mut listeners = vec![listener];
+                // The IPv6 side is optional, e.g. when IPv6 is disabled on this PC
+                for addr in addrs {
+                    match TcpListener::bind(addr).await {
+                        Ok(listener) => listeners.push(listener),
+                        Err(e) => warn!("Cannot listen on {}: {}", addr, e),
+                    }
+                }
+                BOUND_PORT.store(port, Ordering::Relaxed);
+                return Ok(listeners);
+            }
+            Err(e) => {
+                warn!("Cannot listen on {}: {}", primary, e);
+                last_error = Some(e);
+            }
+        }
+    }
+
+    Err(match last_error {
+        Some(e) => format!("No free port between {} and {} ({})", first, last, e),
+        None => format!("No free port between {} and {}", first, last),
+    })
+}
+
+/// List this PC's usable LAN addresses with their adapter names, IPv4 first
+pub fn lan_addresses() -> Vec<(String, IpAddr)> {
+    let mut addresses: Vec<(String, IpAddr)> = match list_afinet_netifas() {
+        Ok(interfaces) => interfaces
+            .into_iter()
+            .filter(|(_, ip)| !ip.is_loopback() && !is_link_local(ip))
+            .collect(),
+        Err(e) => {
+            error!("Failed to list network interfaces: {}", e);
+            Vec::new()
+        }
+    };
+    addresses.sort_by_key(|(_, ip)| ip.is_ipv6());
+    addresses
+}
+
+/// Address picked in the tray's address menu, if it still exists
+fn advertised_ip() -> Option<IpAddr> {
+    let ip = crate::config::get().advertise_ip?;
+    lan_addresses().iter().any(|(_, address)| *address == ip).then_some(ip)
+}
+
+/// Get the URL other devices should use to reach the web interface
+/// (None if the LAN address could not be detected)
+pub fn access_url() -> Option<String> {
+    if let Some(url) = &crate::config::get().public_url {
+        return Some(url.clone());
+    }
+
+    let ip = if is_loopback_only() {
+        IpAddr::V4(Ipv4Addr::LOCALHOST)
+    } else {
+        match bound_ip() {
+            Some(ip) if !ip.is_unspecified() => ip,
+            _ => match advertised_ip() {
+                Some(ip) => ip,
+                // Fall back to IPv6 on machines without IPv4 connectivity
+                None => local_ip().or_else(|_| local_ipv6()).ok()?,
+            },
+        }
+    };
+    Some(url_for(ip))
+}
+
+/// Get the URL of the web interface at one of this PC's addresses
+pub fn url_for(ip: IpAddr) -> String {
+    // SocketAddr puts IPv6 addresses in brackets
+    format!("http://{}{}/", SocketAddr::new(ip, port()), crate::proxy::base_path())
+}
+
+/// Get the URL for opening the web interface on this PC
+#[cfg(feature = "gui")]
+pub fn local_url() -> String {
+    access_url().unwrap_or_else(|| format!("http://127.0.0.1:{}{}/", port(), crate::proxy::base_path()))
+}
+
+/// Get a placeholder URL for log output and tooltips when the LAN address is unknown
+pub fn placeholder_url() -> String {
+    format!("http://<PC_IP>:{}{}/", port(), crate::proxy::base_path())
+}
@@ -0,0 +1,32 @@
+use tauri_winrt_notification::Toast;
+use tracing::warn;
+
+/// Show a Windows toast notification (failures are only logged)
+pub fn show(title: &str, message: &str) {
+    let result = Toast::new(Toast::POWERSHELL_APP_ID)
+        .title(title)
+        .text1(message)
+        .show();
+    if let Err(e) = result {
+        warn!("Failed to show notification: {}", e);
+    }
+}
+
+/// Show a notification that opens `url` when it or its button is clicked
+pub fn show_with_link(title: &str, message: &str, button: &str, url: &str) {
+    let url = url.to_string();
+    let result = Toast::new(Toast::POWERSHELL_APP_ID)
+        .title(title)
+        .text1(message)
+        .add_button(button, "open")
+        .on_activated(move |_| {
+            if let Err(e) = open::that(&url) {
+                warn!("Failed to open {}: {}", url, e);
+            }
+            Ok(())
+        })
+        .show();
+    if let Err(e) = result {
+        warn!("Failed to show notification: {}", e);
+    }
+}
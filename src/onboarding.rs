@@ -0,0 +1,49 @@
+use tracing::{info, warn};
+
+use crate::i18n::{t, t_with};
+
+/// Help a first-time user connect their phone: open the touchpad on this PC and
+/// check that Windows Firewall lets the phone in, which is what most often goes wrong
+pub fn run() {
+    std::thread::spawn(|| {
+        let url = crate::network::local_url();
+        info!("First run, opening {}", url);
+        if let Err(e) = open::that(&url) {
+            warn!("Failed to open the web interface: {}", e);
+        }
+
+        if crate::network::is_loopback_only() {
+            return;
+        }
+        let port = crate::network::port();
+        match crate::firewall::is_port_allowed(port) {
+            Ok(true) => info!("Windows Firewall allows port {}", port),
+            Ok(false) => {
+                warn!("Windows Firewall is likely to block phones from reaching port {}", port);
+                let prompt = t_with("onboarding.firewall_blocked", &[("port", &port.to_string())]);
+                if !crate::dialog::confirm(&t("onboarding.title"), &prompt) {
+                    return;
+                }
+                // Fall back to the manual way if adding the rule fails or is declined
+                match crate::firewall::set_rule_elevated(true, port) {
+                    Ok(()) => crate::events::send(crate::events::AppEvent::StateChanged),
+                    Err(e) => {
+                        warn!("{}", e);
+                        open_firewall_settings();
+                    }
+                }
+            }
+            Err(e) => warn!("{}", e),
+        }
+    });
+}
+
+/// Open the "Allow an app through Windows Firewall" page of the Control Panel
+fn open_firewall_settings() {
+    let result = std::process::Command::new("control.exe")
+        .args(["/name", "Microsoft.WindowsFirewall", "/page", "pageConfigureApps"])
+        .spawn();
+    if let Err(e) = result {
+        warn!("Failed to open the firewall settings: {}", e);
+    }
+}
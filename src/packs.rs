@@ -0,0 +1,302 @@
+use axum::{
+    extract::Path,
+    http::{header, StatusCode},
+    response::IntoResponse,
+};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tracing::info;
+
+// Largest pack accepted for import
+const MAX_PACK_SIZE: u64 = 4 * 1024 * 1024;
+const DOWNLOAD_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+const PACK_FILE: &str = "pack.json";
+const ICON_TYPES: [(&str, &str); 3] = [("png", "image/png"), ("svg", "image/svg+xml"), ("ico", "image/x-icon")];
+
+/// A community pack of named actions, layouts and icons
+#[derive(Deserialize)]
+struct Pack {
+    name: String,
+    #[serde(default)]
+    version: Option<String>,
+    #[serde(default)]
+    description: Option<String>,
+    /// Named command arrays that layout controls can refer to with `"action"`
+    #[serde(default)]
+    actions: HashMap<String, Value>,
+    #[serde(default)]
+    layouts: HashMap<String, Value>,
+    /// Icon files (base64) that layout controls can refer to with `"icon"`
+    #[serde(default)]
+    icons: HashMap<String, String>,
+}
+
+/// Summary of an installed pack
+#[derive(Serialize)]
+pub struct PackInfo {
+    pub name: String,
+    pub version: Option<String>,
+    pub description: Option<String>,
+    pub layouts: Vec<String>,
+    pub enabled: bool,
+}
+
+/// Where a pack should be imported from
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PackSource {
+    Path(PathBuf),
+    Url(String),
+}
+
+/// Directory holding one folder per installed pack
+pub fn packs_dir() -> PathBuf {
+    crate::config::config_dir().join("packs")
+}
+
+/// Accept only plain names for packs, layouts and actions
+fn check_name(kind: &str, name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && name.len() <= 64
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("Invalid {} name: {}", kind, name))
+    }
+}
+
+/// Content type of an allowed icon file name
+fn icon_type(file: &str) -> Option<&'static str> {
+    let (stem, extension) = file.rsplit_once('.')?;
+    check_name("icon", stem).ok()?;
+    ICON_TYPES
+        .iter()
+        .find(|(ext, _)| ext.eq_ignore_ascii_case(extension))
+        .map(|(_, content_type)| *content_type)
+}
+
+/// Hex SHA-256 of the pack file
+fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Replace `"action"` references in a layout's controls with the pack's command arrays
+fn resolve_actions(layout: &mut Value, actions: &HashMap<String, Value>) -> Result<(), String> {
+    let Some(controls) = layout.get_mut("controls").and_then(Value::as_array_mut) else {
+        return Ok(());
+    };
+
+    for control in controls {
+        let Some(action) = control.get("action").and_then(Value::as_str) else {
+            continue;
+        };
+        let command = actions
+            .get(action)
+            .ok_or_else(|| format!("Unknown action: {}", action))?
+            .clone();
+        control["command"] = command;
+    }
+    Ok(())
+}
+
+fn read_pack(name: &str) -> Result<Pack, String> {
+    check_name("pack", name)?;
+    let path = packs_dir().join(name).join(PACK_FILE);
+    let text = std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    serde_json::from_str(&text).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))
+}
+
+fn is_enabled(name: &str) -> bool {
+    !crate::config::get().disabled_packs.iter().any(|disabled| disabled == name)
+}
+
+/// List installed packs
+pub fn list() -> Vec<PackInfo> {
+    let Ok(entries) = std::fs::read_dir(packs_dir()) else {
+        return Vec::new();
+    };
+
+    let mut packs: Vec<PackInfo> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| read_pack(entry.file_name().to_str()?).ok())
+        .map(|pack| {
+            let mut layouts: Vec<String> = pack.layouts.into_keys().collect();
+            layouts.sort();
+            PackInfo {
+                enabled: is_enabled(&pack.name),
+                name: pack.name,
+                version: pack.version,
+                description: pack.description,
+                layouts,
+            }
+        })
+        .collect();
+    packs.sort_by(|a, b| a.name.cmp(&b.name));
+    packs
+}
+
+/// Names of the layouts provided by enabled packs, as `<pack>.<layout>`
+pub fn layout_names() -> Vec<String> {
+    list()
+        .into_iter()
+        .filter(|pack| pack.enabled)
+        .flat_map(|pack| {
+            let name = pack.name;
+            pack.layouts.into_iter().map(move |layout| format!("{}.{}", name, layout))
+        })
+        .collect()
+}
+
+/// Load a layout from an enabled pack with its actions resolved (Ok(None) if missing)
+pub fn layout(pack: &str, layout: &str) -> Result<Option<Value>, String> {
+    check_name("pack", pack)?;
+    check_name("layout", layout)?;
+    if !is_enabled(pack) || !packs_dir().join(pack).exists() {
+        return Ok(None);
+    }
+
+    let mut definition = read_pack(pack)?;
+    let Some(mut found) = definition.layouts.remove(layout) else {
+        return Ok(None);
+    };
+    resolve_actions(&mut found, &definition.actions)?;
+    // Lets the web client build icon URLs
+    found["pack"] = Value::from(pack);
+    Ok(Some(found))
+}
+
+/// Fetch pack bytes from a local file or an http(s) URL
+fn fetch(source: &PackSource) -> Result<Vec<u8>, String> {
+    match source {
+        PackSource::Path(path) => {
+            let size = std::fs::metadata(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?.len();
+            if size > MAX_PACK_SIZE {
+                return Err(format!("Pack is too large ({} bytes)", size));
+            }
+            std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+        }
+        PackSource::Url(url) => {
+            if !url.starts_with("https://") && !url.starts_with("http://") {
+                return Err(format!("Unsupported URL: {}", url));
+            }
+            let mut response = crate::http::agent(DOWNLOAD_TIMEOUT)
+                .get(url).call().map_err(|e| format!("Failed to download {}: {}", url, e))?;
+            response
+                .body_mut()
+                .with_config()
+                .limit(MAX_PACK_SIZE)
+                .read_to_vec()
+                .map_err(|e| format!("Failed to download {}: {}", url, e))
+        }
+    }
+}
+
+/// Import a pack after checking it against the expected SHA-256 checksum.
+/// Blocks while downloading, so call it from a blocking task.
+pub fn import(source: &PackSource, sha256: &str) -> Result<PackInfo, String> {
+    let data = fetch(source)?;
+    let actual = sha256_hex(&data);
+    if !actual.eq_ignore_ascii_case(sha256.trim()) {
+        return Err(format!("Checksum mismatch (pack is {})", actual));
+    }
+
+    let pack: Pack = serde_json::from_slice(&data).map_err(|e| format!("Invalid pack: {}", e))?;
+    check_name("pack", &pack.name)?;
+    for name in pack.actions.keys() {
+        check_name("action", name)?;
+    }
+    for (name, layout) in &pack.layouts {
+        check_name("layout", name)?;
+        let mut resolved = layout.clone();
+        resolve_actions(&mut resolved, &pack.actions)?;
+        crate::layouts::validate(&resolved).map_err(|e| format!("Layout {}: {}", name, e))?;
+    }
+    let mut icons = Vec::new();
+    for (file, encoded) in &pack.icons {
+        if icon_type(file).is_none() {
+            return Err(format!("Invalid icon file: {}", file));
+        }
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(encoded)
+            .map_err(|e| format!("Invalid icon {}: {}", file, e))?;
+        icons.push((file, bytes));
+    }
+
+    // Replace any previous version of the pack
+    let dir = packs_dir().join(&pack.name);
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir).map_err(|e| format!("Failed to replace {}: {}", dir.display(), e))?;
+    }
+    std::fs::create_dir_all(dir.join("icons")).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    std::fs::write(dir.join(PACK_FILE), &data).map_err(|e| format!("Failed to write pack: {}", e))?;
+    for (file, bytes) in icons {
+        std::fs::write(dir.join("icons").join(file), bytes).map_err(|e| format!("Failed to write icon {}: {}", file, e))?;
+    }
+
+    info!("Imported pack {} ({} layouts)", pack.name, pack.layouts.len());
+    list()
+        .into_iter()
+        .find(|info| info.name == pack.name)
+        .ok_or_else(|| "Pack disappeared after import".to_string())
+}
+
+/// Enable or disable an installed pack
+pub fn set_enabled(name: &str, enabled: bool) -> Result<(), String> {
+    check_name("pack", name)?;
+    if !packs_dir().join(name).exists() {
+        return Err(format!("Pack {} is not installed", name));
+    }
+
+    crate::config::update(|config| {
+        config.disabled_packs.retain(|disabled| disabled != name);
+        if !enabled {
+            config.disabled_packs.push(name.to_string());
+        }
+    })?;
+    info!("Pack {} {}", name, if enabled { "enabled" } else { "disabled" });
+    Ok(())
+}
+
+/// Remove an installed pack, returning whether it existed
+pub fn remove(name: &str) -> Result<bool, String> {
+    check_name("pack", name)?;
+    let dir = packs_dir().join(name);
+    if !dir.exists() {
+        return Ok(false);
+    }
+
+    std::fs::remove_dir_all(&dir).map_err(|e| format!("Failed to remove {}: {}", dir.display(), e))?;
+    crate::config::update(|config| config.disabled_packs.retain(|disabled| disabled != name))?;
+    info!("Removed pack {}", name);
+    Ok(true)
+}
+
+/// GET /packs/{pack}/icons/{file}
+pub async fn icon_handler(Path((pack, file)): Path<(String, String)>) -> impl IntoResponse {
+    let Some(content_type) = icon_type(&file) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    if check_name("pack", &pack).is_err() || !is_enabled(&pack) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    match std::fs::read(packs_dir().join(&pack).join("icons").join(&file)) {
+        // Pack SVGs come from third parties and may carry scripts: opened directly they would run
+        // on our origin, next to /ws and the browser's Basic credentials. As images they still work.
+        Ok(bytes) => (
+            [
+                (header::CONTENT_TYPE, content_type),
+                (header::CONTENT_SECURITY_POLICY, "sandbox; default-src 'none'; style-src 'unsafe-inline'"),
+                (header::X_CONTENT_TYPE_OPTIONS, "nosniff"),
+            ],
+            bytes,
+        )
+            .into_response(),
+        Err(_) => StatusCode::NOT_FOUND.into_response(),
+    }
+}
@@ -0,0 +1,56 @@
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::{error, info, warn};
+
+use crate::eventlog::{self, EventId, Level};
+
+// Set while an approval prompt is on screen, so devices cannot stack up dialogs
+static PROMPT_OPEN: AtomicBool = AtomicBool::new(false);
+
+/// Check whether a connecting device must be approved by the host first
+pub fn needs_approval(device_id: Option<&str>) -> bool {
+    let config = crate::config::get();
+    config.require_pairing_approval
+        && !device_id.is_some_and(|id| config.devices.contains_key(id))
+}
+
+/// Ask the host to approve a device that has not been paired before.
+/// Returns true if the device may continue.
+pub async fn request_approval(device_id: Option<&str>, addr: SocketAddr) -> bool {
+    // Devices without an ID can never become trusted
+    let Some(device_id) = device_id else {
+        warn!("Rejected device without ID from {}", addr.ip());
+        return false;
+    };
+
+    // Only one device may wait for approval at a time
+    if PROMPT_OPEN.swap(true, Ordering::SeqCst) {
+        warn!("Rejected device {} from {} while another pairing prompt is open", device_id, addr.ip());
+        return false;
+    }
+
+    info!("Asking host to approve new device {} from {}", device_id, addr.ip());
+    let prompt = crate::i18n::t_with("dialog.pairing_request", &[("device", device_id), ("ip", &addr.ip().to_string())]);
+    let approved = tokio::task::spawn_blocking(move || crate::dialog::confirm("TouchRelay", &prompt))
+        .await
+        .unwrap_or(false);
+    PROMPT_OPEN.store(false, Ordering::SeqCst);
+
+    if !approved {
+        warn!("Host denied pairing for device {} from {}", device_id, addr.ip());
+        let message = format!("Pairing denied for device {} from {}", device_id, addr.ip());
+        eventlog::report(Level::Warning, EventId::AuthFailed, &message);
+        return false;
+    }
+
+    let device_id = device_id.to_string();
+    let result = crate::config::update(|config| {
+        config.device_entry(&device_id);
+    });
+    match result {
+        Ok(()) => info!("Paired device {}", device_id),
+        Err(e) => error!("Failed to save paired device {}: {}", device_id, e),
+    }
+
+    true
+}
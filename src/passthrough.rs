@@ -0,0 +1,94 @@
+use serde_json::json;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::process::{Child, Command};
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+// Keep the bridged program from opening a console window
+const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+/// Connection from one WebSocket session to the configured host-side program.
+/// Each `["x", payload]` message is written as one line; each line the program
+/// writes back is sent to the client as `["x", line]`.
+pub struct Bridge {
+    writer: Box<dyn AsyncWrite + Send + Unpin>,
+    reader: JoinHandle<()>,
+    // Killed when the bridge is dropped
+    _child: Option<Child>,
+}
+
+impl Bridge {
+    /// Connect to the TCP port or start the program from the `[passthrough]` settings
+    pub async fn open(client_id: u64) -> Result<Self, String> {
+        let config = crate::config::get();
+        let settings = config.passthrough.as_ref().ok_or("Passthrough is not configured")?;
+
+        if let Some(addr) = &settings.tcp {
+            let stream = TcpStream::connect(addr)
+                .await
+                .map_err(|e| format!("Failed to connect to {}: {}", addr, e))?;
+            let (read, write) = stream.into_split();
+            info!("Client {} bridged to {}", client_id, addr);
+            return Ok(Self {
+                writer: Box::new(write),
+                reader: spawn_reader(client_id, read),
+                _child: None,
+            });
+        }
+
+        let (program, args) = settings.command.split_first().ok_or("Passthrough needs `tcp` or `command`")?;
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .creation_flags(CREATE_NO_WINDOW)
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| format!("Failed to start {}: {}", program, e))?;
+        let stdin = child.stdin.take().ok_or("Program has no stdin")?;
+        let stdout = child.stdout.take().ok_or("Program has no stdout")?;
+        info!("Client {} bridged to {}", client_id, program);
+
+        Ok(Self {
+            writer: Box::new(stdin),
+            reader: spawn_reader(client_id, stdout),
+            _child: Some(child),
+        })
+    }
+
+    /// Forward one payload as a line
+    pub async fn send(&mut self, payload: &str) -> Result<(), String> {
+        let line = format!("{}\n", payload.replace(['\r', '\n'], " "));
+        self.writer
+            .write_all(line.as_bytes())
+            .await
+            .map_err(|e| format!("Passthrough write failed: {}", e))?;
+        self.writer.flush().await.map_err(|e| format!("Passthrough write failed: {}", e))
+    }
+}
+
+impl Drop for Bridge {
+    fn drop(&mut self) {
+        self.reader.abort();
+    }
+}
+
+/// Relay every line the program writes back to the client
+fn spawn_reader(client_id: u64, output: impl AsyncRead + Send + Unpin + 'static) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(output).lines();
+        loop {
+            match lines.next_line().await {
+                Ok(Some(line)) => crate::clients::send_to(client_id, &json!(["x", line])),
+                Ok(None) => break,
+                Err(e) => {
+                    warn!("Passthrough read failed: {}", e);
+                    break;
+                }
+            }
+        }
+        info!("Passthrough for client {} closed", client_id);
+        crate::clients::send_to(client_id, &json!(["x_closed"]));
+    })
+}
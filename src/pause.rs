@@ -0,0 +1,35 @@
+use serde_json::json;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::{info, warn};
+
+// Whether remote input injection is currently suspended
+static PAUSED: AtomicBool = AtomicBool::new(false);
+
+/// Check if remote input is suspended
+pub fn is_paused() -> bool {
+    PAUSED.load(Ordering::SeqCst)
+}
+
+/// Toggle remote input suspension and return the new state
+#[cfg(feature = "gui")]
+pub fn toggle() -> bool {
+    let paused = !is_paused();
+    set_paused(paused);
+    paused
+}
+
+/// Suspend or resume remote input and notify connected clients
+pub fn set_paused(paused: bool) {
+    if PAUSED.swap(paused, Ordering::SeqCst) == paused {
+        return;
+    }
+
+    if paused {
+        warn!("Remote input suspended");
+        // Keys and the selection button held before the pause would otherwise stay down
+        crate::clients::release_all_input();
+    } else {
+        info!("Remote input resumed");
+    }
+    crate::clients::broadcast(&json!(["paused", paused]));
+}
@@ -0,0 +1,113 @@
+use serde::Deserialize;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use tracing::{info, warn};
+use winreg::enums::*;
+use winreg::RegKey;
+
+use crate::capability::Capability;
+
+const POLICY_KEY: &str = r"SOFTWARE\Policies\DeltaFoundry\TouchRelay";
+const POLICY_FILE: &str = "policy.toml";
+
+static POLICY: OnceLock<Policy> = OnceLock::new();
+
+/// Machine-wide restrictions set by IT, which user configuration cannot override
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Policy {
+    /// Capabilities no device may use
+    pub disabled_capabilities: Vec<Capability>,
+    /// Only listen on the loopback interface
+    pub disable_external_binding: bool,
+}
+
+/// `policy.toml` as written, with capability names checked one at a time
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct PolicyFile {
+    disabled_capabilities: Vec<String>,
+    disable_external_binding: bool,
+}
+
+impl Policy {
+    /// Policy for a file that cannot be read: a lockdown must not vanish over a typo, so
+    /// everything beyond pointer and keyboard is disabled and only this PC can connect
+    fn locked_down() -> Self {
+        Policy {
+            disabled_capabilities: vec![
+                Capability::Audio,
+                Capability::Power,
+                Capability::File,
+                Capability::Passthrough,
+            ],
+            disable_external_binding: true,
+        }
+    }
+
+    /// Load policy from the policy file and HKLM policy keys
+    fn load() -> Self {
+        let mut policy = load_file().unwrap_or_default();
+
+        if let Ok(key) = RegKey::predef(HKEY_LOCAL_MACHINE).open_subkey(POLICY_KEY) {
+            // REG_MULTI_SZ, or a comma separated REG_SZ
+            let names: Vec<String> = key
+                .get_value::<Vec<String>, _>("DisabledCapabilities")
+                .or_else(|_| {
+                    key.get_value::<String, _>("DisabledCapabilities")
+                        .map(|value| value.split(',').map(str::to_string).collect())
+                })
+                .unwrap_or_default();
+
+            policy.disabled_capabilities.extend(names.iter().filter_map(|name| parse_capability(name)));
+
+            if key.get_value::<u32, _>("DisableExternalBinding").unwrap_or(0) != 0 {
+                policy.disable_external_binding = true;
+            }
+        }
+
+        if !policy.disabled_capabilities.is_empty() || policy.disable_external_binding {
+            info!("Managed policy in effect: {:?}", policy);
+        }
+        policy
+    }
+
+    /// Check whether policy permits a capability
+    pub fn allows(&self, capability: Capability) -> bool {
+        !self.disabled_capabilities.contains(&capability)
+    }
+}
+
+/// Capability named in a policy, skipping (and logging) unknown names
+fn parse_capability(name: &str) -> Option<Capability> {
+    let capability = Capability::from_name(name);
+    if capability.is_none() {
+        warn!("Unknown capability in policy: {}", name.trim());
+    }
+    capability
+}
+
+/// Read the machine-wide policy file from `%ProgramData%\TouchRelay\policy.toml`
+fn load_file() -> Option<Policy> {
+    let path = std::env::var_os("ProgramData")
+        .map(PathBuf::from)?
+        .join("TouchRelay")
+        .join(POLICY_FILE);
+    let text = std::fs::read_to_string(&path).ok()?;
+
+    match toml::from_str::<PolicyFile>(&text) {
+        Ok(file) => Some(Policy {
+            disabled_capabilities: file.disabled_capabilities.iter().filter_map(|name| parse_capability(name)).collect(),
+            disable_external_binding: file.disable_external_binding,
+        }),
+        Err(e) => {
+            warn!("Failed to parse {}, locking down instead: {}", path.display(), e);
+            Some(Policy::locked_down())
+        }
+    }
+}
+
+/// Get the machine-wide policy (loaded once at startup)
+pub fn get() -> &'static Policy {
+    POLICY.get_or_init(Policy::load)
+}
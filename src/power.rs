@@ -0,0 +1,44 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::{debug, info};
+use windows::Win32::Foundation::{LPARAM, WPARAM};
+use windows::Win32::UI::Input::KeyboardAndMouse::{SendInput, INPUT, INPUT_0, INPUT_MOUSE, MOUSEEVENTF_MOVE, MOUSEINPUT};
+use windows::Win32::UI::WindowsAndMessaging::{PostMessageW, HWND_BROADCAST, SC_MONITORPOWER, WM_SYSCOMMAND};
+
+// SC_MONITORPOWER argument that turns the displays off
+const MONITOR_OFF: isize = 2;
+
+/// Whether the displays were turned off by a client and not woken since
+static DISPLAYS_OFF: AtomicBool = AtomicBool::new(false);
+
+/// Turn all displays off without sleeping the PC, so music keeps playing
+pub fn displays_off() -> Result<(), String> {
+    // Posted rather than sent, so a hung window cannot hold up the command
+    unsafe { PostMessageW(Some(HWND_BROADCAST), WM_SYSCOMMAND, WPARAM(SC_MONITORPOWER as usize), LPARAM(MONITOR_OFF)) }
+        .map_err(|e| format!("Failed to turn the displays off: {}", e))?;
+    DISPLAYS_OFF.store(true, Ordering::Relaxed);
+    info!("Displays turned off");
+    Ok(())
+}
+
+/// Turn the displays back on if a client turned them off; called for every remote command,
+/// since commands like volume changes do not wake them the way pointer input does
+pub fn wake_displays() {
+    if !DISPLAYS_OFF.swap(false, Ordering::Relaxed) {
+        return;
+    }
+    // SC_MONITORPOWER's "on" is ignored by current Windows, but any mouse input wakes the displays
+    let input = INPUT {
+        r#type: INPUT_MOUSE,
+        Anonymous: INPUT_0 {
+            mi: MOUSEINPUT {
+                dwFlags: MOUSEEVENTF_MOVE,
+                ..Default::default()
+            },
+        },
+    };
+    if unsafe { SendInput(&[input], std::mem::size_of::<INPUT>() as i32) } == 0 {
+        debug!("Failed to wake the displays");
+        return;
+    }
+    info!("Displays woken by remote input");
+}
@@ -0,0 +1,59 @@
+use axum::http::{header, HeaderMap};
+use std::net::{IpAddr, SocketAddr};
+
+/// Path prefix the app is mounted under, normalized to "" or "/prefix"
+pub fn base_path() -> String {
+    let configured = crate::config::get().base_path.trim().trim_matches('/').to_string();
+    if configured.is_empty() {
+        String::new()
+    } else {
+        format!("/{}", configured)
+    }
+}
+
+/// Check whether a peer is a reverse proxy whose forwarding headers can be believed
+fn is_trusted(peer: IpAddr) -> bool {
+    crate::config::get().trusted_proxies.contains(&peer)
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+/// Address of the actual client: the last untrusted hop in `X-Forwarded-For` when the
+/// request comes through a trusted proxy, otherwise the peer itself
+pub fn client_addr(peer: SocketAddr, headers: &HeaderMap) -> SocketAddr {
+    if !is_trusted(peer.ip()) {
+        return peer;
+    }
+
+    // Walk from the right: entries added by our own proxies come last
+    let forwarded = header_str(headers, "x-forwarded-for")
+        .unwrap_or_default()
+        .rsplit(',')
+        .filter_map(|hop| hop.trim().parse::<IpAddr>().ok())
+        .find(|ip| !is_trusted(*ip));
+    match forwarded {
+        Some(ip) => SocketAddr::new(ip, peer.port()),
+        None => peer,
+    }
+}
+
+/// URL of the web interface as seen by a client that reached us through a trusted proxy
+/// (built from `X-Forwarded-Proto` and `X-Forwarded-Host`/`Host`)
+pub fn public_url(peer: SocketAddr, headers: &HeaderMap) -> Option<String> {
+    if !is_trusted(peer.ip()) {
+        return None;
+    }
+
+    let proto = header_str(headers, "x-forwarded-proto")?.split(',').next()?.trim();
+    if proto != "http" && proto != "https" {
+        return None;
+    }
+    let host = header_str(headers, "x-forwarded-host")
+        .or_else(|| header_str(headers, header::HOST.as_str()))?
+        .split(',')
+        .next()?
+        .trim();
+    Some(format!("{}://{}{}/", proto, host, base_path()))
+}
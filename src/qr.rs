@@ -0,0 +1,61 @@
+use axum::{
+    extract::ConnectInfo,
+    http::{header, HeaderMap, StatusCode},
+    response::IntoResponse,
+};
+use image::{ImageFormat, Luma};
+use qrcode::QrCode;
+use std::io::Cursor;
+use std::net::SocketAddr;
+#[cfg(feature = "gui")]
+use tracing::{error, info};
+
+/// Render the access URL as a QR code PNG
+pub fn png() -> Result<Vec<u8>, String> {
+    let url = crate::network::access_url().ok_or("LAN address unknown")?;
+    render(&url)
+}
+
+/// Render a URL as a QR code PNG
+fn render(url: &str) -> Result<Vec<u8>, String> {
+    let code = QrCode::new(url.as_bytes()).map_err(|e| format!("Failed to encode QR code: {}", e))?;
+    let image = code.render::<Luma<u8>>().min_dimensions(256, 256).build();
+
+    let mut png = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .map_err(|e| format!("Failed to write QR code: {}", e))?;
+    Ok(png)
+}
+
+/// Serve /qr.png, encoding the proxy's URL when requested through a trusted reverse proxy
+pub async fn qr_handler(ConnectInfo(peer): ConnectInfo<SocketAddr>, headers: HeaderMap) -> impl IntoResponse {
+    let result = match crate::proxy::public_url(peer, &headers) {
+        Some(url) => render(&url),
+        None => png(),
+    };
+    match result {
+        Ok(png) => (
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, "image/png"), (header::CACHE_CONTROL, "no-store")],
+            png,
+        )
+            .into_response(),
+        Err(e) => (StatusCode::SERVICE_UNAVAILABLE, e).into_response(),
+    }
+}
+
+/// Show the QR code in the default image viewer
+#[cfg(feature = "gui")]
+pub fn show() {
+    let result = png().and_then(|png| {
+        let path = std::env::temp_dir().join("touchrelay-qr.png");
+        std::fs::write(&path, png).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        open::that(&path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))
+    });
+
+    match result {
+        Ok(()) => info!("Showing QR code"),
+        Err(e) => error!("Failed to show QR code: {}", e),
+    }
+}
@@ -0,0 +1,130 @@
+use serde_json::{json, Value};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::{error, info, warn};
+
+use crate::handler::Session;
+
+// Control messages that say nothing about what happened on the PC
+const SKIPPED_COMMANDS: [&str; 3] = ["ping", "takeover", "takeover_reply"];
+
+/// Directory holding one `.jsonl` file per recorded session
+fn recordings_dir() -> PathBuf {
+    crate::config::config_dir().join("recordings")
+}
+
+/// Writes the commands of one session as JSON lines: a header with the device ID,
+/// then `[elapsed_ms, command]` per message
+pub struct Recorder {
+    file: BufWriter<File>,
+    path: PathBuf,
+    started: Instant,
+}
+
+impl Recorder {
+    /// Start recording a session if `record_sessions` is enabled
+    pub fn start(client_id: u64, device_id: Option<&str>) -> Option<Self> {
+        if !crate::config::get().record_sessions {
+            return None;
+        }
+
+        let started_at = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let path = recordings_dir().join(format!("session-{}-{}.jsonl", started_at, client_id));
+        let file = std::fs::create_dir_all(recordings_dir()).and_then(|()| File::create(&path));
+        let mut recorder = match file {
+            Ok(file) => Self { file: BufWriter::new(file), path, started: Instant::now() },
+            Err(e) => {
+                error!("Failed to create recording {}: {}", path.display(), e);
+                return None;
+            }
+        };
+
+        recorder.write(&json!({ "device": device_id, "started": started_at }));
+        info!("Recording session to {}", recorder.path.display());
+        Some(recorder)
+    }
+
+    /// Append a handled message
+    pub fn record(&mut self, text: &str) {
+        let Ok(message) = serde_json::from_str::<Value>(text) else {
+            return;
+        };
+        let cmd = message.get(0).and_then(Value::as_str).unwrap_or_default();
+        if SKIPPED_COMMANDS.contains(&cmd) {
+            return;
+        }
+        let elapsed = self.started.elapsed().as_millis() as u64;
+        self.write(&json!([elapsed, message]));
+    }
+
+    fn write(&mut self, line: &Value) {
+        if let Err(e) = writeln!(self.file, "{}", line) {
+            warn!("Failed to write recording {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        if let Err(e) = self.file.flush() {
+            warn!("Failed to write recording {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// Re-inject a recorded session at its original timing (`speed` > 1 plays faster)
+pub fn replay(path: &Path, speed: f64) -> Result<(), String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let mut lines = BufReader::new(file).lines();
+
+    let header: Value = match lines.next() {
+        Some(Ok(line)) => serde_json::from_str(&line).map_err(|e| format!("Invalid recording header: {}", e))?,
+        _ => return Err(format!("{} is not a session recording", path.display())),
+    };
+    let device_id = header.get("device").and_then(Value::as_str).map(str::to_string);
+
+    let runtime = tokio::runtime::Runtime::new()
+        .map_err(|e| format!("Failed to start runtime: {}", e))?;
+    runtime.block_on(async move {
+        // Replay with the recorded device's permissions and as a regular client,
+        // so pause, policy and exclusive control still apply
+        let backend = crate::backend::desktop()?;
+        let (client_id, _outbound) = crate::clients::register_one_shot(device_id.as_deref())?;
+        let session = Session::new(client_id, device_id.clone(), backend);
+        info!("Replaying {} (device: {}) at {}x", path.display(), device_id.as_deref().unwrap_or("unknown"), speed);
+
+        let started = tokio::time::Instant::now();
+        let mut replayed = 0;
+        for (number, line) in lines.enumerate() {
+            let line = line.map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            let Ok(Value::Array(entry)) = serde_json::from_str::<Value>(&line) else {
+                warn!("Skipping invalid line {}", number + 2);
+                continue;
+            };
+            let (Some(elapsed), Some(message)) = (entry.first().and_then(Value::as_f64), entry.get(1)) else {
+                warn!("Skipping invalid line {}", number + 2);
+                continue;
+            };
+
+            // Keep a deflected joystick moving the cursor until the next command is due
+            let due = started + Duration::from_secs_f64(elapsed / speed / 1000.0);
+            while session.joystick_active() && tokio::time::Instant::now() < due {
+                tokio::time::sleep(crate::handler::JOYSTICK_TICK).await;
+                session.tick_joystick().await;
+            }
+            tokio::time::sleep_until(due).await;
+            if let Err(e) = crate::handler::handle_message(&message.to_string(), &session).await {
+                warn!("Failed to replay {}: {}", message, e);
+            }
+            replayed += 1;
+        }
+
+        session.release_held_keys().await;
+        session.end_select_mode().await?;
+        crate::clients::unregister(client_id);
+        info!("Replayed {} commands", replayed);
+        Ok(())
+    })
+}
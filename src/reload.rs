@@ -0,0 +1,101 @@
+use ::notify::{EventKind, RecursiveMode, Watcher};
+use std::sync::mpsc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::events::{self, AppEvent};
+
+// Editors save in several steps; wait for the file to settle before reading it
+const DEBOUNCE: Duration = Duration::from_millis(500);
+// Settings the server only reads when it starts
+const RESTART_KEYS: [&str; 9] = [
+    "port", "bind", "loopback_only", "base_path", "companion_port", "discovery_port", "mdns", "static_dir", "mqtt",
+];
+
+/// Watch `config.toml` and apply edits while TouchRelay runs
+pub fn watch() {
+    std::thread::spawn(|| {
+        let (sender, receiver) = mpsc::channel();
+        let mut watcher = match ::notify::recommended_watcher(sender) {
+            Ok(watcher) => watcher,
+            Err(e) => {
+                error!("Failed to watch the config file: {}", e);
+                return;
+            }
+        };
+        // Watch the directory: editors often replace the file instead of writing to it
+        let path = crate::config::config_path();
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => std::path::PathBuf::from("."),
+        };
+        let _ = std::fs::create_dir_all(&dir);
+        if let Err(e) = watcher.watch(&dir, RecursiveMode::NonRecursive) {
+            error!("Failed to watch {}: {}", dir.display(), e);
+            return;
+        }
+
+        info!("Watching {} for changes", path.display());
+        while let Ok(event) = receiver.recv() {
+            // Only the config directory is watched, so the file name is enough
+            let touches_config = event.is_ok_and(|event| {
+                !matches!(event.kind, EventKind::Access(_))
+                    && event.paths.iter().any(|changed| changed.file_name() == path.file_name())
+            });
+            if !touches_config {
+                continue;
+            }
+
+            // Swallow the rest of the burst
+            while receiver.recv_timeout(DEBOUNCE).is_ok() {}
+            apply_changes();
+        }
+    });
+}
+
+/// Settings differing between two configs (as JSON) that need a server restart to apply
+pub fn changed_restart_keys(old: &serde_json::Value, new: &serde_json::Value) -> Vec<&'static str> {
+    RESTART_KEYS.into_iter().filter(|key| old.get(key) != new.get(key)).collect()
+}
+
+/// Re-read the config file and apply what can change live, asking to restart the server for the rest
+fn apply_changes() {
+    let old = crate::config::get();
+    if let Err(e) = crate::config::reload() {
+        warn!("Keeping current settings: {}", e);
+        crate::notify::show(&crate::i18n::t("notify.config_not_applied"), &e);
+        return;
+    }
+    let new = crate::config::get();
+
+    let (Ok(old), Ok(new)) = (serde_json::to_value(&*old), serde_json::to_value(&*new)) else {
+        return;
+    };
+    // Our own saves (tray, API, settings page) come back through here unchanged
+    if old == new {
+        return;
+    }
+    info!("Config file changed, applying new settings");
+
+    crate::logging::apply_config();
+    crate::clients::send_profiles();
+    if old.get("startup") != new.get("startup") && crate::startup::is_startup_enabled() {
+        if let Err(e) = crate::startup::set_startup_enabled(true) {
+            warn!("Failed to update startup: {}", e);
+        }
+    }
+    events::send(AppEvent::StateChanged);
+
+    if old.get("kill_switch_hotkey") != new.get("kill_switch_hotkey") {
+        warn!("The new kill-switch hotkey takes effect after TouchRelay is restarted");
+    }
+
+    let changed = changed_restart_keys(&old, &new);
+    if changed.is_empty() {
+        return;
+    }
+    let prompt = crate::i18n::t_with("dialog.restart_needed", &[("settings", &changed.join(", "))]);
+    if crate::dialog::confirm("TouchRelay", &prompt) {
+        events::send(AppEvent::RestartServer);
+    }
+}
@@ -0,0 +1,253 @@
+use enigo::{Axis, Button, Direction, Key};
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::time::MissedTickBehavior;
+use tracing::{error, info, warn};
+
+use crate::backend::InputBackend;
+use crate::handler::{self, Session};
+
+// How often progress is logged during a soak run
+const REPORT_INTERVAL: Duration = Duration::from_secs(10);
+
+const DEFAULT_DURATION_SECS: u64 = 3600;
+const DEFAULT_RATE: u64 = 500;
+
+/// Counters shared between the mock backend and the soak loop
+#[derive(Default)]
+struct Counters {
+    events: AtomicU64,
+    cursor_x: AtomicI64,
+    cursor_y: AtomicI64,
+    // Vertical wheel steps, the only axis the generator scrolls
+    scroll: AtomicI64,
+}
+
+/// Backend that records input instead of injecting it
+struct MockBackend {
+    counters: Arc<Counters>,
+}
+
+impl InputBackend for MockBackend {
+    fn move_mouse(&mut self, dx: i32, dy: i32) -> Result<(), String> {
+        self.counters.events.fetch_add(1, Ordering::Relaxed);
+        self.counters.cursor_x.fetch_add(dx as i64, Ordering::Relaxed);
+        self.counters.cursor_y.fetch_add(dy as i64, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn button(&mut self, _button: Button, _direction: Direction) -> Result<(), String> {
+        self.counters.events.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn scroll(&mut self, amount: i32, axis: Axis) -> Result<(), String> {
+        self.counters.events.fetch_add(1, Ordering::Relaxed);
+        if axis == Axis::Vertical {
+            self.counters.scroll.fetch_add(amount as i64, Ordering::Relaxed);
+        }
+        Ok(())
+    }
+
+    fn text(&mut self, _text: &str) -> Result<(), String> {
+        self.counters.events.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn key(&mut self, _key: Key, _direction: Direction) -> Result<(), String> {
+        self.counters.events.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+/// Deterministic xorshift generator so runs can be reproduced
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn range(&mut self, low: i64, high: i64) -> i64 {
+        low + (self.next() % (high - low + 1) as u64) as i64
+    }
+}
+
+/// Synthetic command stream resembling a real touchpad session
+struct Generator {
+    rng: Rng,
+    expected_x: i64,
+    expected_y: i64,
+    expected_scroll: i64,
+}
+
+impl Generator {
+    fn next_message(&mut self) -> Value {
+        match self.rng.next() % 100 {
+            0..=84 => {
+                let dx = self.rng.range(-20, 20);
+                let dy = self.rng.range(-20, 20);
+                self.expected_x += dx;
+                self.expected_y += dy;
+                json!(["m", dx, dy])
+            }
+            85..=92 => {
+                let dy = self.rng.range(-5, 5);
+                self.expected_scroll += dy;
+                json!(["w", dy])
+            }
+            93..=95 => json!(["b", if self.rng.next() & 1 == 0 { "l" } else { "r" }, 1]),
+            96..=97 => {
+                let key = ["Escape", "PageUp", "PageDown", "Return"][self.rng.next() as usize % 4];
+                json!(["k", key])
+            }
+            98 => json!(["t", "soak"]),
+            _ => json!(["ping"]),
+        }
+    }
+}
+
+/// Current working set of this process in bytes
+fn working_set() -> u64 {
+    use windows::Win32::System::ProcessStatus::{GetProcessMemoryInfo, PROCESS_MEMORY_COUNTERS};
+    use windows::Win32::System::Threading::GetCurrentProcess;
+
+    let mut counters = PROCESS_MEMORY_COUNTERS::default();
+    let size = std::mem::size_of::<PROCESS_MEMORY_COUNTERS>() as u32;
+    match unsafe { GetProcessMemoryInfo(GetCurrentProcess(), &mut counters, size) } {
+        Ok(()) => counters.WorkingSetSize as u64,
+        Err(_) => 0,
+    }
+}
+
+/// Run the hidden `--selftest` soak mode: feed a synthetic command stream through
+/// the message handler into a mock backend and report throughput and memory use
+pub fn run(args: &crate::cli::Args) -> Result<(), String> {
+    // The expected totals assume unscaled input reaching the backend, so ignore the
+    // PC's pointer speed, scroll multipliers and inversion, exclusive control and
+    // a pause left by a broken config
+    crate::config::use_defaults();
+    crate::pause::set_paused(false);
+
+    let duration = Duration::from_secs(args.duration.unwrap_or(DEFAULT_DURATION_SECS));
+    let rate = args.rate.unwrap_or(DEFAULT_RATE);
+    let seed = args.seed.unwrap_or(0x5eed_7011).max(1);
+
+    let runtime = tokio::runtime::Runtime::new()
+        .map_err(|e| format!("Failed to start runtime: {}", e))?;
+    runtime.block_on(soak(duration, rate, seed))
+}
+
+async fn soak(duration: Duration, rate: u64, seed: u64) -> Result<(), String> {
+    info!(
+        "Soak test: {:?} at {} msgs/sec (seed {})",
+        duration,
+        if rate == 0 { "unlimited".to_string() } else { rate.to_string() },
+        seed
+    );
+
+    let counters = Arc::new(Counters::default());
+    // The mock backend works the same over SSH, from a service or while the mouse is in use
+    let session = Session::new(0, None, Box::new(MockBackend { counters: counters.clone() })).without_environment_gates();
+    let mut generator = Generator {
+        rng: Rng(seed),
+        expected_x: 0,
+        expected_y: 0,
+        expected_scroll: 0,
+    };
+
+    // Rate 0 runs unthrottled to measure raw handler throughput
+    let mut pacer = (rate > 0).then(|| {
+        let mut interval = tokio::time::interval(Duration::from_secs_f64(1.0 / rate as f64));
+        interval.set_missed_tick_behavior(MissedTickBehavior::Burst);
+        interval
+    });
+
+    let start = Instant::now();
+    let baseline_memory = working_set();
+    let mut peak_memory = baseline_memory;
+    let mut sent: u64 = 0;
+    let mut errors: u64 = 0;
+    let mut slowest = Duration::ZERO;
+    let mut last_report = start;
+    let mut sent_at_last_report: u64 = 0;
+
+    while start.elapsed() < duration {
+        match pacer.as_mut() {
+            Some(interval) => {
+                interval.tick().await;
+            }
+            // Keep the runtime responsive when unthrottled
+            None if sent & 1023 == 0 => tokio::task::yield_now().await,
+            None => {}
+        }
+
+        let text = generator.next_message().to_string();
+        let handled_at = Instant::now();
+        if let Err(e) = handler::handle_message(&text, &session).await {
+            errors += 1;
+            if errors <= 10 {
+                warn!("Soak message {} failed: {}", text, e);
+            }
+        }
+        slowest = slowest.max(handled_at.elapsed());
+        sent += 1;
+
+        if last_report.elapsed() >= REPORT_INTERVAL {
+            let memory = working_set();
+            peak_memory = peak_memory.max(memory);
+            let throughput = (sent - sent_at_last_report) as f64 / last_report.elapsed().as_secs_f64();
+            info!(
+                "Soak: {}s elapsed, {} messages ({:.0}/s), {} injected events, {} errors, slowest {:?}, working set {} KiB",
+                start.elapsed().as_secs(),
+                sent,
+                throughput,
+                counters.events.load(Ordering::Relaxed),
+                errors,
+                slowest,
+                memory / 1024
+            );
+            last_report = Instant::now();
+            sent_at_last_report = sent;
+        }
+    }
+
+    let final_memory = working_set();
+    peak_memory = peak_memory.max(final_memory);
+    info!(
+        "Soak finished: {} messages in {:?} ({:.0}/s), {} errors, slowest {:?}, working set {} KiB -> {} KiB (peak {} KiB)",
+        sent,
+        start.elapsed(),
+        sent as f64 / start.elapsed().as_secs_f64(),
+        errors,
+        slowest,
+        baseline_memory / 1024,
+        final_memory / 1024,
+        peak_memory / 1024
+    );
+
+    // Every generated movement must reach the backend exactly once, however it is batched
+    let cursor = (counters.cursor_x.load(Ordering::Relaxed), counters.cursor_y.load(Ordering::Relaxed));
+    let scroll = counters.scroll.load(Ordering::Relaxed);
+    if cursor != (generator.expected_x, generator.expected_y) || scroll != generator.expected_scroll {
+        error!(
+            "Soak: backend received cursor {:?} / scroll {}, expected {:?} / {}",
+            cursor,
+            scroll,
+            (generator.expected_x, generator.expected_y),
+            generator.expected_scroll
+        );
+        return Err("Injected input does not match the generated stream".to_string());
+    }
+
+    if errors > 0 {
+        return Err(format!("{} messages failed during the soak test", errors));
+    }
+
+    Ok(())
+}
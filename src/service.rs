@@ -0,0 +1,286 @@
+use std::ffi::OsString;
+use std::sync::mpsc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+use windows::core::{HSTRING, PCWSTR, PWSTR};
+use windows::Win32::Foundation::{CloseHandle, HANDLE};
+use windows::Win32::System::Environment::{CreateEnvironmentBlock, DestroyEnvironmentBlock};
+use windows::Win32::System::RemoteDesktop::{WTSGetActiveConsoleSessionId, WTSQueryUserToken};
+use windows::Win32::System::Threading::{
+    CreateProcessAsUserW, GetExitCodeProcess, TerminateProcess, CREATE_UNICODE_ENVIRONMENT,
+    PROCESS_INFORMATION, STARTUPINFOW,
+};
+use windows_service::service::{
+    ServiceAccess, ServiceControl, ServiceControlAccept, ServiceErrorControl, ServiceExitCode,
+    ServiceInfo, ServiceStartType, ServiceState, ServiceStatus, ServiceType, SessionChangeReason,
+};
+use windows_service::service_control_handler::{self, ServiceControlHandlerResult};
+use windows_service::service_dispatcher;
+use windows_service::service_manager::{ServiceManager, ServiceManagerAccess};
+
+use crate::eventlog::{self, EventId, Level};
+
+const SERVICE_NAME: &str = "TouchRelay";
+const DESCRIPTION: &str = "Starts TouchRelay for the user signed in at the console and restarts it if it crashes.";
+// How often the console session and the running instances are checked
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+// How long uninstalling waits for the service to stop
+const STOP_TIMEOUT: Duration = Duration::from_secs(10);
+// Exit code reported by GetExitCodeProcess while the process runs
+const STILL_ACTIVE: u32 = 259;
+// Returned by WTSGetActiveConsoleSessionId while sessions are being switched
+const NO_CONSOLE_SESSION: u32 = 0xFFFF_FFFF;
+
+windows_service::define_windows_service!(ffi_service_main, service_main);
+
+/// Messages from the service control handler to the supervisor loop
+enum Control {
+    Stop,
+    /// A user signed in to this session
+    Logon(u32),
+    SessionChanged,
+}
+
+/// TouchRelay started by the service in a user's session
+struct Instance {
+    process: HANDLE,
+    session: u32,
+}
+
+impl Instance {
+    /// Exit code once the process has ended
+    fn exit_code(&self) -> Option<u32> {
+        let mut code = 0;
+        match unsafe { GetExitCodeProcess(self.process, &mut code) } {
+            Ok(()) if code == STILL_ACTIVE => None,
+            Ok(()) => Some(code),
+            Err(_) => Some(1),
+        }
+    }
+}
+
+impl Drop for Instance {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = CloseHandle(self.process);
+        }
+    }
+}
+
+/// Run under the service control manager (`--service`, set up by `--install-service`)
+pub fn run() -> Result<(), String> {
+    service_dispatcher::start(SERVICE_NAME, ffi_service_main)
+        .map_err(|e| format!("Not started as a service ({}); use --install-service", e))
+}
+
+fn service_main(_arguments: Vec<OsString>) {
+    if let Err(e) = run_service() {
+        error!("TouchRelay service failed: {}", e);
+        eventlog::report(Level::Error, EventId::ServiceFailed, &format!("TouchRelay service failed: {}", e));
+    }
+}
+
+fn run_service() -> Result<(), windows_service::Error> {
+    let (sender, receiver) = mpsc::channel();
+    let handler = move |control| match control {
+        ServiceControl::Stop | ServiceControl::Shutdown => {
+            let _ = sender.send(Control::Stop);
+            ServiceControlHandlerResult::NoError
+        }
+        ServiceControl::SessionChange(change) => {
+            let message = match change.reason {
+                SessionChangeReason::SessionLogon => Control::Logon(change.notification.session_id),
+                _ => Control::SessionChanged,
+            };
+            let _ = sender.send(message);
+            ServiceControlHandlerResult::NoError
+        }
+        ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+        _ => ServiceControlHandlerResult::NotImplemented,
+    };
+    let status_handle = service_control_handler::register(SERVICE_NAME, handler)?;
+
+    let status = |state, controls_accepted| ServiceStatus {
+        service_type: ServiceType::OWN_PROCESS,
+        current_state: state,
+        controls_accepted,
+        exit_code: ServiceExitCode::Win32(0),
+        checkpoint: 0,
+        wait_hint: Duration::default(),
+        process_id: None,
+    };
+    status_handle.set_service_status(status(
+        ServiceState::Running,
+        ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN | ServiceControlAccept::SESSION_CHANGE,
+    ))?;
+    info!("TouchRelay service started");
+
+    supervise(&receiver);
+
+    status_handle.set_service_status(status(ServiceState::Stopped, ServiceControlAccept::empty()))?;
+    Ok(())
+}
+
+/// Keep one TouchRelay running in the console session while a user is signed in there.
+/// Crashed instances are restarted; one the user quit stays closed until their next sign-in.
+fn supervise(receiver: &mpsc::Receiver<Control>) {
+    let mut instances: Vec<Instance> = Vec::new();
+    // Sessions whose user quit TouchRelay from the tray
+    let mut quit_in: Vec<u32> = Vec::new();
+
+    loop {
+        match receiver.recv_timeout(CHECK_INTERVAL) {
+            Ok(Control::Stop) | Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            Ok(Control::Logon(session)) => quit_in.retain(|quit| *quit != session),
+            Ok(Control::SessionChanged) | Err(mpsc::RecvTimeoutError::Timeout) => {}
+        }
+
+        instances.retain(|instance| match instance.exit_code() {
+            None => true,
+            Some(0) => {
+                info!("TouchRelay in session {} was closed", instance.session);
+                quit_in.push(instance.session);
+                false
+            }
+            Some(code) => {
+                warn!("TouchRelay in session {} exited with code {}", instance.session, code);
+                false
+            }
+        });
+
+        let session = unsafe { WTSGetActiveConsoleSessionId() };
+        if session == NO_CONSOLE_SESSION
+            || quit_in.contains(&session)
+            || instances.iter().any(|instance| instance.session == session)
+        {
+            continue;
+        }
+        // Fails quietly while nobody is signed in at the console
+        match launch_in_session(session) {
+            Ok(Some(instance)) => instances.push(instance),
+            Ok(None) => {}
+            Err(e) => {
+                warn!("{}", e);
+                eventlog::report(Level::Warning, EventId::ServiceFailed, &e);
+            }
+        }
+    }
+
+    for instance in instances {
+        unsafe {
+            let _ = TerminateProcess(instance.process, 0);
+        }
+    }
+    info!("TouchRelay service stopped");
+}
+
+/// Start this executable as the user signed in to the given session (None if nobody is)
+fn launch_in_session(session: u32) -> Result<Option<Instance>, String> {
+    let mut token = HANDLE::default();
+    if unsafe { WTSQueryUserToken(session, &mut token) }.is_err() {
+        return Ok(None);
+    }
+
+    let exe = std::env::current_exe().map_err(|e| format!("Failed to locate TouchRelay: {}", e))?;
+    let exe = HSTRING::from(exe.as_os_str());
+    let mut command_line: Vec<u16> = format!("\"{}\"", exe).encode_utf16().chain([0]).collect();
+    let mut desktop: Vec<u16> = "winsta0\\default".encode_utf16().chain([0]).collect();
+    let startup_info = STARTUPINFOW {
+        cb: std::mem::size_of::<STARTUPINFOW>() as u32,
+        lpDesktop: PWSTR(desktop.as_mut_ptr()),
+        ..Default::default()
+    };
+    let mut process_info = PROCESS_INFORMATION::default();
+
+    let result = unsafe {
+        // The user's environment, so %APPDATA% points at their config
+        let mut environment = std::ptr::null_mut();
+        let have_environment = CreateEnvironmentBlock(&mut environment, Some(token), false).is_ok();
+        let result = CreateProcessAsUserW(
+            Some(token),
+            &exe,
+            Some(PWSTR(command_line.as_mut_ptr())),
+            None,
+            None,
+            false,
+            CREATE_UNICODE_ENVIRONMENT,
+            have_environment.then_some(environment as *const _),
+            PCWSTR::null(),
+            &startup_info,
+            &mut process_info,
+        );
+        if have_environment {
+            let _ = DestroyEnvironmentBlock(environment);
+        }
+        let _ = CloseHandle(token);
+        result
+    };
+    result.map_err(|e| format!("Failed to start TouchRelay in session {}: {}", session, e))?;
+
+    unsafe {
+        let _ = CloseHandle(process_info.hThread);
+    }
+    info!("Started TouchRelay in session {} (pid {})", session, process_info.dwProcessId);
+    Ok(Some(Instance { process: process_info.hProcess, session }))
+}
+
+/// Register the service to start at boot (`--install-service`, needs administrator rights)
+pub fn install() -> Result<(), String> {
+    let manager = ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CONNECT | ServiceManagerAccess::CREATE_SERVICE)
+        .map_err(|e| format!("Failed to open the service manager (run as administrator): {}", e))?;
+    let executable_path = std::env::current_exe().map_err(|e| format!("Failed to locate TouchRelay: {}", e))?;
+
+    let info = ServiceInfo {
+        name: SERVICE_NAME.into(),
+        display_name: SERVICE_NAME.into(),
+        service_type: ServiceType::OWN_PROCESS,
+        start_type: ServiceStartType::AutoStart,
+        error_control: ServiceErrorControl::Normal,
+        executable_path,
+        launch_arguments: vec!["--service".into()],
+        dependencies: Vec::new(),
+        account_name: None,
+        account_password: None,
+    };
+    let service = manager
+        .create_service(&info, ServiceAccess::CHANGE_CONFIG | ServiceAccess::START)
+        .map_err(|e| format!("Failed to create the {} service: {}", SERVICE_NAME, e))?;
+    if let Err(e) = service.set_description(DESCRIPTION) {
+        warn!("Failed to set the service description: {}", e);
+    }
+    service
+        .start::<&str>(&[])
+        .map_err(|e| format!("Service installed but failed to start: {}", e))?;
+
+    info!("Installed and started the {} service", SERVICE_NAME);
+    Ok(())
+}
+
+/// Stop and remove the service (`--uninstall-service`, needs administrator rights)
+pub fn uninstall() -> Result<(), String> {
+    let manager = ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CONNECT)
+        .map_err(|e| format!("Failed to open the service manager (run as administrator): {}", e))?;
+    let service = manager
+        .open_service(SERVICE_NAME, ServiceAccess::QUERY_STATUS | ServiceAccess::STOP | ServiceAccess::DELETE)
+        .map_err(|e| format!("Failed to open the {} service: {}", SERVICE_NAME, e))?;
+
+    // Deleting only marks the service; it goes away once stopped
+    service.delete().map_err(|e| format!("Failed to remove the {} service: {}", SERVICE_NAME, e))?;
+    let running = service
+        .query_status()
+        .is_ok_and(|status| status.current_state != ServiceState::Stopped);
+    if running {
+        if let Err(e) = service.stop() {
+            warn!("Failed to stop the service: {}", e);
+        }
+        let deadline = std::time::Instant::now() + STOP_TIMEOUT;
+        while std::time::Instant::now() < deadline
+            && service.query_status().is_ok_and(|status| status.current_state != ServiceState::Stopped)
+        {
+            std::thread::sleep(Duration::from_millis(200));
+        }
+    }
+
+    info!("Removed the {} service", SERVICE_NAME);
+    Ok(())
+}
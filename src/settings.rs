@@ -0,0 +1,251 @@
+use axum::{
+    extract::Query,
+    http::{header, HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Redirect},
+    Form,
+};
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::config::{StartupMethod, StartupSettings};
+use crate::events::{self, AppEvent};
+
+const SENSITIVITY_RANGE: (f64, f64) = (0.5, 3.0);
+
+/// Fields posted by the settings form (checkboxes are only sent when ticked)
+#[derive(Deserialize)]
+pub struct SettingsForm {
+    port: u16,
+    /// Empty = each phone keeps its own slider value
+    sensitivity: String,
+    idle_timeout_secs: u64,
+    local_input_grace_ms: u64,
+    max_clients: usize,
+    joystick_speed: f64,
+    log_level: String,
+    exclusive_control: Option<String>,
+    require_pairing_approval: Option<String>,
+    mdns: Option<String>,
+    record_sessions: Option<String>,
+    start_with_windows: Option<String>,
+    /// `registry` or `task`
+    startup_method: String,
+    startup_elevated: Option<String>,
+    startup_delay_secs: u64,
+    paused: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct PageParams {
+    saved: Option<String>,
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// The settings page changes how the PC can be controlled, so it needs a login
+fn is_enabled() -> bool {
+    crate::config::get().basic_auth.is_some()
+}
+
+/// Reject cross-site form posts: browsers resend Basic credentials to any site that asks
+fn is_same_origin(headers: &HeaderMap) -> bool {
+    let Some(origin) = headers.get(header::ORIGIN).and_then(|value| value.to_str().ok()) else {
+        // Not sent by a browser form on another site
+        return true;
+    };
+    let origin_host = origin.split_once("://").map_or(origin, |(_, host)| host);
+    ["x-forwarded-host", header::HOST.as_str()]
+        .iter()
+        .filter_map(|name| headers.get(*name).and_then(|value| value.to_str().ok()))
+        .any(|host| host.split(',').next().unwrap_or_default().trim() == origin_host)
+}
+
+fn number_field(name: &str, label: &str, value: impl std::fmt::Display, attrs: &str) -> String {
+    format!(
+        r#"<label class="flex items-center justify-between gap-4 py-2"><span class="text-sm text-slate-700">{}</span><input type="number" name="{}" value="{}" {} class="w-32 px-3 py-2 text-sm border border-slate-200 rounded-lg"></label>"#,
+        label, name, value, attrs
+    )
+}
+
+fn checkbox(name: &str, label: &str, checked: bool) -> String {
+    format!(
+        r#"<label class="flex items-center justify-between gap-4 py-2"><span class="text-sm text-slate-700">{}</span><input type="checkbox" name="{}" {} class="w-5 h-5 accent-slate-900"></label>"#,
+        label, name, if checked { "checked" } else { "" }
+    )
+}
+
+/// Render the form with the current settings and an optional notice
+fn render(notice: Option<(bool, &str)>) -> String {
+    let config = crate::config::get();
+    let notice = match notice {
+        Some((true, text)) => format!(r#"<p class="px-3 py-2 text-sm rounded-lg bg-emerald-50 text-emerald-800">{}</p>"#, escape(text)),
+        Some((false, text)) => format!(r#"<p class="px-3 py-2 text-sm rounded-lg bg-red-50 text-red-800">{}</p>"#, escape(text)),
+        None => String::new(),
+    };
+
+    let fields = [
+        number_field("port", "Port (restarts the server)", config.port, r#"min="1" max="65535" required"#),
+        format!(
+            r#"<label class="flex items-center justify-between gap-4 py-2"><span class="text-sm text-slate-700">Default sensitivity (empty = per phone)</span><input type="number" name="sensitivity" value="{}" min="{}" max="{}" step="0.1" class="w-32 px-3 py-2 text-sm border border-slate-200 rounded-lg"></label>"#,
+            config.sensitivity.map(|s| s.to_string()).unwrap_or_default(),
+            SENSITIVITY_RANGE.0,
+            SENSITIVITY_RANGE.1
+        ),
+        number_field("joystick_speed", "Joystick speed (px/s)", config.joystick_speed, r#"min="1" step="any" required"#),
+        number_field("idle_timeout_secs", "Idle timeout (s, 0 = off)", config.idle_timeout_secs, r#"min="0" required"#),
+        number_field("local_input_grace_ms", "Local input grace (ms)", config.local_input_grace_ms, r#"min="0" required"#),
+        number_field("max_clients", "Max clients (0 = unlimited)", config.max_clients, r#"min="0" required"#),
+        format!(
+            r#"<label class="flex items-center justify-between gap-4 py-2"><span class="text-sm text-slate-700">Log level</span><input type="text" name="log_level" value="{}" placeholder="info" class="w-32 px-3 py-2 text-sm border border-slate-200 rounded-lg"></label>"#,
+            escape(config.log_level.as_deref().unwrap_or_default())
+        ),
+        checkbox("exclusive_control", "Exclusive control", config.exclusive_control),
+        checkbox("require_pairing_approval", "Require pairing approval", config.require_pairing_approval),
+        checkbox("mdns", "Advertise via mDNS (restarts the server)", config.mdns),
+        checkbox("record_sessions", "Record sessions", config.record_sessions),
+        checkbox("start_with_windows", "Start with Windows", crate::startup::is_startup_enabled()),
+        format!(
+            r#"<label class="flex items-center justify-between gap-4 py-2"><span class="text-sm text-slate-700">Start using</span><select name="startup_method" class="w-32 px-3 py-2 text-sm border border-slate-200 rounded-lg bg-white"><option value="registry"{}>Run key</option><option value="task"{}>Task Scheduler</option></select></label>"#,
+            if config.startup.method == StartupMethod::Registry { " selected" } else { "" },
+            if config.startup.method == StartupMethod::Task { " selected" } else { "" }
+        ),
+        checkbox("startup_elevated", "Start as administrator (Task Scheduler)", config.startup.elevated),
+        number_field("startup_delay_secs", "Delay after sign-in (s)", config.startup.delay_secs, r#"min="0" required"#),
+        checkbox("paused", "Pause remote input", crate::pause::is_paused()),
+    ];
+
+    format!(
+        r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>TouchRelay Settings</title>
+    <meta name="theme-color" content="#0f172a">
+    <script src="https://cdn.tailwindcss.com"></script>
+</head>
+<body class="bg-slate-50 min-h-screen">
+    <div class="max-w-2xl mx-auto bg-white min-h-screen border-x border-slate-200">
+        <header class="flex items-center justify-between px-6 py-4 border-b border-slate-200">
+            <h1 class="text-lg font-semibold text-slate-900">Settings</h1>
+            <a href="./" class="text-sm text-slate-600">Back</a>
+        </header>
+        <form method="post" action="settings" class="p-6 flex flex-col gap-2">
+            {}
+            {}
+            <button type="submit" class="mt-4 px-4 py-3 text-sm font-medium bg-slate-900 text-white rounded-lg active:scale-95 transition-all">Save</button>
+        </form>
+    </div>
+</body>
+</html>"##,
+        notice,
+        fields.join("\n            ")
+    )
+}
+
+/// GET /settings
+pub async fn page(Query(params): Query<PageParams>) -> impl IntoResponse {
+    if !is_enabled() {
+        return (StatusCode::FORBIDDEN, "The settings page needs [basic_auth] to be configured").into_response();
+    }
+
+    let notice = match params.saved.as_deref() {
+        Some("restart") => Some((true, "Saved. The server is restarting; reload this page in a few seconds (on the new port if you changed it).")),
+        Some(_) => Some((true, "Saved.")),
+        None => None,
+    };
+    Html(render(notice)).into_response()
+}
+
+/// POST /settings
+pub async fn save(headers: HeaderMap, Form(form): Form<SettingsForm>) -> impl IntoResponse {
+    if !is_enabled() {
+        return (StatusCode::FORBIDDEN, "The settings page needs [basic_auth] to be configured").into_response();
+    }
+    if !is_same_origin(&headers) {
+        warn!("Rejected settings form posted from another site");
+        return (StatusCode::FORBIDDEN, "Cross-site request rejected").into_response();
+    }
+
+    match apply(form) {
+        Ok(restart) => {
+            if restart {
+                events::send(AppEvent::RestartServer);
+            }
+            // Relative, so it also works below a reverse proxy's path prefix
+            Redirect::to(if restart { "settings?saved=restart" } else { "settings?saved=1" }).into_response()
+        }
+        Err(e) => (StatusCode::BAD_REQUEST, Html(render(Some((false, &e))))).into_response(),
+    }
+}
+
+/// Validate and store the form, returning whether the server must restart
+fn apply(form: SettingsForm) -> Result<bool, String> {
+    if form.port == 0 {
+        return Err("Port must be between 1 and 65535".to_string());
+    }
+    let sensitivity = match form.sensitivity.trim() {
+        "" => None,
+        value => {
+            let sensitivity: f64 = value.parse().map_err(|_| format!("Invalid sensitivity: {}", value))?;
+            if !(SENSITIVITY_RANGE.0..=SENSITIVITY_RANGE.1).contains(&sensitivity) {
+                return Err(format!("Sensitivity must be between {} and {}", SENSITIVITY_RANGE.0, SENSITIVITY_RANGE.1));
+            }
+            Some(sensitivity)
+        }
+    };
+    if !form.joystick_speed.is_finite() || form.joystick_speed <= 0.0 {
+        return Err("Joystick speed must be positive".to_string());
+    }
+    let startup = StartupSettings {
+        method: match form.startup_method.as_str() {
+            "registry" => StartupMethod::Registry,
+            "task" => StartupMethod::Task,
+            other => return Err(format!("Unknown startup method: {}", other)),
+        },
+        elevated: form.startup_elevated.is_some(),
+        delay_secs: form.startup_delay_secs,
+    };
+    let log_level = Some(form.log_level.trim().to_string()).filter(|level| !level.is_empty());
+    if let Some(level) = &log_level {
+        tracing_subscriber::EnvFilter::try_new(level).map_err(|e| format!("Invalid log level: {}", e))?;
+    }
+
+    let old = serde_json::to_value(&*crate::config::get()).map_err(|e| e.to_string())?;
+    crate::config::update(|config| {
+        config.port = form.port;
+        config.sensitivity = sensitivity;
+        config.joystick_speed = form.joystick_speed;
+        config.idle_timeout_secs = form.idle_timeout_secs;
+        config.local_input_grace_ms = form.local_input_grace_ms;
+        config.max_clients = form.max_clients;
+        config.log_level = log_level;
+        config.exclusive_control = form.exclusive_control.is_some();
+        config.require_pairing_approval = form.require_pairing_approval.is_some();
+        config.mdns = form.mdns.is_some();
+        config.record_sessions = form.record_sessions.is_some();
+        config.startup = startup;
+    })?;
+    let new = serde_json::to_value(&*crate::config::get()).map_err(|e| e.to_string())?;
+
+    // Register again when the method, elevation or delay changed
+    let start_with_windows = form.start_with_windows.is_some();
+    if start_with_windows != crate::startup::is_startup_enabled() || (start_with_windows && old.get("startup") != new.get("startup")) {
+        crate::startup::set_startup_enabled(start_with_windows).map_err(|e| format!("Failed to update startup: {}", e))?;
+    }
+    crate::pause::set_paused(form.paused.is_some());
+
+    // Apply what can change live, like an edit of config.toml would
+    crate::logging::apply_config();
+    crate::clients::send_profiles();
+    events::send(AppEvent::StateChanged);
+
+    let restart = crate::reload::changed_restart_keys(&old, &new);
+    info!("Settings saved from the web page{}", if restart.is_empty() { String::new() } else { format!(", restarting for {}", restart.join(", ")) });
+    Ok(!restart.is_empty())
+}
@@ -0,0 +1,23 @@
+use std::future::Future;
+use std::sync::OnceLock;
+use tokio::sync::watch;
+
+// Bumped on every shutdown request, so a restarted server gets a fresh signal
+static SIGNAL: OnceLock<watch::Sender<u64>> = OnceLock::new();
+
+fn signal() -> &'static watch::Sender<u64> {
+    SIGNAL.get_or_init(|| watch::channel(0).0)
+}
+
+/// Ask the running web server to close all connections and stop listening
+pub fn request() {
+    signal().send_modify(|generation| *generation += 1);
+}
+
+/// Resolve at the first shutdown request made after this call
+pub fn requested() -> impl Future<Output = ()> + Send + 'static {
+    let mut receiver = signal().subscribe();
+    async move {
+        let _ = receiver.changed().await;
+    }
+}
@@ -1,44 +1,94 @@
+#[cfg(feature = "gui")]
 use winreg::enums::*;
+#[cfg(feature = "gui")]
 use winreg::RegKey;
+#[cfg(feature = "gui")]
 use tracing::{info, error};
 
+#[cfg(feature = "gui")]
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[cfg(feature = "gui")]
+use crate::config::StartupMethod;
+
+#[cfg(feature = "gui")]
 const APP_NAME: &str = "TouchRelay";
+// Keep schtasks.exe from flashing a console window
+#[cfg(feature = "gui")]
+const CREATE_NO_WINDOW: u32 = 0x0800_0000;
 
-/// Check if the application is set to start with Windows
+// Last known startup state, so the tray menu never waits for schtasks on the UI thread
+#[cfg(feature = "gui")]
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Check if the application is set to start with Windows (by either method).
+/// Runs schtasks, so the tray menu uses `is_startup_enabled_cached` instead.
+#[cfg(feature = "gui")]
 pub fn is_startup_enabled() -> bool {
-    match get_startup_registry_key(false) {
-        Ok(key) => {
-            match key.get_value::<String, _>(APP_NAME) {
-                Ok(_) => true,
-                Err(_) => false,
-            }
-        }
+    let in_registry = match get_startup_registry_key(false) {
+        Ok(key) => key.get_value::<String, _>(APP_NAME).is_ok(),
         Err(_) => false,
-    }
+    };
+    let enabled = in_registry || task_exists();
+    ENABLED.store(enabled, Ordering::Relaxed);
+    enabled
+}
+
+/// Startup state as of the last check or change
+#[cfg(feature = "gui")]
+pub fn is_startup_enabled_cached() -> bool {
+    ENABLED.load(Ordering::Relaxed)
 }
 
-/// Enable startup with Windows
+/// Check the startup state on a worker thread, updating the menu if it differs from the cached one
+#[cfg(feature = "gui")]
+pub fn refresh_cached() {
+    std::thread::spawn(|| {
+        let cached = is_startup_enabled_cached();
+        if is_startup_enabled() != cached {
+            crate::events::send(crate::events::AppEvent::StateChanged);
+        }
+    });
+}
+
+/// Enable startup with Windows using the configured `[startup]` method
+#[cfg(feature = "gui")]
 fn enable_startup() -> Result<(), Box<dyn std::error::Error>> {
     let exe_path = std::env::current_exe()?;
     let exe_path_str = exe_path.to_string_lossy().to_string();
+    let settings = crate::config::get().startup;
 
-    let key = get_startup_registry_key(true)?;
-    key.set_value(APP_NAME, &exe_path_str)?;
+    // Only one entry may remain, or TouchRelay would be started twice
+    match settings.method {
+        StartupMethod::Registry => {
+            let key = get_startup_registry_key(true)?;
+            key.set_value(APP_NAME, &format!("\"{}\" --autostart", exe_path_str))?;
+            delete_task()?;
+        }
+        StartupMethod::Task => {
+            create_task(&exe_path_str, settings.elevated, settings.delay_secs)?;
+            remove_registry_value()?;
+        }
+    }
 
-    info!("Startup enabled: {}", exe_path_str);
+    ENABLED.store(true, Ordering::Relaxed);
+    info!("Startup enabled ({:?}): {}", settings.method, exe_path_str);
     Ok(())
 }
 
 /// Disable startup with Windows
+#[cfg(feature = "gui")]
 fn disable_startup() -> Result<(), Box<dyn std::error::Error>> {
-    let key = get_startup_registry_key(true)?;
-    key.delete_value(APP_NAME)?;
+    remove_registry_value()?;
+    delete_task()?;
 
+    ENABLED.store(false, Ordering::Relaxed);
     info!("Startup disabled");
     Ok(())
 }
 
 /// Toggle startup with Windows
+#[cfg(feature = "gui")]
 pub fn toggle_startup() {
     if is_startup_enabled() {
         match disable_startup() {
@@ -53,7 +103,50 @@ pub fn toggle_startup() {
     }
 }
 
+/// Enable startup with Windows (registering again with the current `[startup]` settings) or disable it
+#[cfg(feature = "gui")]
+pub fn set_startup_enabled(enabled: bool) -> Result<(), Box<dyn std::error::Error>> {
+    if enabled {
+        enable_startup()
+    } else if is_startup_enabled() {
+        disable_startup()
+    } else {
+        Ok(())
+    }
+}
+
+/// After restarting as administrator, make an enabled startup entry start TouchRelay the same way
+/// (a Task Scheduler task, since the Run key cannot start programs elevated)
+#[cfg(feature = "gui")]
+pub fn use_elevated_task() {
+    let settings = crate::config::get().startup;
+    if !is_startup_enabled() || (settings.method == StartupMethod::Task && settings.elevated) {
+        return;
+    }
+    let result = crate::config::update(|config| {
+        config.startup.method = StartupMethod::Task;
+        config.startup.elevated = true;
+    })
+    .and_then(|()| set_startup_enabled(true).map_err(|e| e.to_string()));
+    match result {
+        Ok(()) => info!("Start with Windows now starts TouchRelay as administrator"),
+        Err(e) => error!("Failed to update startup: {}", e),
+    }
+}
+
+/// How long to wait before starting the server when launched by "Start with Windows"
+#[cfg(feature = "gui")]
+pub fn launch_delay() -> std::time::Duration {
+    let settings = crate::config::get().startup;
+    match settings.method {
+        StartupMethod::Registry if crate::cli::get().autostart => std::time::Duration::from_secs(settings.delay_secs),
+        // Task Scheduler already waited before starting us
+        _ => std::time::Duration::ZERO,
+    }
+}
+
 /// Get the Windows registry key for startup programs
+#[cfg(feature = "gui")]
 fn get_startup_registry_key(writable: bool) -> Result<RegKey, std::io::Error> {
     let hkcu = RegKey::predef(HKEY_CURRENT_USER);
 
@@ -66,3 +159,103 @@ fn get_startup_registry_key(writable: bool) -> Result<RegKey, std::io::Error> {
         hkcu.open_subkey(r"Software\Microsoft\Windows\CurrentVersion\Run")
     }
 }
+
+/// Remove the Run key entry, if any
+#[cfg(feature = "gui")]
+fn remove_registry_value() -> Result<(), std::io::Error> {
+    match get_startup_registry_key(true)?.delete_value(APP_NAME) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Run `schtasks.exe` without flashing a console window
+#[cfg(feature = "gui")]
+fn schtasks(args: &[&str]) -> std::io::Result<std::process::Output> {
+    use std::os::windows::process::CommandExt;
+
+    std::process::Command::new("schtasks.exe")
+        .args(args)
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+}
+
+#[cfg(feature = "gui")]
+fn task_exists() -> bool {
+    schtasks(&["/Query", "/TN", APP_NAME]).is_ok_and(|output| output.status.success())
+}
+
+/// Register a Task Scheduler task starting TouchRelay when the current user signs in
+#[cfg(feature = "gui")]
+fn create_task(exe_path: &str, elevated: bool, delay_secs: u64) -> Result<(), String> {
+    let user = match (std::env::var("USERDOMAIN"), std::env::var("USERNAME")) {
+        (Ok(domain), Ok(name)) => format!("{}\\{}", domain, name),
+        (_, Ok(name)) => name,
+        _ => return Err("Failed to determine the current user".to_string()),
+    };
+    let escape = |text: &str| text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
+    let delay = if delay_secs > 0 { format!("<Delay>PT{}S</Delay>", delay_secs) } else { String::new() };
+    let run_level = if elevated { "HighestAvailable" } else { "LeastPrivilege" };
+
+    let xml = format!(
+        r#"<?xml version="1.0" encoding="UTF-16"?>
+<Task version="1.2" xmlns="http://schemas.microsoft.com/windows/2004/02/mit/task">
+  <RegistrationInfo><Description>Start TouchRelay when signing in</Description></RegistrationInfo>
+  <Triggers><LogonTrigger><Enabled>true</Enabled><UserId>{user}</UserId>{delay}</LogonTrigger></Triggers>
+  <Principals><Principal id="Author"><UserId>{user}</UserId><LogonType>InteractiveToken</LogonType><RunLevel>{run_level}</RunLevel></Principal></Principals>
+  <Settings>
+    <MultipleInstancesPolicy>IgnoreNew</MultipleInstancesPolicy>
+    <DisallowStartIfOnBatteries>false</DisallowStartIfOnBatteries>
+    <StopIfGoingOnBatteries>false</StopIfGoingOnBatteries>
+    <ExecutionTimeLimit>PT0S</ExecutionTimeLimit>
+  </Settings>
+  <Actions Context="Author"><Exec><Command>{exe}</Command><Arguments>--autostart</Arguments></Exec></Actions>
+</Task>"#,
+        user = escape(&user),
+        delay = delay,
+        run_level = run_level,
+        exe = escape(exe_path),
+    );
+
+    // schtasks expects the encoding the XML declares
+    let path = std::env::temp_dir().join("touchrelay-task.xml");
+    let bytes: Vec<u8> = [0xFEFFu16].into_iter().chain(xml.encode_utf16()).flat_map(u16::to_le_bytes).collect();
+    std::fs::write(&path, bytes).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    let output = schtasks(&["/Create", "/TN", APP_NAME, "/XML", &path.to_string_lossy(), "/F"]);
+    let _ = std::fs::remove_file(&path);
+
+    let output = output.map_err(|e| format!("Failed to run schtasks: {}", e))?;
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        let hint = if elevated { " (a task running elevated can only be created as administrator)" } else { "" };
+        return Err(format!("Failed to create the startup task: {}{}", message, hint));
+    }
+    Ok(())
+}
+
+/// Remove the Task Scheduler task, if any
+#[cfg(feature = "gui")]
+fn delete_task() -> Result<(), String> {
+    if !task_exists() {
+        return Ok(());
+    }
+    let output = schtasks(&["/Delete", "/TN", APP_NAME, "/F"]).map_err(|e| format!("Failed to run schtasks: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("Failed to delete the startup task: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(())
+}
+
+/// Builds without `gui` have no tray to start at login
+#[cfg(not(feature = "gui"))]
+pub fn is_startup_enabled() -> bool {
+    false
+}
+
+#[cfg(not(feature = "gui"))]
+pub fn set_startup_enabled(enabled: bool) -> Result<(), Box<dyn std::error::Error>> {
+    if enabled {
+        return Err("starting with Windows needs a build with the tray (gui feature)".into());
+    }
+    Ok(())
+}
@@ -0,0 +1,68 @@
+use axum::{response::IntoResponse, Json};
+use serde_json::json;
+use tracing::warn;
+
+use crate::config::{ThemeMode, ThemeSettings};
+
+const MIN_BUTTON_SCALE: f64 = 0.75;
+const MAX_BUTTON_SCALE: f64 = 2.0;
+
+/// The accent color if it is a `#rgb` or `#rrggbb` hex color (it ends up in the page's HTML)
+fn accent(theme: &ThemeSettings) -> Option<&str> {
+    let accent = theme.accent.as_deref()?;
+    let valid = accent
+        .strip_prefix('#')
+        .is_some_and(|hex| matches!(hex.len(), 3 | 6) && hex.chars().all(|c| c.is_ascii_hexdigit()));
+    if !valid {
+        warn!("Ignoring [theme] accent '{}': expected a color like \"#2563eb\"", accent);
+        return None;
+    }
+    Some(accent)
+}
+
+fn button_scale(theme: &ThemeSettings) -> f64 {
+    if theme.button_scale.is_finite() {
+        theme.button_scale.clamp(MIN_BUTTON_SCALE, MAX_BUTTON_SCALE)
+    } else {
+        1.0
+    }
+}
+
+fn mode_name(mode: ThemeMode) -> &'static str {
+    match mode {
+        ThemeMode::Light => "light",
+        ThemeMode::Dark => "dark",
+    }
+}
+
+/// Put the configured `[theme]` on the page's `<html>` element, where style.css picks it up
+pub fn inject(html: &str) -> String {
+    let theme = crate::config::get().theme.clone();
+    let mut attributes = format!(" data-theme=\"{}\"", mode_name(theme.mode));
+    let mut style = String::new();
+
+    if let Some(accent) = accent(&theme) {
+        attributes.push_str(" data-accent");
+        style.push_str(&format!("--tr-accent: {};", accent));
+    }
+    let scale = button_scale(&theme);
+    if scale != 1.0 {
+        attributes.push_str(" data-button-scale");
+        style.push_str(&format!("--tr-button-scale: {};", scale));
+    }
+    if !style.is_empty() {
+        attributes.push_str(&format!(" style=\"{}\"", style));
+    }
+
+    html.replacen("<html", &format!("<html{}", attributes), 1)
+}
+
+/// GET /api/theme (no API token needed, for clients drawing their own UI)
+pub async fn theme_handler() -> impl IntoResponse {
+    let theme = crate::config::get().theme.clone();
+    Json(json!({
+        "mode": mode_name(theme.mode),
+        "accent": accent(&theme),
+        "button_scale": button_scale(&theme),
+    }))
+}
@@ -0,0 +1,236 @@
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::i18n::{t, t_with};
+
+const RELEASES_URL: &str = "https://api.github.com/repos/DeltaFoundry/TouchRelay/releases/latest";
+// Release asset that replaces this executable
+const ASSET_NAME: &str = "touch-relay.exe";
+const CHECK_TIMEOUT: Duration = Duration::from_secs(15);
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(300);
+const MAX_DOWNLOAD_SIZE: u64 = 64 * 1024 * 1024;
+// Leave the network a moment to come up after signing in
+const FIRST_CHECK_DELAY: Duration = Duration::from_secs(60);
+const MIN_INTERVAL_HOURS: u64 = 1;
+
+/// Release tag the user was last told about, so each one is announced once
+static NOTIFIED: Mutex<Option<String>> = Mutex::new(None);
+/// Downloaded executable waiting to replace this one when TouchRelay quits
+static PENDING: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// The latest published release, as returned by the GitHub API
+#[derive(Debug, Clone, Deserialize)]
+pub struct Release {
+    pub tag_name: String,
+    /// Release page on github.com
+    pub html_url: String,
+    #[serde(default)]
+    assets: Vec<Asset>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Asset {
+    name: String,
+    browser_download_url: String,
+    /// Checksum such as `sha256:<hex>`
+    digest: Option<String>,
+}
+
+impl Release {
+    /// Version number without the `v` prefix
+    pub fn version(&self) -> &str {
+        self.tag_name.trim_start_matches(['v', 'V'])
+    }
+}
+
+/// Numeric components of a version such as `1.2.10` (pre-release and build suffixes ignored)
+fn parse_version(version: &str) -> Option<Vec<u64>> {
+    let core = version.trim_start_matches(['v', 'V']).split(['-', '+']).next()?;
+    core.split('.').map(|part| part.parse().ok()).collect()
+}
+
+/// Whether `latest` is a newer version than `current`
+fn is_newer(latest: &str, current: &str) -> bool {
+    match (parse_version(latest), parse_version(current)) {
+        (Some(latest), Some(current)) => latest > current,
+        _ => false,
+    }
+}
+
+/// Ask GitHub for the latest release, returning it if it is newer than this build.
+/// Blocks while the request runs.
+pub fn check() -> Result<Option<Release>, String> {
+    let mut response = crate::http::agent(CHECK_TIMEOUT)
+        .get(RELEASES_URL)
+        .header("Accept", "application/vnd.github+json")
+        .header("User-Agent", concat!("TouchRelay/", env!("CARGO_PKG_VERSION")))
+        .call()
+        .map_err(|e| format!("Failed to check for updates: {}", e))?;
+    let text = response
+        .body_mut()
+        .read_to_string()
+        .map_err(|e| format!("Failed to check for updates: {}", e))?;
+    let release: Release = serde_json::from_str(&text).map_err(|e| format!("Unexpected release information: {}", e))?;
+
+    if is_newer(release.version(), env!("CARGO_PKG_VERSION")) {
+        Ok(Some(release))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Check for updates in the background at startup and then every `[updates] interval_hours`
+pub fn spawn_checker() {
+    std::thread::spawn(|| {
+        std::thread::sleep(FIRST_CHECK_DELAY);
+        loop {
+            let settings = crate::config::get().updates;
+            if settings.check {
+                match check() {
+                    Ok(Some(release)) => announce(&release, settings.install_on_quit),
+                    Ok(None) => info!("TouchRelay is up to date"),
+                    Err(e) => warn!("{}", e),
+                }
+            }
+            std::thread::sleep(Duration::from_secs(settings.interval_hours.max(MIN_INTERVAL_HOURS) * 3600));
+        }
+    });
+}
+
+/// Check right away (from the tray menu) and report the result either way
+#[cfg(feature = "gui")]
+pub fn check_now() {
+    std::thread::spawn(|| match check() {
+        Ok(Some(release)) => {
+            *NOTIFIED.lock().unwrap() = Some(release.tag_name.clone());
+            show_release(&release, crate::config::get().updates.install_on_quit);
+        }
+        Ok(None) => {
+            info!("TouchRelay is up to date");
+            crate::notify::show(
+                &t("notify.up_to_date"),
+                &t_with("notify.update_current_version", &[("version", env!("CARGO_PKG_VERSION"))]),
+            );
+        }
+        Err(e) => {
+            warn!("{}", e);
+            crate::notify::show(&t("notify.update_check_failed"), &e);
+        }
+    });
+}
+
+/// Tell the user about a new release once, downloading it first if it should be installed on quit
+fn announce(release: &Release, install_on_quit: bool) {
+    {
+        let mut notified = NOTIFIED.lock().unwrap();
+        if notified.as_deref() == Some(release.tag_name.as_str()) {
+            return;
+        }
+        *notified = Some(release.tag_name.clone());
+    }
+    show_release(release, install_on_quit);
+}
+
+/// Notify about a new release with a button to its page
+fn show_release(release: &Release, install_on_quit: bool) {
+    info!("TouchRelay {} is available: {}", release.version(), release.html_url);
+
+    let mut message = t_with("notify.update_current_version", &[("version", env!("CARGO_PKG_VERSION"))]);
+    if install_on_quit {
+        match download(release) {
+            Ok(()) => message = format!("{} {}", message, t("notify.update_install_on_quit")),
+            Err(e) => warn!("{}", e),
+        }
+    }
+    crate::notify::show_with_link(
+        &t_with("notify.update_available", &[("version", release.version())]),
+        &message,
+        &t("notify.update_view_release"),
+        &release.html_url,
+    );
+}
+
+/// Path of this executable with another extension appended, e.g. `touch-relay.exe.new`
+fn sibling(suffix: &str) -> Result<PathBuf, String> {
+    let exe = std::env::current_exe().map_err(|e| format!("Failed to locate TouchRelay: {}", e))?;
+    let mut name = exe.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    Ok(exe.with_file_name(name))
+}
+
+/// Download the release's executable next to this one and verify its checksum
+fn download(release: &Release) -> Result<(), String> {
+    let asset = release
+        .assets
+        .iter()
+        .find(|asset| asset.name.eq_ignore_ascii_case(ASSET_NAME))
+        .ok_or_else(|| format!("Release {} has no {}", release.tag_name, ASSET_NAME))?;
+    let expected = asset
+        .digest
+        .as_deref()
+        .and_then(|digest| digest.strip_prefix("sha256:"))
+        .ok_or_else(|| format!("Release {} has no checksum for {}, not installing it", release.tag_name, ASSET_NAME))?;
+
+    let url = &asset.browser_download_url;
+    let mut response = crate::http::agent(DOWNLOAD_TIMEOUT)
+        .get(url)
+        .header("User-Agent", concat!("TouchRelay/", env!("CARGO_PKG_VERSION")))
+        .call()
+        .map_err(|e| format!("Failed to download {}: {}", url, e))?;
+    let data = response
+        .body_mut()
+        .with_config()
+        .limit(MAX_DOWNLOAD_SIZE)
+        .read_to_vec()
+        .map_err(|e| format!("Failed to download {}: {}", url, e))?;
+
+    let actual: String = Sha256::digest(&data).iter().map(|b| format!("{:02x}", b)).collect();
+    if !actual.eq_ignore_ascii_case(expected) {
+        return Err(format!("Checksum mismatch for {} (downloaded file is {})", url, actual));
+    }
+
+    let path = sibling(".new")?;
+    std::fs::write(&path, &data).map_err(|e| format!("Failed to save the update to {}: {}", path.display(), e))?;
+    info!("Downloaded TouchRelay {} to {}", release.version(), path.display());
+    *PENDING.lock().unwrap() = Some(path);
+    Ok(())
+}
+
+/// Swap in a downloaded update; call when TouchRelay is about to exit.
+/// A running exe can be renamed but not overwritten, so it moves aside to `.old`.
+pub fn install_pending() {
+    let Some(new) = PENDING.lock().unwrap().take() else {
+        return;
+    };
+    let result = (|| {
+        let exe = std::env::current_exe().map_err(|e| format!("Failed to locate TouchRelay: {}", e))?;
+        let old = sibling(".old")?;
+        let _ = std::fs::remove_file(&old);
+        std::fs::rename(&exe, &old).map_err(|e| format!("Failed to move {} aside: {}", exe.display(), e))?;
+        if let Err(e) = std::fs::rename(&new, &exe) {
+            let _ = std::fs::rename(&old, &exe);
+            return Err(format!("Failed to replace {}: {}", exe.display(), e));
+        }
+        Ok::<_, String>(exe)
+    })();
+    match result {
+        Ok(exe) => info!("Installed the update to {}", exe.display()),
+        Err(e) => warn!("{}", e),
+    }
+}
+
+/// Delete the executable left behind by the last update
+pub fn remove_old() {
+    if let Ok(old) = sibling(".old") {
+        if old.exists() {
+            match std::fs::remove_file(&old) {
+                Ok(()) => info!("Removed {} left by the last update", old.display()),
+                Err(e) => warn!("Failed to remove {}: {}", old.display(), e),
+            }
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use serde_json::{json, Value};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{debug, warn};
+
+// Events waiting for delivery; more are dropped so a flood of failed logins cannot pile up
+const QUEUE_SIZE: usize = 64;
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+static QUEUE: OnceLock<SyncSender<Value>> = OnceLock::new();
+
+/// Queue feeding the thread that posts events to the configured URLs one at a time
+fn queue() -> &'static SyncSender<Value> {
+    QUEUE.get_or_init(|| {
+        let (sender, receiver) = mpsc::sync_channel::<Value>(QUEUE_SIZE);
+        std::thread::spawn(move || {
+            let agent = crate::http::agent(TIMEOUT);
+            for body in receiver {
+                for url in &crate::config::get().webhooks {
+                    deliver(&agent, url, &body);
+                }
+            }
+        });
+        sender
+    })
+}
+
+fn deliver(agent: &ureq::Agent, url: &str, body: &Value) {
+    if !url.starts_with("https://") && !url.starts_with("http://") {
+        warn!("Ignoring webhook with unsupported URL: {}", url);
+        return;
+    }
+
+    let result = agent
+        .post(url)
+        .header("Content-Type", "application/json")
+        .send(body.to_string());
+    match result {
+        Ok(_) => debug!("Delivered {} webhook to {}", body["event"], url),
+        Err(e) => warn!("Failed to deliver webhook to {}: {}", url, e),
+    }
+}
+
+/// Post `{"event", "host", "timestamp", ...details}` to every configured webhook URL
+/// (`connect`, `disconnect` and `auth_failed`). Delivery happens in the background.
+pub fn notify(event: &str, details: Value) {
+    if crate::config::get().webhooks.is_empty() {
+        return;
+    }
+
+    let mut body = json!({
+        "event": event,
+        "host": std::env::var("COMPUTERNAME").unwrap_or_default(),
+        "timestamp": SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+    });
+    if let (Some(body), Value::Object(details)) = (body.as_object_mut(), details) {
+        body.extend(details);
+    }
+
+    if let Err(TrySendError::Full(_)) = queue().try_send(body) {
+        warn!("Webhook queue full, dropping {} event", event);
+    }
+}
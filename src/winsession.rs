@@ -0,0 +1,86 @@
+use serde_json::json;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use tracing::{info, warn};
+use windows::core::PWSTR;
+use windows::Win32::System::RemoteDesktop::{
+    ProcessIdToSessionId, WTSActive, WTSConnectState, WTSFreeMemory, WTSGetActiveConsoleSessionId,
+    WTSQuerySessionInformationW, WTS_CONNECTSTATE_CLASS,
+};
+use windows::Win32::UI::WindowsAndMessaging::{GetSystemMetrics, SM_REMOTESESSION};
+
+// Returned by WTSGetActiveConsoleSessionId while sessions are being switched
+const NO_CONSOLE_SESSION: u32 = 0xFFFF_FFFF;
+
+static OWN_SESSION: OnceLock<Option<u32>> = OnceLock::new();
+static WAS_ACTIVE: AtomicBool = AtomicBool::new(true);
+
+/// Windows session this process runs in
+fn own_session() -> Option<u32> {
+    *OWN_SESSION.get_or_init(|| {
+        let mut session = 0;
+        match unsafe { ProcessIdToSessionId(std::process::id(), &mut session) } {
+            Ok(()) => Some(session),
+            Err(e) => {
+                warn!("Failed to query Windows session: {}", e);
+                None
+            }
+        }
+    })
+}
+
+/// Check whether this process runs inside a Remote Desktop session
+pub fn is_remote() -> bool {
+    unsafe { GetSystemMetrics(SM_REMOTESESSION) != 0 }
+}
+
+/// Check whether a Remote Desktop client is currently attached to the session
+fn is_connected(session: u32) -> bool {
+    let mut buffer = PWSTR::null();
+    let mut size = 0;
+    unsafe {
+        if WTSQuerySessionInformationW(None, session, WTSConnectState, &mut buffer, &mut size).is_err() {
+            // Unknown, assume it works rather than dropping input
+            return true;
+        }
+        let state = *(buffer.0 as *const WTS_CONNECTSTATE_CLASS);
+        WTSFreeMemory(buffer.0 as _);
+        state == WTSActive
+    }
+}
+
+/// Describe the host session for clients
+pub fn status(active: bool) -> serde_json::Value {
+    json!({ "active": active, "remote": is_remote() })
+}
+
+/// Check whether input injected by this process reaches a desktop someone is looking at:
+/// the physical console, or a Remote Desktop session with a client attached.
+/// With fast user switching, input from other sessions would land on a desktop nobody sees.
+pub fn is_active() -> bool {
+    let Some(own) = own_session() else {
+        // Unknown, keep working as before
+        return true;
+    };
+
+    let console = unsafe { WTSGetActiveConsoleSessionId() };
+    let active = if is_remote() {
+        is_connected(own)
+    } else {
+        console != NO_CONSOLE_SESSION && console == own
+    };
+
+    // Report transitions only, not every dropped message
+    if WAS_ACTIVE.swap(active, Ordering::Relaxed) != active {
+        if active {
+            info!("Session {} is active again, resuming input", own);
+        } else if is_remote() {
+            warn!("Remote Desktop client disconnected from session {}, ignoring remote input", own);
+        } else {
+            warn!("Session {} is not on the console (active session {}), ignoring remote input", own, console);
+        }
+        crate::clients::broadcast(&json!(["session", status(active)]));
+    }
+
+    active
+}
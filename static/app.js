@@ -2,11 +2,44 @@
 let ws = null;
 let isConnected = false;
 
+// Set when the PC owner rejects this device (stops reconnect attempts)
+let pairingDenied = false;
+
+// Set when the PC closed the connection because TouchRelay is quitting
+let serverStopped = false;
+
+// Heartbeat so the server doesn't close the connection as idle
+const HEARTBEAT_INTERVAL = 30000; // ms
+let heartbeatTimer = null;
+
+// Message protocol version spoken by this script (must match the server)
+const PROTOCOL_VERSION = 1;
+
+// Fingerprint of the web assets reported by the server when this page was loaded
+let assetVersion = null;
+
+// Host screen layout from the hello message (size, scale, monitors)
+let hostScreen = null;
+
 // Mouse movement sensitivity factor (will be loaded from localStorage or use default)
 let MOVE_FACTOR = 1.8;
 
+// Invert two-finger scroll direction (set by the device's input profile)
+let NATURAL_SCROLL = false;
+
+// Commands sent for each gesture (can be overridden by the device's input profile)
+const GESTURE_BINDINGS = {
+    tap: ['b', 'l', 1],
+    double_tap: ['b', 'l', 2],
+    two_finger_tap: ['b', 'r', 1]
+};
+
 // Scroll accumulator for smooth scrolling
 let scrollAccumulator = 0;
+let scrollAccumulatorX = 0;
+
+// Select mode: the server holds the left button down while the cursor moves
+let selectMode = false;
 const SCROLL_THRESHOLD = 20; // pixels to accumulate before sending scroll command
 
 // Last position tracking for smooth movement
@@ -21,20 +54,92 @@ let lastTapTime = 0;
 let tapTimeout = null;
 const DOUBLE_TAP_INTERVAL = 180; // ms - reduced for faster single tap response
 
+// Translated UI strings from the server (empty: the English built into the page)
+let STRINGS = {};
+
 // Initialize when page loads
-document.addEventListener('DOMContentLoaded', () => {
+document.addEventListener('DOMContentLoaded', async () => {
+    await loadStrings();
     loadSettings();
     initWebSocket();
     initTouchpad();
     initTextInput();
     initFunctionKeys();
+    initGamepad();
+    initJoystick();
+    initSelectMode();
     initSensitivityControls();
+    initTakeover();
+    registerServiceWorker();
 });
 
+// Fetch the UI strings for the phone's language and apply them to the page
+async function loadStrings() {
+    const language = navigator.language || 'en';
+    try {
+        const response = await fetch(`api/i18n/${encodeURIComponent(language)}.json`);
+        if (!response.ok) {
+            return;
+        }
+        STRINGS = await response.json();
+        document.documentElement.lang = response.headers.get('Content-Language') || language;
+    } catch (e) {
+        console.warn('UI strings not loaded:', e);
+        return;
+    }
+
+    document.querySelectorAll('[data-i18n]').forEach(el => {
+        el.textContent = t(el.dataset.i18n, el.textContent);
+    });
+    document.querySelectorAll('[data-i18n-placeholder]').forEach(el => {
+        el.placeholder = t(el.dataset.i18nPlaceholder, el.placeholder);
+    });
+    document.querySelectorAll('[data-i18n-title]').forEach(el => {
+        el.title = t(el.dataset.i18nTitle, el.title);
+    });
+}
+
+// Translated string for a key, or the English text when there is none
+function t(key, fallback) {
+    return STRINGS[key] || fallback;
+}
+
+// Register the service worker so the page can be installed to the home screen.
+// Browsers only allow this over HTTPS or on localhost.
+function registerServiceWorker() {
+    if (!('serviceWorker' in navigator)) {
+        return;
+    }
+
+    navigator.serviceWorker.register('sw.js').catch(e => console.warn('Service worker not registered:', e));
+    navigator.serviceWorker.addEventListener('message', (event) => {
+        if (event.data === 'assets-updated') {
+            console.log('Web assets updated in the background, reloading');
+            window.location.reload();
+        }
+    });
+}
+
+// Get a persistent ID for this device (used by the server's permission policy)
+function getDeviceId() {
+    let deviceId = localStorage.getItem('deviceId');
+
+    if (deviceId === null) {
+        deviceId = Array.from(crypto.getRandomValues(new Uint8Array(8)))
+            .map(b => b.toString(16).padStart(2, '0'))
+            .join('');
+        localStorage.setItem('deviceId', deviceId);
+    }
+
+    return deviceId;
+}
+
 // WebSocket initialization
 function initWebSocket() {
-    const protocol = window.location.protocol === 'https:' ? 'wss:' : 'ws:';
-    const wsUrl = `${protocol}//${window.location.host}/ws`;
+    // Relative to the page so the app also works below a reverse proxy's path prefix
+    const url = new URL('ws', document.baseURI);
+    url.protocol = url.protocol === 'https:' ? 'wss:' : 'ws:';
+    const wsUrl = `${url.href}?device=${encodeURIComponent(getDeviceId())}&v=${PROTOCOL_VERSION}`;
 
     console.log('Connecting to:', wsUrl);
     ws = new WebSocket(wsUrl);
@@ -42,31 +147,256 @@ function initWebSocket() {
     ws.onopen = () => {
         console.log('WebSocket connected');
         isConnected = true;
-        updateStatus('Connected', true);
+        serverStopped = false;
+        updateStatus(t('status_connected', 'Connected'), true);
+
+        clearInterval(heartbeatTimer);
+        heartbeatTimer = setInterval(() => sendMessage(['ping']), HEARTBEAT_INTERVAL);
     };
 
-    ws.onclose = () => {
-        console.log('WebSocket disconnected');
+    ws.onclose = (event) => {
+        console.log('WebSocket disconnected', event.code, event.reason);
+        // 1001 "going away": the server is shutting down, keep saying so while retrying
+        if (event.code === 1001) {
+            serverStopped = true;
+        }
+        // 1008 "policy": disconnected from the PC's tray menu, stay disconnected until reloaded
+        const disconnectedByHost = event.code === 1008 && !pairingDenied;
         isConnected = false;
-        updateStatus('Disconnected', false);
+        clearInterval(heartbeatTimer);
+        showTakeoverButton(false);
+        document.getElementById('status-indicator').classList.remove('paused');
+        // The server lets go of the selection button when the connection drops
+        selectMode = false;
+        document.getElementById('btn-select').classList.remove('active');
+        updateStatus(serverStopped ? t('status_server_stopped', 'Server stopped') : t('status_disconnected', 'Disconnected'), false);
+
+        if (pairingDenied) {
+            updateStatus(t('status_denied', 'Denied'), false);
+            return;
+        }
+        if (disconnectedByHost) {
+            updateStatus(t('status_disconnected_by_host', 'Disconnected by the PC'), false);
+            return;
+        }
 
         // Attempt to reconnect after 3 seconds
         setTimeout(() => {
-            updateStatus('Connecting', false);
+            updateStatus(serverStopped ? t('status_server_stopped', 'Server stopped') : t('status_connecting', 'Connecting'), false);
             initWebSocket();
         }, 3000);
     };
 
     ws.onerror = (error) => {
         console.error('WebSocket error:', error);
-        updateStatus('Error', false);
+        updateStatus(t('status_error', 'Error'), false);
     };
 
     ws.onmessage = (event) => {
         console.log('Message from server:', event.data);
+        handleServerMessage(event.data);
     };
 }
 
+// Handle a message pushed by the server
+function handleServerMessage(data) {
+    let msg;
+    try {
+        msg = JSON.parse(data);
+    } catch (e) {
+        console.warn('Invalid server message:', data);
+        return;
+    }
+
+    if (!Array.isArray(msg) || msg.length === 0) return;
+
+    switch (msg[0]) {
+        case 'hello':
+            handleHello(msg[1] || {});
+            break;
+        case 'reload':
+            console.log('Server requested reload');
+            window.location.reload();
+            break;
+        case 'profile':
+            applyProfile(msg[1] || {});
+            break;
+        case 'pairing':
+            handlePairing(msg[1]);
+            break;
+        case 'busy':
+            // 'controlled': another device has control, 'full': connection limit reached
+            updateStatus(msg[1] === 'full' ? t('status_server_full', 'Server full') : t('status_busy', 'Busy'), false);
+            showTakeoverButton(msg[1] === 'controlled');
+            break;
+        case 'control':
+            updateStatus(t('status_connected', 'Connected'), true);
+            showTakeoverButton(false);
+            break;
+        case 'takeover_request': {
+            const approved = confirm(t('takeover_prompt', 'Another device wants to take over control. Hand over?'));
+            sendMessage(['takeover_reply', msg[1], approved]);
+            break;
+        }
+        case 'takeover_denied':
+            updateStatus(t('status_busy', 'Busy'), false);
+            showTakeoverButton(true);
+            break;
+        case 'paused':
+            updatePaused(msg[1] === true);
+            break;
+        case 'select':
+            // The PC let go of the selection button (input paused or control taken over)
+            selectMode = msg[1] === true;
+            document.getElementById('btn-select').classList.toggle('active', selectMode);
+            break;
+        case 'session':
+            updateSession(msg[1]);
+            break;
+        case 'screen':
+            // Monitors were added, removed or resized on the host
+            console.log('Host screen changed:', msg[1]);
+            hostScreen = msg[1] || null;
+            break;
+        default:
+            console.warn('Unknown server message:', msg[0]);
+    }
+}
+
+// Reload the page if the server was updated since this page was loaded
+function handleHello(info) {
+    console.log('Server version:', info.version, 'protocol:', info.protocol);
+    hostScreen = info.screen || null;
+    updateSession(info.session);
+
+    if (assetVersion === null) {
+        assetVersion = info.assets;
+    } else if (info.assets !== assetVersion) {
+        console.log('Web assets changed, reloading');
+        window.location.reload();
+    }
+}
+
+// Show the state of the host's approval for this (new) device
+function handlePairing(state) {
+    if (state === 'pending') {
+        updateStatus(t('status_awaiting_approval', 'Awaiting approval'), false);
+    } else if (state === 'approved') {
+        updateStatus(t('status_connected', 'Connected'), true);
+    } else if (state === 'denied') {
+        pairingDenied = true;
+    }
+}
+
+// Apply the input profile stored on the server for this device
+function applyProfile(profile) {
+    if (typeof profile.sensitivity === 'number') {
+        MOVE_FACTOR = profile.sensitivity;
+        document.getElementById('move-factor').value = MOVE_FACTOR;
+        document.getElementById('move-factor-value').textContent = MOVE_FACTOR.toFixed(1) + 'x';
+    }
+
+    if (typeof profile.natural_scroll === 'boolean') {
+        NATURAL_SCROLL = profile.natural_scroll;
+        document.getElementById('natural-scroll').checked = NATURAL_SCROLL;
+    }
+
+    if (profile.gestures) {
+        for (const [gesture, command] of Object.entries(profile.gestures)) {
+            if (gesture in GESTURE_BINDINGS && Array.isArray(command)) {
+                GESTURE_BINDINGS[gesture] = command;
+            }
+        }
+    }
+
+    if (profile.layout) {
+        applyLayout(profile.layout);
+        showLayoutChoice(profile.layout);
+    }
+
+    console.log('Applied device profile:', profile);
+}
+
+// Show or hide the keyboard controls ('full' or 'touchpad')
+function applyLayout(layout) {
+    const builtIn = ['full', 'touchpad', 'gamepad', 'joystick'].includes(layout);
+    const hideKeyboard = layout !== 'full';
+    document.getElementById('text-row').classList.toggle('hidden', hideKeyboard);
+    document.getElementById('function-keys').classList.toggle('hidden', hideKeyboard);
+    document.getElementById('gamepad').classList.toggle('hidden', layout !== 'gamepad');
+    document.getElementById('joystick').classList.toggle('hidden', layout !== 'joystick');
+
+    if (builtIn) {
+        document.getElementById('custom-layout').classList.add('hidden');
+        document.getElementById('touchpad').classList.toggle('hidden', layout === 'joystick');
+    } else {
+        loadCustomLayout(layout);
+    }
+}
+
+// Select the layout in the picker, adding custom layouts set in the device's profile
+function showLayoutChoice(layout) {
+    const select = document.getElementById('layout-select');
+    if (![...select.options].some(option => option.value === layout)) {
+        select.add(new Option(layout, layout));
+    }
+    select.value = layout;
+}
+
+// Fetch a custom layout defined through /api/layouts and render it
+async function loadCustomLayout(name) {
+    try {
+        const response = await fetch('layouts/' + encodeURIComponent(name));
+        if (!response.ok) throw new Error(response.status);
+        renderCustomLayout(await response.json());
+    } catch (e) {
+        console.warn('Failed to load layout', name, e);
+        applyLayout('full');
+    }
+}
+
+// Build buttons, sliders and the touchpad described by a layout definition
+function renderCustomLayout(layout) {
+    const container = document.getElementById('custom-layout');
+    container.innerHTML = '';
+    container.style.gridTemplateColumns = `repeat(${layout.columns || 4}, minmax(0, 1fr))`;
+
+    let hasTouchpad = false;
+    for (const control of layout.controls || []) {
+        if (control.type === 'touchpad') {
+            hasTouchpad = true;
+        } else if (control.type === 'button') {
+            const button = document.createElement('button');
+            button.className = 'px-3 py-2 text-xs font-medium bg-slate-100 text-slate-700 rounded-lg hover:bg-slate-200 active:scale-95 transition-all border border-slate-300';
+            button.textContent = control.label || control.command.join(' ');
+            if (control.icon && layout.pack) {
+                // Pack layouts can show one of the pack's icons next to the label
+                const icon = document.createElement('img');
+                icon.src = `packs/${encodeURIComponent(layout.pack)}/icons/${encodeURIComponent(control.icon)}`;
+                icon.alt = '';
+                icon.className = 'inline-block w-4 h-4 mr-1 align-middle';
+                button.prepend(icon);
+            }
+            button.addEventListener('click', () => sendMessage(control.command));
+            container.appendChild(button);
+        } else if (control.type === 'slider') {
+            // Sliders send their command with the value appended
+            const slider = document.createElement('input');
+            slider.type = 'range';
+            slider.min = control.min ?? 0;
+            slider.max = control.max ?? 100;
+            slider.value = control.value ?? slider.min;
+            slider.title = control.label || '';
+            slider.className = 'col-span-full accent-slate-900';
+            slider.addEventListener('change', () => sendMessage([...control.command, Number(slider.value)]));
+            container.appendChild(slider);
+        }
+    }
+
+    container.classList.remove('hidden');
+    document.getElementById('touchpad').classList.toggle('hidden', !hasTouchpad);
+}
+
 // Update connection status display
 function updateStatus(text, connected) {
     const statusText = document.getElementById('status-text');
@@ -83,6 +413,32 @@ function updateStatus(text, connected) {
     }
 }
 
+// Show whether the PC owner has paused remote input
+function updatePaused(paused) {
+    const statusIndicator = document.getElementById('status-indicator');
+
+    if (paused) {
+        updateStatus(t('status_paused', 'Paused'), false);
+        statusIndicator.classList.add('paused');
+    } else {
+        statusIndicator.classList.remove('paused');
+        updateStatus(t('status_connected', 'Connected'), true);
+    }
+}
+
+// Show whether input can reach the PC's desktop (another user or a
+// disconnected Remote Desktop session means it is being ignored)
+function updateSession(session) {
+    if (!session) return;
+
+    const paused = document.getElementById('status-indicator').classList.contains('paused');
+    if (session.active === false) {
+        updateStatus(session.remote ? t('status_remote_disconnected', 'Remote session disconnected') : t('status_session_inactive', 'PC session inactive'), false);
+    } else if (!paused) {
+        updateStatus(t('status_connected', 'Connected'), true);
+    }
+}
+
 // Send message via WebSocket
 function sendMessage(msg) {
     if (ws && ws.readyState === WebSocket.OPEN) {
@@ -137,6 +493,7 @@ function initTouchpad() {
         panPointerCount = e.pointers.length;
         lastPanDelta = { x: e.deltaX, y: e.deltaY };
         scrollAccumulator = 0; // Reset scroll accumulator
+        scrollAccumulatorX = 0;
         console.log(`Pan start with ${panPointerCount} finger(s)`);
     });
 
@@ -155,17 +512,26 @@ function initTouchpad() {
             const moveY = Math.round(dy * MOVE_FACTOR);
 
             if (moveX !== 0 || moveY !== 0) {
-                sendMessage(['m', moveX, moveY]);
+                // Timestamp lets the server replay bursts delayed by the network smoothly
+                sendMessage(['m', moveX, moveY, Math.round(performance.now())]);
             }
         } else if (panPointerCount === 2) {
             // Two fingers - scroll wheel with accumulation
-            scrollAccumulator += dy;
+            // Follow the dominant axis so a slightly diagonal swipe does not scroll both ways
+            if (Math.abs(dx) > Math.abs(dy)) {
+                scrollAccumulatorX += dx;
+            } else {
+                scrollAccumulator += dy;
+            }
 
             // Send scroll command when threshold is reached
             if (Math.abs(scrollAccumulator) >= SCROLL_THRESHOLD) {
                 // Calculate how many scroll units to send
                 const scrollUnits = Math.floor(Math.abs(scrollAccumulator) / SCROLL_THRESHOLD);
-                const scrollDirection = scrollAccumulator > 0 ? -1 : 1; // Invert: down swipe = scroll down
+                let scrollDirection = scrollAccumulator > 0 ? -1 : 1; // Invert: down swipe = scroll down
+                if (NATURAL_SCROLL) {
+                    scrollDirection = -scrollDirection;
+                }
 
                 // Send scroll commands
                 for (let i = 0; i < scrollUnits; i++) {
@@ -175,6 +541,21 @@ function initTouchpad() {
                 // Reduce accumulator by the amount we've scrolled
                 scrollAccumulator = scrollAccumulator % SCROLL_THRESHOLD * Math.sign(scrollAccumulator);
             }
+
+            // Sideways swipes scroll horizontally in the same direction convention
+            if (Math.abs(scrollAccumulatorX) >= SCROLL_THRESHOLD) {
+                const scrollUnits = Math.floor(Math.abs(scrollAccumulatorX) / SCROLL_THRESHOLD);
+                let scrollDirection = scrollAccumulatorX > 0 ? -1 : 1;
+                if (NATURAL_SCROLL) {
+                    scrollDirection = -scrollDirection;
+                }
+
+                for (let i = 0; i < scrollUnits; i++) {
+                    sendMessage(['w', 0, scrollDirection]);
+                }
+
+                scrollAccumulatorX = scrollAccumulatorX % SCROLL_THRESHOLD * Math.sign(scrollAccumulatorX);
+            }
         }
     });
 
@@ -191,6 +572,12 @@ function initTouchpad() {
     hammer.on('tap', (e) => {
         if (isPanning) return; // Ignore taps during pan
 
+        // A tap finishes the selection instead of clicking
+        if (selectMode) {
+            setSelectMode(false);
+            return;
+        }
+
         const now = Date.now();
 
         // Ignore single tap shortly after two finger tap
@@ -206,8 +593,8 @@ function initTouchpad() {
                 clearTimeout(tapTimeout);
                 tapTimeout = null;
             }
-            console.log('Double tap - instant double left click');
-            sendMessage(['b', 'l', 2]);
+            console.log('Double tap');
+            sendMessage(GESTURE_BINDINGS.double_tap);
             lastTapTime = 0; // Reset to prevent triple tap
         } else {
             // Potentially a single tap - delay to check for double tap
@@ -215,8 +602,8 @@ function initTouchpad() {
                 clearTimeout(tapTimeout);
             }
             tapTimeout = setTimeout(() => {
-                console.log('Single tap - left click');
-                sendMessage(['b', 'l', 1]);
+                console.log('Single tap');
+                sendMessage(GESTURE_BINDINGS.tap);
                 tapTimeout = null;
             }, DOUBLE_TAP_INTERVAL);
             lastTapTime = now;
@@ -230,8 +617,8 @@ function initTouchpad() {
         const now = Date.now();
         lastTwoFingerTapTime = now;
 
-        console.log('Two finger tap - right click');
-        sendMessage(['b', 'r', 1]);
+        console.log('Two finger tap');
+        sendMessage(GESTURE_BINDINGS.two_finger_tap);
     });
 
     // Prevent context menu
@@ -263,7 +650,7 @@ function initTextInput() {
                     btnSendText.classList.remove('scale-95');
                 }, 150);
             } else {
-                alert('Not connected to server, cannot send text');
+                alert(t('not_connected', 'Not connected to server, cannot send text'));
             }
         }
     };
@@ -316,6 +703,123 @@ function initFunctionKeys() {
     });
 }
 
+// Initialize the gamepad keys, which stay down for as long as they are touched
+function initGamepad() {
+    document.querySelectorAll('[data-hold-key]').forEach(button => {
+        const keyName = button.getAttribute('data-hold-key');
+
+        button.addEventListener('pointerdown', (e) => {
+            e.preventDefault();
+            button.setPointerCapture(e.pointerId);
+            if (sendMessage(['kd', keyName])) {
+                button.classList.add('held');
+            }
+        });
+
+        const release = () => {
+            if (button.classList.contains('held')) {
+                button.classList.remove('held');
+                sendMessage(['ku', keyName]);
+            }
+        };
+        button.addEventListener('pointerup', release);
+        button.addEventListener('pointercancel', release);
+    });
+}
+
+// Initialize the joystick, which sends its deflection a few times per second
+// while the server turns it into smooth cursor motion
+function initJoystick() {
+    const base = document.getElementById('joystick-base');
+    const knob = document.getElementById('joystick-knob');
+    let vector = null;
+    let timer = null;
+
+    const send = () => sendMessage(['j', vector.x, vector.y]);
+
+    const update = (e) => {
+        const rect = base.getBoundingClientRect();
+        const radius = rect.width / 2;
+        let x = (e.clientX - rect.left - radius) / radius;
+        let y = (e.clientY - rect.top - radius) / radius;
+        const length = Math.hypot(x, y);
+        if (length > 1) {
+            x /= length;
+            y /= length;
+        }
+        vector = { x: Math.round(x * 1000) / 1000, y: Math.round(y * 1000) / 1000 };
+        knob.style.transform = `translate(${x * radius}px, ${y * radius}px)`;
+    };
+
+    base.addEventListener('pointerdown', (e) => {
+        e.preventDefault();
+        base.setPointerCapture(e.pointerId);
+        update(e);
+        send();
+        // Refresh well within the server's 500 ms timeout
+        timer = setInterval(send, 100);
+    });
+
+    base.addEventListener('pointermove', (e) => {
+        if (timer !== null) {
+            update(e);
+        }
+    });
+
+    const release = () => {
+        if (timer === null) {
+            return;
+        }
+        clearInterval(timer);
+        timer = null;
+        vector = { x: 0, y: 0 };
+        send();
+        knob.style.transform = '';
+    };
+    base.addEventListener('pointerup', release);
+    base.addEventListener('pointercancel', release);
+}
+
+// Initialize the select mode toggle on the touchpad
+function initSelectMode() {
+    const button = document.getElementById('btn-select');
+
+    // Keep the touchpad from treating the toggle as a tap or drag
+    ['pointerdown', 'touchstart', 'mousedown'].forEach(type => {
+        button.addEventListener(type, (e) => e.stopPropagation());
+    });
+    button.addEventListener('click', (e) => {
+        e.stopPropagation();
+        setSelectMode(!selectMode);
+    });
+}
+
+// Enter or leave select mode
+function setSelectMode(enabled) {
+    if (!sendMessage([enabled ? 'sd' : 'su'])) {
+        return;
+    }
+    selectMode = enabled;
+    document.getElementById('btn-select').classList.toggle('active', enabled);
+}
+
+// Initialize the "Request control" button shown while another device has control
+function initTakeover() {
+    const btnTakeover = document.getElementById('btn-takeover');
+
+    btnTakeover.addEventListener('click', () => {
+        if (sendMessage(['takeover'])) {
+            updateStatus(t('status_requested', 'Requested'), false);
+            showTakeoverButton(false);
+        }
+    });
+}
+
+// Show or hide the "Request control" button
+function showTakeoverButton(visible) {
+    document.getElementById('btn-takeover').classList.toggle('hidden', !visible);
+}
+
 // Load settings from localStorage
 function loadSettings() {
     const savedMoveFactor = localStorage.getItem('moveFactor');
@@ -348,4 +852,18 @@ function initSensitivityControls() {
         moveFactorValue.textContent = MOVE_FACTOR.toFixed(1) + 'x';
         saveSettings();
     });
+    // Remember the final value on the PC once the slider is released
+    moveFactorSlider.addEventListener('change', () => {
+        sendMessage(['profile', { sensitivity: MOVE_FACTOR }]);
+    });
+
+    document.getElementById('natural-scroll').addEventListener('change', (e) => {
+        NATURAL_SCROLL = e.target.checked;
+        sendMessage(['profile', { natural_scroll: NATURAL_SCROLL }]);
+    });
+
+    document.getElementById('layout-select').addEventListener('change', (e) => {
+        applyLayout(e.target.value);
+        sendMessage(['profile', { layout: e.target.value }]);
+    });
 }
@@ -0,0 +1,52 @@
+// Service worker: serves the web client from cache for fast startup and
+// refreshes the cache in the background, reloading open pages when it changed
+const CACHE = 'touchrelay-v1';
+const ASSETS = ['./', 'static/app.js', 'static/style.css', 'manifest.json', 'static/icon-192.png'];
+
+self.addEventListener('install', (event) => {
+    event.waitUntil(caches.open(CACHE).then(cache => cache.addAll(ASSETS)));
+    self.skipWaiting();
+});
+
+self.addEventListener('activate', (event) => {
+    event.waitUntil(
+        caches.keys()
+            .then(keys => Promise.all(keys.filter(key => key !== CACHE).map(key => caches.delete(key))))
+            .then(() => self.clients.claim())
+    );
+});
+
+// Tell open pages to reload after a cached file was replaced by a newer version
+async function notifyUpdated() {
+    const clients = await self.clients.matchAll({ type: 'window' });
+    clients.forEach(client => client.postMessage('assets-updated'));
+}
+
+self.addEventListener('fetch', (event) => {
+    const request = event.request;
+    const url = new URL(request.url);
+    const scope = new URL(self.registration.scope);
+    const cacheable = ASSETS.some(asset => new URL(asset, scope).pathname === url.pathname);
+
+    // Only the app shell is cached; the WebSocket, API and layouts always go to the server
+    if (request.method !== 'GET' || !cacheable) {
+        return;
+    }
+
+    event.respondWith(caches.open(CACHE).then(async cache => {
+        const cached = await cache.match(request);
+        const refresh = fetch(request).then(response => {
+            if (response.ok) {
+                const changed = cached && cached.headers.get('ETag') !== response.headers.get('ETag');
+                cache.put(request, response.clone()).then(() => changed && notifyUpdated());
+            }
+            return response;
+        });
+
+        if (cached) {
+            event.waitUntil(refresh.catch(() => {}));
+            return cached;
+        }
+        return refresh;
+    }));
+});